@@ -0,0 +1,14 @@
+-- Create proposed_plans table for draft planning runs awaiting user approval
+-- Draft runs collect create_subtask calls here instead of writing to subtasks
+CREATE TABLE IF NOT EXISTS proposed_plans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending', -- 'pending', 'approved', 'discarded'
+    items TEXT NOT NULL DEFAULT '[]', -- JSON array of proposed subtasks, in order
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (task_id) REFERENCES tasks (id) ON DELETE CASCADE
+);
+
+-- Index for looking up the pending proposal of a task
+CREATE INDEX IF NOT EXISTS idx_proposed_plans_task_status ON proposed_plans(task_id, status);
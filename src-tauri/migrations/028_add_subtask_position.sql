@@ -0,0 +1,7 @@
+-- Add position column to subtasks so plans can be explicitly ordered
+-- Existing subtasks keep their creation order
+ALTER TABLE subtasks ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+UPDATE subtasks SET position = id;
+
+CREATE INDEX IF NOT EXISTS idx_subtasks_task_position ON subtasks(task_id, position);
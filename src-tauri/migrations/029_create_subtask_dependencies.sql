@@ -0,0 +1,14 @@
+-- Create subtask_dependencies table so plans can express sequential workflows
+-- A row means subtask_id cannot start until depends_on_subtask_id is completed
+CREATE TABLE IF NOT EXISTS subtask_dependencies (
+    subtask_id INTEGER NOT NULL,
+    depends_on_subtask_id INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (subtask_id, depends_on_subtask_id),
+    FOREIGN KEY (subtask_id) REFERENCES subtasks (id) ON DELETE CASCADE,
+    FOREIGN KEY (depends_on_subtask_id) REFERENCES subtasks (id) ON DELETE CASCADE,
+    CHECK (subtask_id != depends_on_subtask_id)
+);
+
+-- Index for finding the subtasks that wait on a given subtask
+CREATE INDEX IF NOT EXISTS idx_subtask_dependencies_depends_on ON subtask_dependencies(depends_on_subtask_id);
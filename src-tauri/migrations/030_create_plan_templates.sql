@@ -0,0 +1,18 @@
+-- Create plan_templates table for user-defined subtask pipelines
+-- Templates are used as the planning fallback and for manual "apply template" actions
+CREATE TABLE IF NOT EXISTS plan_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    description TEXT,
+    space_id INTEGER, -- NULL = available in every space
+    task_type TEXT, -- NULL = applies to any task type
+    items TEXT NOT NULL DEFAULT '[]', -- JSON array of {title, description, agent_id, depends_on}
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (space_id) REFERENCES spaces (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_plan_templates_space_id ON plan_templates(space_id);
+
+-- Free-form task type (e.g. 'feature', 'bug', 'article') used to pick a template
+ALTER TABLE tasks ADD COLUMN task_type TEXT;
@@ -0,0 +1,23 @@
+-- Create planning_runs table recording every planning run for later inspection
+CREATE TABLE IF NOT EXISTS planning_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    mode TEXT NOT NULL DEFAULT 'standard', -- 'standard', 'draft', 'replan'
+    status TEXT NOT NULL DEFAULT 'running', -- 'running', 'succeeded', 'failed', 'cancelled', 'interrupted'
+    system_prompt TEXT,
+    transcript TEXT NOT NULL DEFAULT '[]', -- JSON array of messages, including tool calls and results
+    changes TEXT NOT NULL DEFAULT '[]', -- JSON array of subtask changes applied by the run
+    subtasks_created INTEGER NOT NULL DEFAULT 0,
+    used_fallback BOOLEAN NOT NULL DEFAULT FALSE,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    duration_ms INTEGER,
+    started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    finished_at DATETIME,
+    FOREIGN KEY (task_id) REFERENCES tasks (id) ON DELETE CASCADE
+);
+
+-- Index for listing the runs of a task, newest first
+CREATE INDEX IF NOT EXISTS idx_planning_runs_task_id ON planning_runs(task_id, started_at);
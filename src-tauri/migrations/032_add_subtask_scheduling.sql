@@ -0,0 +1,9 @@
+-- Add effort, deadline, priority and scheduling fields to subtasks
+-- so that plans can be turned into a day-by-day schedule
+ALTER TABLE subtasks ADD COLUMN estimated_minutes INTEGER;
+ALTER TABLE subtasks ADD COLUMN due_date TEXT; -- YYYY-MM-DD
+ALTER TABLE subtasks ADD COLUMN priority TEXT; -- 'low', 'medium', 'high'
+ALTER TABLE subtasks ADD COLUMN scheduled_date TEXT; -- YYYY-MM-DD
+
+-- Index for the Today page, which also shows tasks with a subtask scheduled that day
+CREATE INDEX IF NOT EXISTS idx_subtasks_scheduled_date ON subtasks(scheduled_date);
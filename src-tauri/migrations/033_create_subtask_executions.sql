@@ -0,0 +1,28 @@
+-- Track delegated execution of subtasks by their assigned agents
+-- 'idle', 'queued', 'running', 'review', 'completed', 'failed'
+ALTER TABLE subtasks ADD COLUMN execution_status TEXT NOT NULL DEFAULT 'idle';
+
+UPDATE subtasks SET execution_status = 'completed' WHERE completed = TRUE;
+
+CREATE TABLE IF NOT EXISTS subtask_executions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    subtask_id INTEGER NOT NULL,
+    task_id INTEGER NOT NULL,
+    agent_id INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'queued', -- 'queued', 'running', 'succeeded', 'failed', 'cancelled'
+    output_target TEXT NOT NULL DEFAULT 'notes', -- 'notes' (appended to task notes) or 'artifact' (kept here only)
+    require_review BOOLEAN NOT NULL DEFAULT TRUE,
+    continue_with_unblocked BOOLEAN NOT NULL DEFAULT FALSE,
+    output TEXT,
+    error TEXT,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    started_at DATETIME,
+    finished_at DATETIME,
+    FOREIGN KEY (subtask_id) REFERENCES subtasks (id) ON DELETE CASCADE,
+    FOREIGN KEY (task_id) REFERENCES tasks (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_subtask_executions_subtask_id ON subtask_executions(subtask_id);
+CREATE INDEX IF NOT EXISTS idx_subtask_executions_task_id ON subtask_executions(task_id, created_at);
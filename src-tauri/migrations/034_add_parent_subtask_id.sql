@@ -0,0 +1,5 @@
+-- Allow subtasks to nest under other subtasks of the same task
+-- NULL = top-level subtask; children are removed with their parent
+ALTER TABLE subtasks ADD COLUMN parent_subtask_id INTEGER REFERENCES subtasks (id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_subtasks_parent_subtask_id ON subtasks(parent_subtask_id);
@@ -0,0 +1,4 @@
+-- Loop state of a planning run (task, options, staged changes and counters), saved
+-- after every iteration so that a run cut off by an app restart can be resumed.
+-- Cleared once the run finishes, is cancelled or is discarded.
+ALTER TABLE planning_runs ADD COLUMN resume_state TEXT;
@@ -0,0 +1,24 @@
+-- Turn edit locks into leases: each lock names the specific holder (an agent run
+-- or UI session) and expires unless the holder renews it.
+-- locked_by keeps the kind of holder ('agent', 'user', ...) for display.
+CREATE TABLE IF NOT EXISTS agent_edit_locks_new (
+  task_id INTEGER PRIMARY KEY,
+  locked_by TEXT NOT NULL,
+  owner_id TEXT NOT NULL,
+  locked_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  renewed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  expires_at DATETIME NOT NULL,
+  original_content TEXT,
+  FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+);
+
+-- Existing locks get an owner nobody holds and the old 5 minute timeout
+INSERT INTO agent_edit_locks_new (task_id, locked_by, owner_id, locked_at, renewed_at, expires_at, original_content)
+SELECT task_id, locked_by, 'legacy-' || locked_by, locked_at, locked_at,
+       datetime(locked_at, '+5 minutes'), original_content
+FROM agent_edit_locks;
+
+DROP TABLE agent_edit_locks;
+ALTER TABLE agent_edit_locks_new RENAME TO agent_edit_locks;
+
+CREATE INDEX IF NOT EXISTS idx_agent_edit_locks_expires_at ON agent_edit_locks(expires_at);
@@ -0,0 +1,17 @@
+-- Leases on documents other than task notes (which keep agent_edit_locks), keyed
+-- by resource type and id:
+--   'space_context' -> spaces.id
+--   'setting'       -> settings.key
+--   'agent_prompt'  -> agents.id
+CREATE TABLE IF NOT EXISTS resource_locks (
+  resource_type TEXT NOT NULL,
+  resource_id TEXT NOT NULL,
+  locked_by TEXT NOT NULL,
+  owner_id TEXT NOT NULL,
+  locked_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  renewed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  expires_at DATETIME NOT NULL,
+  PRIMARY KEY (resource_type, resource_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_resource_locks_expires_at ON resource_locks(expires_at);
@@ -0,0 +1,24 @@
+-- Full snapshot of a document after each save, so earlier versions can be
+-- compared and restored.
+-- document_type names what document_id refers to ('task_notes' -> tasks.id).
+CREATE TABLE IF NOT EXISTS document_revisions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  document_type TEXT NOT NULL,
+  document_id INTEGER NOT NULL,
+  content TEXT NOT NULL,
+  author TEXT NOT NULL DEFAULT 'user', -- 'user', 'agent' or 'system'
+  agent_id INTEGER, -- set when an agent wrote the revision
+  restored_from INTEGER, -- revision this one restored, if any
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  FOREIGN KEY (agent_id) REFERENCES agents (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_revisions_document
+  ON document_revisions(document_type, document_id, id);
+
+-- Revisions of a task's notes go when the task does
+CREATE TRIGGER IF NOT EXISTS delete_task_note_revisions
+AFTER DELETE ON tasks
+BEGIN
+  DELETE FROM document_revisions WHERE document_type = 'task_notes' AND document_id = OLD.id;
+END;
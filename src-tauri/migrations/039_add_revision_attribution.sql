@@ -0,0 +1,10 @@
+-- Record which background task run made a revision and why, and keep
+-- revisions of space context ('space_context' -> spaces.id)
+ALTER TABLE document_revisions ADD COLUMN background_task_run_id INTEGER;
+ALTER TABLE document_revisions ADD COLUMN summary TEXT;
+
+CREATE TRIGGER IF NOT EXISTS delete_space_context_revisions
+AFTER DELETE ON spaces
+BEGIN
+  DELETE FROM document_revisions WHERE document_type = 'space_context' AND document_id = OLD.id;
+END;
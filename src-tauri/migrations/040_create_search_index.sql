@@ -0,0 +1,109 @@
+-- Full-text index over tasks, subtasks, task notes and space context, kept in
+-- sync by triggers on the source tables. Chat history lives in the frontend's
+-- local storage, so it is not indexed here.
+-- entity_type: 'task', 'subtask', 'task_notes', 'space'
+-- entity_id: tasks.id, subtasks.id, task_notes.task_id or spaces.id
+CREATE VIRTUAL TABLE IF NOT EXISTS search_index USING fts5(
+  entity_type UNINDEXED,
+  entity_id UNINDEXED,
+  task_id UNINDEXED,
+  updated_at UNINDEXED,
+  title,
+  body,
+  tokenize = 'porter unicode61'
+);
+
+-- Tasks
+CREATE TRIGGER IF NOT EXISTS search_index_task_insert AFTER INSERT ON tasks
+BEGIN
+  INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES ('task', NEW.id, NEW.id, NEW.updated_at, NEW.title, COALESCE(NEW.description, ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_index_task_update AFTER UPDATE ON tasks
+BEGIN
+  DELETE FROM search_index WHERE entity_type = 'task' AND entity_id = OLD.id;
+  INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES ('task', NEW.id, NEW.id, NEW.updated_at, NEW.title, COALESCE(NEW.description, ''));
+  -- Notes are listed under their task's title
+  UPDATE search_index SET title = NEW.title
+  WHERE entity_type = 'task_notes' AND entity_id = NEW.id AND title != NEW.title;
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_index_task_delete AFTER DELETE ON tasks
+BEGIN
+  DELETE FROM search_index WHERE task_id = OLD.id;
+END;
+
+-- Subtasks
+CREATE TRIGGER IF NOT EXISTS search_index_subtask_insert AFTER INSERT ON subtasks
+BEGIN
+  INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES ('subtask', NEW.id, NEW.task_id, NEW.updated_at, NEW.title, COALESCE(NEW.description, ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_index_subtask_update AFTER UPDATE ON subtasks
+BEGIN
+  DELETE FROM search_index WHERE entity_type = 'subtask' AND entity_id = OLD.id;
+  INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES ('subtask', NEW.id, NEW.task_id, NEW.updated_at, NEW.title, COALESCE(NEW.description, ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_index_subtask_delete AFTER DELETE ON subtasks
+BEGIN
+  DELETE FROM search_index WHERE entity_type = 'subtask' AND entity_id = OLD.id;
+END;
+
+-- Task notes
+CREATE TRIGGER IF NOT EXISTS search_index_notes_insert AFTER INSERT ON task_notes
+BEGIN
+  INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+  SELECT 'task_notes', NEW.task_id, NEW.task_id, NEW.updated_at, title, COALESCE(NEW.content, '')
+  FROM tasks WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_index_notes_update AFTER UPDATE ON task_notes
+BEGIN
+  DELETE FROM search_index WHERE entity_type = 'task_notes' AND entity_id = OLD.task_id;
+  INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+  SELECT 'task_notes', NEW.task_id, NEW.task_id, NEW.updated_at, title, COALESCE(NEW.content, '')
+  FROM tasks WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_index_notes_delete AFTER DELETE ON task_notes
+BEGIN
+  DELETE FROM search_index WHERE entity_type = 'task_notes' AND entity_id = OLD.task_id;
+END;
+
+-- Space context
+CREATE TRIGGER IF NOT EXISTS search_index_space_insert AFTER INSERT ON spaces
+BEGIN
+  INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES ('space', NEW.id, NULL, NEW.updated_at, NEW.title, COALESCE(NEW.context_markdown, ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_index_space_update AFTER UPDATE ON spaces
+BEGIN
+  DELETE FROM search_index WHERE entity_type = 'space' AND entity_id = OLD.id;
+  INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES ('space', NEW.id, NULL, NEW.updated_at, NEW.title, COALESCE(NEW.context_markdown, ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_index_space_delete AFTER DELETE ON spaces
+BEGIN
+  DELETE FROM search_index WHERE entity_type = 'space' AND entity_id = OLD.id;
+END;
+
+-- Index what already exists
+INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+SELECT 'task', id, id, updated_at, title, COALESCE(description, '') FROM tasks;
+
+INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+SELECT 'subtask', id, task_id, updated_at, title, COALESCE(description, '') FROM subtasks;
+
+INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+SELECT 'task_notes', n.task_id, n.task_id, n.updated_at, t.title, COALESCE(n.content, '')
+FROM task_notes n JOIN tasks t ON t.id = n.task_id;
+
+INSERT INTO search_index (entity_type, entity_id, task_id, updated_at, title, body)
+SELECT 'space', id, NULL, updated_at, title, COALESCE(context_markdown, '') FROM spaces;
@@ -0,0 +1,22 @@
+-- Chunked copies of longer documents for retrieval into agent prompts, ranked
+-- with BM25. Unlike search_index this is rebuilt from Rust (chunking needs
+-- Markdown parsing), so retrieval_sources records what each source looked like
+-- when it was last chunked.
+-- source_type: 'task_notes' (tasks.id), 'space_context' (spaces.id),
+-- 'subtask_output' (subtask_executions.id)
+CREATE TABLE IF NOT EXISTS retrieval_sources (
+  source_type TEXT NOT NULL,
+  source_id INTEGER NOT NULL,
+  source_version TEXT, -- changes whenever the source content does
+  indexed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (source_type, source_id)
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS retrieval_chunks USING fts5(
+  source_type UNINDEXED,
+  source_id UNINDEXED,
+  chunk_index UNINDEXED,
+  heading,
+  content,
+  tokenize = 'porter unicode61'
+);
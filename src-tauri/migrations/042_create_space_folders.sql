@@ -0,0 +1,45 @@
+-- A space bound to a folder of Markdown files, one per task, kept in sync both
+-- ways
+CREATE TABLE IF NOT EXISTS space_folders (
+  space_id INTEGER PRIMARY KEY,
+  folder_path TEXT NOT NULL,
+  enabled BOOLEAN NOT NULL DEFAULT TRUE,
+  last_synced_at DATETIME,
+  last_error TEXT,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  FOREIGN KEY (space_id) REFERENCES spaces (id) ON DELETE CASCADE
+);
+
+-- What each synced file held the last time the file and the database agreed.
+-- Kept after the task is deleted so the deletion can reach the file.
+-- kind: 'task' (the task file) or 'notes' (its companion notes file)
+CREATE TABLE IF NOT EXISTS space_folder_files (
+  space_id INTEGER NOT NULL,
+  task_id INTEGER NOT NULL,
+  kind TEXT NOT NULL,
+  relative_path TEXT NOT NULL,
+  synced_content TEXT NOT NULL,
+  synced_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (task_id, kind),
+  FOREIGN KEY (space_id) REFERENCES space_folders (space_id) ON DELETE CASCADE
+);
+
+-- Files changed on disk and in the database since the last sync in ways that
+-- could not be merged. Nothing is written for them until they are resolved.
+CREATE TABLE IF NOT EXISTS space_folder_conflicts (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  space_id INTEGER NOT NULL,
+  task_id INTEGER NOT NULL,
+  kind TEXT NOT NULL,
+  relative_path TEXT NOT NULL,
+  base_content TEXT, -- last synced content, if any
+  file_content TEXT, -- NULL when the file was deleted
+  database_content TEXT, -- NULL when the task was deleted
+  detected_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  resolved_at DATETIME,
+  resolution TEXT, -- 'file', 'database' or 'in_sync' (both sides came to match)
+  FOREIGN KEY (space_id) REFERENCES space_folders (space_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_space_folder_conflicts_open
+  ON space_folder_conflicts(space_id, resolved_at);
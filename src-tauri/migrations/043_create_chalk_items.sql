@@ -0,0 +1,20 @@
+-- Items of a repo's .chalk/tasks folder imported into (or exported from) a
+-- space, so ids and fields Orcas has no column for survive a round trip.
+-- entity_type: 'task' (tasks.id) or 'subtask' (subtasks.id)
+CREATE TABLE IF NOT EXISTS chalk_items (
+  space_id INTEGER NOT NULL,
+  chalk_id TEXT NOT NULL,
+  entity_type TEXT NOT NULL,
+  entity_id INTEGER NOT NULL,
+  item_type TEXT NOT NULL, -- 'epic', 'feature', 'task' or 'bug'
+  status TEXT, -- status as written in the file, e.g. 'blocked'
+  priority TEXT, -- priority as written in the file, e.g. 'p1'
+  file_name TEXT NOT NULL,
+  unmapped_blocked_by TEXT NOT NULL DEFAULT '[]', -- JSON array of blocked_by ids with no subtask dependency
+  extra_fields TEXT NOT NULL DEFAULT '[]', -- JSON array of [key, value] for other frontmatter
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (space_id, chalk_id),
+  FOREIGN KEY (space_id) REFERENCES spaces (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_chalk_items_entity ON chalk_items(entity_type, entity_id);
@@ -0,0 +1,21 @@
+-- Files attached to a task by the user or saved by agents. Content lives in
+-- the app data directory under attachments/, named by its SHA-256, so the
+-- same bytes attached twice are stored once.
+CREATE TABLE IF NOT EXISTS task_attachments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL,
+    file_name TEXT NOT NULL,
+    mime_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    author TEXT NOT NULL DEFAULT 'user', -- 'user' or 'agent'
+    agent_id INTEGER,
+    subtask_execution_id INTEGER, -- execution whose output this is, if any
+    description TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (task_id) REFERENCES tasks (id) ON DELETE CASCADE,
+    FOREIGN KEY (subtask_execution_id) REFERENCES subtask_executions (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_attachments_task_id ON task_attachments(task_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_task_attachments_sha256 ON task_attachments(sha256);
@@ -0,0 +1,3 @@
+-- Frontmatter keys of a task file that Orcas has no field for (tags, aliases
+-- and the like), kept so writing the file back does not drop them
+ALTER TABLE space_folder_files ADD COLUMN extra_fields TEXT NOT NULL DEFAULT '[]'; -- JSON array of [key, value]
@@ -0,0 +1,4 @@
+-- File content as last imported or exported, so an export can tell whether
+-- the file was changed since and must not be overwritten. NULL for items
+-- synced before this column existed.
+ALTER TABLE chalk_items ADD COLUMN synced_content TEXT;
@@ -0,0 +1,113 @@
+-- Key search_index rows by rowid so the triggers find an entity's row with a
+-- rowid lookup instead of scanning the UNINDEXED entity columns. Each entity
+-- has a fixed rowid: id * 4 + 0 for tasks, + 1 for subtasks, + 2 for task
+-- notes (task_notes.task_id) and + 3 for spaces.
+DROP TRIGGER IF EXISTS search_index_task_insert;
+DROP TRIGGER IF EXISTS search_index_task_update;
+DROP TRIGGER IF EXISTS search_index_task_delete;
+DROP TRIGGER IF EXISTS search_index_subtask_insert;
+DROP TRIGGER IF EXISTS search_index_subtask_update;
+DROP TRIGGER IF EXISTS search_index_subtask_delete;
+DROP TRIGGER IF EXISTS search_index_notes_insert;
+DROP TRIGGER IF EXISTS search_index_notes_update;
+DROP TRIGGER IF EXISTS search_index_notes_delete;
+DROP TRIGGER IF EXISTS search_index_space_insert;
+DROP TRIGGER IF EXISTS search_index_space_update;
+DROP TRIGGER IF EXISTS search_index_space_delete;
+
+-- Tasks
+CREATE TRIGGER search_index_task_insert AFTER INSERT ON tasks
+BEGIN
+  INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES (NEW.id * 4, 'task', NEW.id, NEW.id, NEW.updated_at, NEW.title, COALESCE(NEW.description, ''));
+END;
+
+CREATE TRIGGER search_index_task_update AFTER UPDATE ON tasks
+BEGIN
+  DELETE FROM search_index WHERE rowid = OLD.id * 4;
+  INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES (NEW.id * 4, 'task', NEW.id, NEW.id, NEW.updated_at, NEW.title, COALESCE(NEW.description, ''));
+  -- Notes are listed under their task's title
+  UPDATE search_index SET title = NEW.title WHERE rowid = NEW.id * 4 + 2 AND title != NEW.title;
+END;
+
+-- Subtasks and notes have their own delete triggers, which cascades fire
+CREATE TRIGGER search_index_task_delete AFTER DELETE ON tasks
+BEGIN
+  DELETE FROM search_index WHERE rowid = OLD.id * 4;
+END;
+
+-- Subtasks
+CREATE TRIGGER search_index_subtask_insert AFTER INSERT ON subtasks
+BEGIN
+  INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES (NEW.id * 4 + 1, 'subtask', NEW.id, NEW.task_id, NEW.updated_at, NEW.title, COALESCE(NEW.description, ''));
+END;
+
+CREATE TRIGGER search_index_subtask_update AFTER UPDATE ON subtasks
+BEGIN
+  DELETE FROM search_index WHERE rowid = OLD.id * 4 + 1;
+  INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES (NEW.id * 4 + 1, 'subtask', NEW.id, NEW.task_id, NEW.updated_at, NEW.title, COALESCE(NEW.description, ''));
+END;
+
+CREATE TRIGGER search_index_subtask_delete AFTER DELETE ON subtasks
+BEGIN
+  DELETE FROM search_index WHERE rowid = OLD.id * 4 + 1;
+END;
+
+-- Task notes
+CREATE TRIGGER search_index_notes_insert AFTER INSERT ON task_notes
+BEGIN
+  INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+  SELECT NEW.task_id * 4 + 2, 'task_notes', NEW.task_id, NEW.task_id, NEW.updated_at, title, COALESCE(NEW.content, '')
+  FROM tasks WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER search_index_notes_update AFTER UPDATE ON task_notes
+BEGIN
+  DELETE FROM search_index WHERE rowid = OLD.task_id * 4 + 2;
+  INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+  SELECT NEW.task_id * 4 + 2, 'task_notes', NEW.task_id, NEW.task_id, NEW.updated_at, title, COALESCE(NEW.content, '')
+  FROM tasks WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER search_index_notes_delete AFTER DELETE ON task_notes
+BEGIN
+  DELETE FROM search_index WHERE rowid = OLD.task_id * 4 + 2;
+END;
+
+-- Space context
+CREATE TRIGGER search_index_space_insert AFTER INSERT ON spaces
+BEGIN
+  INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES (NEW.id * 4 + 3, 'space', NEW.id, NULL, NEW.updated_at, NEW.title, COALESCE(NEW.context_markdown, ''));
+END;
+
+CREATE TRIGGER search_index_space_update AFTER UPDATE ON spaces
+BEGIN
+  DELETE FROM search_index WHERE rowid = OLD.id * 4 + 3;
+  INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+  VALUES (NEW.id * 4 + 3, 'space', NEW.id, NULL, NEW.updated_at, NEW.title, COALESCE(NEW.context_markdown, ''));
+END;
+
+CREATE TRIGGER search_index_space_delete AFTER DELETE ON spaces
+BEGIN
+  DELETE FROM search_index WHERE rowid = OLD.id * 4 + 3;
+END;
+
+-- Re-index everything under the new rowids
+DELETE FROM search_index;
+
+INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+SELECT id * 4, 'task', id, id, updated_at, title, COALESCE(description, '') FROM tasks;
+
+INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+SELECT id * 4 + 1, 'subtask', id, task_id, updated_at, title, COALESCE(description, '') FROM subtasks;
+
+INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+SELECT n.task_id * 4 + 2, 'task_notes', n.task_id, n.task_id, n.updated_at, t.title, COALESCE(n.content, '')
+FROM task_notes n JOIN tasks t ON t.id = n.task_id;
+
+INSERT INTO search_index (rowid, entity_type, entity_id, task_id, updated_at, title, body)
+SELECT id * 4 + 3, 'space', id, NULL, updated_at, title, COALESCE(context_markdown, '') FROM spaces;
@@ -0,0 +1,7 @@
+-- retrieval_chunks rows are now keyed by rowid: each source owns a block of
+-- 65536 rowids starting at (source_id * 3 + type) * 65536, with type 0 for
+-- task notes, 1 for space context and 2 for subtask output. source_version is
+-- now a SHA-256 of the content. Drop the old index so refresh_index rebuilds
+-- every source that way.
+DELETE FROM retrieval_chunks;
+DELETE FROM retrieval_sources;
@@ -0,0 +1,29 @@
+use crate::resource_locks::{ensure_writable, AGENT_PROMPT};
+use crate::settings::get_db_pool;
+
+/// Replace the section of an agent's prompt that the agent maintains itself.
+/// Fails while another owner holds the agent's prompt lock.
+#[tauri::command]
+pub async fn update_agent_prompt_section(
+    agent_id: i64,
+    content: String,
+    owner_id: Option<String>,
+) -> Result<(), String> {
+    ensure_writable(AGENT_PROMPT, &agent_id.to_string(), owner_id.as_deref()).await?;
+    let pool = get_db_pool()?;
+
+    let result = sqlx::query(
+        "UPDATE agents SET updatable_prompt_section = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(&content)
+    .bind(agent_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update agent prompt: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,804 @@
+use crate::frontmatter::{self, Frontmatter, FrontmatterValue};
+use crate::settings::get_db_pool;
+use crate::subtask_dependencies::{add_dependencies, topological_sort};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Item types a .chalk task file can have
+const ITEM_TYPES: [&str; 4] = ["epic", "feature", "task", "bug"];
+
+/// Frontmatter keys mapped onto Orcas fields; any others are kept as they are
+const KNOWN_KEYS: [&str; 7] = ["id", "title", "type", "status", "priority", "blocked_by", "parent"];
+
+/// One file of a .chalk/tasks folder
+struct ChalkItem {
+    id: String,
+    title: String,
+    item_type: String,
+    status: Option<String>,
+    priority: Option<String>,
+    blocked_by: Vec<String>,
+    parent: Option<String>,
+    body: String,
+    file_name: String,
+    extra: Vec<(String, FrontmatterValue)>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ChalkLink {
+    chalk_id: String,
+    entity_type: String, // 'task', 'subtask'
+    entity_id: i64,
+    item_type: String,
+    status: Option<String>,
+    priority: Option<String>,
+    file_name: String,
+    unmapped_blocked_by: String,
+    extra_fields: String,
+    synced_content: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct TaskRow {
+    id: i64,
+    title: String,
+    description: Option<String>,
+    status: String,
+    priority: Option<String>,
+    task_type: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SubtaskRow {
+    id: i64,
+    task_id: i64,
+    parent_subtask_id: Option<i64>,
+    title: String,
+    description: Option<String>,
+    completed: bool,
+    priority: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ChalkImportReport {
+    pub tasks_created: usize,
+    pub tasks_updated: usize,
+    pub subtasks_created: usize,
+    pub subtasks_updated: usize,
+    /// Subtask dependencies set from blocked_by
+    pub dependencies: usize,
+    /// Files skipped and references that could not be mapped
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ChalkExportReport {
+    /// Files written because they were new or changed
+    pub written: Vec<String>,
+    pub unchanged: usize,
+    /// Files left as they are because they changed since they were last
+    /// imported or exported; import them first to keep those changes
+    pub conflicts: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// The .chalk/tasks folder of a repo. The folder itself may be given too.
+fn tasks_dir(repo_path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(repo_path.trim());
+    if !path.is_absolute() {
+        return Err(format!("Repository path must be absolute (got '{}')", repo_path));
+    }
+    if path.ends_with(Path::new(".chalk").join("tasks")) {
+        Ok(path)
+    } else {
+        Ok(path.join(".chalk").join("tasks"))
+    }
+}
+
+fn normalize(value: &str) -> String {
+    value.trim().to_lowercase().replace(['-', ' '], "_")
+}
+
+/// Orcas task status for a chalk status
+fn task_status(status: Option<&str>) -> &'static str {
+    match normalize(status.unwrap_or_default()).as_str() {
+        "done" | "closed" | "complete" | "completed" | "resolved" | "cancelled" | "canceled" | "wontfix" => "done",
+        "in_progress" | "doing" | "active" | "started" | "wip" => "in_progress",
+        "review" | "in_review" | "for_review" => "for_review",
+        _ => "todo",
+    }
+}
+
+/// Orcas priority for a chalk priority
+fn priority_level(priority: Option<&str>) -> &'static str {
+    match normalize(priority.unwrap_or_default()).as_str() {
+        "critical" | "urgent" | "highest" | "high" | "p0" | "p1" | "0" | "1" => "high",
+        "low" | "lowest" | "trivial" | "p3" | "p4" | "3" | "4" => "low",
+        _ => "medium",
+    }
+}
+
+/// The chalk value to write: the one last read from the file while it still
+/// means the same thing in Orcas, otherwise the Orcas value
+fn preserved(stored: Option<&str>, current: &str, map: fn(Option<&str>) -> &'static str) -> String {
+    match stored {
+        Some(stored) if map(Some(stored)) == current => stored.to_string(),
+        _ => current.to_string(),
+    }
+}
+
+fn parse_item(file_name: &str, content: &str) -> Result<ChalkItem, String> {
+    let (frontmatter, body) = frontmatter::parse(content)?;
+    let stem = file_name.trim_end_matches(".md");
+    let id = frontmatter.text("id").unwrap_or(stem).to_string();
+
+    let mut body = body.trim().to_string();
+    let title = match frontmatter.text("title") {
+        Some(title) => title.to_string(),
+        None => match body.strip_prefix("# ") {
+            Some(rest) => {
+                let (title, rest) = rest.split_once('\n').unwrap_or((rest, ""));
+                let title = title.trim().to_string();
+                body = rest.trim().to_string();
+                title
+            }
+            None => id.clone(),
+        },
+    };
+
+    let item_type = frontmatter.text("type").map(normalize).unwrap_or_else(|| "task".to_string());
+    if !ITEM_TYPES.contains(&item_type.as_str()) {
+        return Err(format!("type must be one of {} (got '{}')", ITEM_TYPES.join(", "), item_type));
+    }
+
+    Ok(ChalkItem {
+        id,
+        title,
+        item_type,
+        status: frontmatter.text("status").map(str::to_string),
+        priority: frontmatter.text("priority").map(str::to_string),
+        blocked_by: frontmatter.list("blocked_by"),
+        parent: frontmatter.text("parent").map(str::to_string),
+        body,
+        file_name: file_name.to_string(),
+        extra: frontmatter
+            .fields
+            .into_iter()
+            .filter(|(key, _)| !KNOWN_KEYS.contains(&key.as_str()))
+            .collect(),
+    })
+}
+
+fn render_item(item: &ChalkItem) -> String {
+    let mut frontmatter = Frontmatter::default();
+    frontmatter.set_text("id", Some(&item.id));
+    frontmatter.set_text("title", Some(&item.title));
+    frontmatter.set_text("type", Some(&item.item_type));
+    frontmatter.set_text("status", item.status.as_deref());
+    frontmatter.set_text("priority", item.priority.as_deref());
+    frontmatter.set("blocked_by", FrontmatterValue::List(item.blocked_by.clone()));
+    frontmatter.set_text("parent", item.parent.as_deref());
+    for (key, value) in &item.extra {
+        frontmatter.set(key, value.clone());
+    }
+    let body = item.body.trim();
+    if body.is_empty() {
+        frontmatter::render(&frontmatter, "")
+    } else {
+        frontmatter::render(&frontmatter, &format!("{}\n", body))
+    }
+}
+
+/// Items of a folder with the content each was read from
+fn read_items(dir: &Path, warnings: &mut Vec<String>) -> Result<Vec<(ChalkItem, String)>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut file_names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".md"))
+        .collect();
+    file_names.sort();
+
+    let mut items: Vec<(ChalkItem, String)> = Vec::new();
+    for file_name in file_names {
+        let content = std::fs::read_to_string(dir.join(&file_name))
+            .map_err(|e| format!("Failed to read {}: {}", file_name, e))?;
+        match parse_item(&file_name, &content) {
+            Ok(item) if items.iter().any(|(other, _)| other.id == item.id) => {
+                warnings.push(format!("{}: id '{}' is used by another file; skipped", file_name, item.id));
+            }
+            Ok(item) => items.push((item, content)),
+            Err(e) => warnings.push(format!("{}: {}; skipped", file_name, e)),
+        }
+    }
+    Ok(items)
+}
+
+/// The top-level item an item belongs under, or None if it is top-level itself
+fn root_of(item: &ChalkItem, by_id: &HashMap<&str, &ChalkItem>) -> Result<Option<String>, String> {
+    let mut seen = HashSet::from([item.id.as_str()]);
+    let mut current = item;
+    while let Some(parent) = current.parent.as_deref().and_then(|p| by_id.get(p)) {
+        if !seen.insert(parent.id.as_str()) {
+            return Err(format!("parent chain of '{}' loops back on itself", item.id));
+        }
+        current = parent;
+    }
+    Ok((current.id != item.id).then(|| current.id.clone()))
+}
+
+async fn load_links(space_id: i64) -> Result<HashMap<String, ChalkLink>, String> {
+    let pool = get_db_pool()?;
+    Ok(sqlx::query_as::<_, ChalkLink>(
+        "SELECT chalk_id, entity_type, entity_id, item_type, status, priority, file_name,
+                unmapped_blocked_by, extra_fields, synced_content
+         FROM chalk_items WHERE space_id = ?",
+    )
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load chalk items: {}", e))?
+    .into_iter()
+    .map(|link| (link.chalk_id.clone(), link))
+    .collect())
+}
+
+async fn save_link(
+    conn: &mut sqlx::SqliteConnection,
+    space_id: i64,
+    item: &ChalkItem,
+    entity: (&str, i64),
+    unmapped_blocked_by: &[String],
+    synced_content: &str,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO chalk_items
+           (space_id, chalk_id, entity_type, entity_id, item_type, status, priority, file_name,
+            unmapped_blocked_by, extra_fields, synced_content, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT(space_id, chalk_id) DO UPDATE SET
+           entity_type = excluded.entity_type,
+           entity_id = excluded.entity_id,
+           item_type = excluded.item_type,
+           status = excluded.status,
+           priority = excluded.priority,
+           file_name = excluded.file_name,
+           unmapped_blocked_by = excluded.unmapped_blocked_by,
+           extra_fields = excluded.extra_fields,
+           synced_content = excluded.synced_content,
+           updated_at = excluded.updated_at",
+    )
+    .bind(space_id)
+    .bind(&item.id)
+    .bind(entity.0)
+    .bind(entity.1)
+    .bind(&item.item_type)
+    .bind(&item.status)
+    .bind(&item.priority)
+    .bind(&item.file_name)
+    .bind(serde_json::json!(unmapped_blocked_by).to_string())
+    .bind(frontmatter::fields_to_json(&item.extra))
+    .bind(synced_content)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to save chalk item '{}': {}", item.id, e))?;
+    Ok(())
+}
+
+/// Create or update the task for a top-level item. Returns the task id and
+/// whether it was created.
+async fn upsert_task(
+    conn: &mut sqlx::SqliteConnection,
+    space_id: i64,
+    item: &ChalkItem,
+    link: Option<&ChalkLink>,
+) -> Result<(i64, bool), String> {
+    let status = task_status(item.status.as_deref());
+    let priority = priority_level(item.priority.as_deref());
+    let description = (!item.body.is_empty()).then_some(item.body.as_str());
+
+    if let Some(link) = link.filter(|l| l.entity_type == "task") {
+        let updated = sqlx::query(
+            "UPDATE tasks SET title = ?, description = ?, status = ?, priority = ?, task_type = ?,
+                    updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? AND space_id = ?",
+        )
+        .bind(&item.title)
+        .bind(description)
+        .bind(status)
+        .bind(priority)
+        .bind(&item.item_type)
+        .bind(link.entity_id)
+        .bind(space_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to update task for '{}': {}", item.id, e))?;
+        if updated.rows_affected() > 0 {
+            return Ok((link.entity_id, false));
+        }
+    }
+
+    let id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO tasks (space_id, title, description, status, priority, task_type)
+         VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
+    )
+    .bind(space_id)
+    .bind(&item.title)
+    .bind(description)
+    .bind(status)
+    .bind(priority)
+    .bind(&item.item_type)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to create task for '{}': {}", item.id, e))?;
+    Ok((id, true))
+}
+
+/// Create or update the subtask for a child item. Returns the subtask id and
+/// whether it was created.
+async fn upsert_subtask(
+    conn: &mut sqlx::SqliteConnection,
+    item: &ChalkItem,
+    link: Option<&ChalkLink>,
+    placement: (i64, Option<i64>, i64),
+) -> Result<(i64, bool), String> {
+    let (task_id, parent_subtask_id, position) = placement;
+    let completed = task_status(item.status.as_deref()) == "done";
+    let priority = priority_level(item.priority.as_deref());
+    let description = (!item.body.is_empty()).then_some(item.body.as_str());
+
+    if let Some(link) = link.filter(|l| l.entity_type == "subtask") {
+        let current_task: Option<i64> = sqlx::query_scalar("SELECT task_id FROM subtasks WHERE id = ?")
+            .bind(link.entity_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if let Some(current_task) = current_task {
+            // Dependencies only hold between subtasks of one task
+            if current_task != task_id {
+                sqlx::query("DELETE FROM subtask_dependencies WHERE subtask_id = ? OR depends_on_subtask_id = ?")
+                    .bind(link.entity_id)
+                    .bind(link.entity_id)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|e| format!("Failed to clear dependencies: {}", e))?;
+            }
+            sqlx::query(
+                "UPDATE subtasks SET task_id = ?, parent_subtask_id = ?, title = ?, description = ?,
+                        completed = ?, priority = ?, position = ?, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?",
+            )
+            .bind(task_id)
+            .bind(parent_subtask_id)
+            .bind(&item.title)
+            .bind(description)
+            .bind(completed)
+            .bind(priority)
+            .bind(position)
+            .bind(link.entity_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| format!("Failed to update subtask for '{}': {}", item.id, e))?;
+            return Ok((link.entity_id, false));
+        }
+    }
+
+    let id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO subtasks (task_id, parent_subtask_id, title, description, completed, priority, position)
+         VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING id",
+    )
+    .bind(task_id)
+    .bind(parent_subtask_id)
+    .bind(&item.title)
+    .bind(description)
+    .bind(completed)
+    .bind(priority)
+    .bind(position)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to create subtask for '{}': {}", item.id, e))?;
+    Ok((id, true))
+}
+
+/// Import a repo's .chalk/tasks files into a space. Top-level items (epics,
+/// and anything without a parent) become tasks, their descendants become
+/// subtasks, and `blocked_by` between subtasks of the same task becomes a
+/// dependency. Items imported before are updated in place.
+#[tauri::command]
+pub async fn import_chalk_tasks(space_id: i64, repo_path: String) -> Result<ChalkImportReport, String> {
+    let dir = tasks_dir(&repo_path)?;
+    let mut report = ChalkImportReport::default();
+    let read = read_items(&dir, &mut report.warnings)?;
+    let contents: HashMap<&str, &str> =
+        read.iter().map(|(item, content)| (item.id.as_str(), content.as_str())).collect();
+    let items: Vec<&ChalkItem> = read.iter().map(|(item, _)| item).collect();
+    let by_id: HashMap<&str, &ChalkItem> = items.iter().map(|item| (item.id.as_str(), *item)).collect();
+    let links = load_links(space_id).await?;
+
+    // Parents before children, so every child finds its parent's record
+    let mut roots: HashMap<&str, Option<String>> = HashMap::new();
+    for item in &items {
+        let root = root_of(item, &by_id).unwrap_or_else(|e| {
+            report.warnings.push(format!("{}: {}; imported as a task", item.file_name, e));
+            None
+        });
+        if root.is_none() {
+            if let Some(parent) = item.parent.as_deref().filter(|p| !by_id.contains_key(p)) {
+                report
+                    .warnings
+                    .push(format!("{}: parent '{}' not found; imported as a task", item.file_name, parent));
+            }
+        }
+        roots.insert(item.id.as_str(), root);
+    }
+    let depth = |item: &ChalkItem| {
+        let mut depth = 0;
+        if roots[item.id.as_str()].is_some() {
+            let mut current = item;
+            while let Some(parent) = current.parent.as_deref().and_then(|p| by_id.get(p)) {
+                depth += 1;
+                current = parent;
+            }
+        }
+        depth
+    };
+    let mut ordered = items;
+    ordered.sort_by_key(|item| depth(item));
+
+    let pool = get_db_pool()?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut task_ids: HashMap<&str, i64> = HashMap::new();
+    let mut subtask_ids: HashMap<&str, (i64, i64)> = HashMap::new(); // chalk id -> (task id, subtask id)
+    let mut positions: HashMap<i64, i64> = HashMap::new();
+    for item in &ordered {
+        let link = links.get(&item.id);
+        if link.is_some_and(|l| (l.entity_type == "task") != roots[item.id.as_str()].is_none()) {
+            report.warnings.push(format!(
+                "{}: moved between task and subtask; a new one was created and the old one left in place",
+                item.file_name
+            ));
+        }
+        match &roots[item.id.as_str()] {
+            None => {
+                let (task_id, created) = upsert_task(&mut tx, space_id, item, link).await?;
+                if created {
+                    report.tasks_created += 1;
+                } else {
+                    report.tasks_updated += 1;
+                }
+                task_ids.insert(item.id.as_str(), task_id);
+            }
+            Some(root) => {
+                let task_id = task_ids[root.as_str()];
+                let parent_subtask_id = item
+                    .parent
+                    .as_deref()
+                    .and_then(|p| subtask_ids.get(p))
+                    .map(|(_, id)| *id);
+                let position = positions.entry(task_id).or_insert(0);
+                *position += 1;
+                let (subtask_id, created) =
+                    upsert_subtask(&mut tx, item, link, (task_id, parent_subtask_id, *position)).await?;
+                if created {
+                    report.subtasks_created += 1;
+                } else {
+                    report.subtasks_updated += 1;
+                }
+                subtask_ids.insert(item.id.as_str(), (task_id, subtask_id));
+            }
+        }
+    }
+
+    // blocked_by between subtasks of one task becomes a dependency; anything
+    // else is kept on the item so it is written back on export
+    let mut edges_by_task: HashMap<i64, Vec<(i64, i64)>> = HashMap::new();
+    for item in &ordered {
+        let mut unmapped = Vec::new();
+        let own = subtask_ids.get(item.id.as_str());
+        for blocker in &item.blocked_by {
+            match (own, subtask_ids.get(blocker.as_str())) {
+                (Some((task_id, id)), Some((blocker_task, blocker_id))) if task_id == blocker_task && id != blocker_id => {
+                    edges_by_task.entry(*task_id).or_default().push((*id, *blocker_id));
+                }
+                _ => {
+                    if own.is_some() {
+                        report.warnings.push(format!(
+                            "{}: blocked_by '{}' is not a subtask of the same task; kept in the file only",
+                            item.file_name, blocker
+                        ));
+                    }
+                    unmapped.push(blocker.clone());
+                }
+            }
+        }
+        let entity = match own {
+            Some((_, subtask_id)) => ("subtask", *subtask_id),
+            None => ("task", task_ids[item.id.as_str()]),
+        };
+        save_link(&mut tx, space_id, item, entity, &unmapped, contents[item.id.as_str()]).await?;
+    }
+
+    let imported: HashSet<i64> = subtask_ids.values().map(|(_, id)| *id).collect();
+    let tasks: HashSet<i64> = subtask_ids.values().map(|(task_id, _)| *task_id).collect();
+    for task_id in tasks {
+        let new_edges = edges_by_task.remove(&task_id).unwrap_or_default();
+        let nodes: Vec<i64> = sqlx::query_scalar("SELECT id FROM subtasks WHERE task_id = ? ORDER BY position, id")
+            .bind(task_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        let kept: Vec<(i64, i64)> = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT d.subtask_id, d.depends_on_subtask_id FROM subtask_dependencies d
+             JOIN subtasks s ON s.id = d.subtask_id WHERE s.task_id = ?",
+        )
+        .bind(task_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .into_iter()
+        .filter(|(from, _)| !imported.contains(from))
+        .collect();
+
+        let all: Vec<(i64, i64)> = kept.iter().chain(new_edges.iter()).copied().collect();
+        if let Err(e) = topological_sort(&nodes, &all) {
+            report.warnings.push(format!("Task {}: blocked_by was not applied: {}", task_id, e));
+            continue;
+        }
+        for id in nodes.iter().filter(|id| imported.contains(id)) {
+            sqlx::query("DELETE FROM subtask_dependencies WHERE subtask_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to clear dependencies: {}", e))?;
+            let depends_on: Vec<i64> = new_edges.iter().filter(|(from, _)| from == id).map(|(_, to)| *to).collect();
+            add_dependencies(&mut tx, *id, &depends_on).await?;
+            report.dependencies += depends_on.len();
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit import: {}", e))?;
+    Ok(report)
+}
+
+/// Hands out ids for items that have none yet, continuing the numbering most
+/// existing ids use (e.g. CHK-041 after CHK-040)
+struct IdGenerator {
+    prefix: String,
+    next: u64,
+    width: usize,
+    taken: HashSet<String>,
+}
+
+impl IdGenerator {
+    fn new(existing: HashSet<String>) -> Self {
+        let pattern = Regex::new(r"^(.*?)(\d+)$").expect("valid regex");
+        let mut prefixes: HashMap<String, (usize, u64, usize)> = HashMap::new();
+        for id in &existing {
+            if let Some(captures) = pattern.captures(id) {
+                let entry = prefixes.entry(captures[1].to_string()).or_default();
+                entry.0 += 1;
+                entry.1 = entry.1.max(captures[2].parse().unwrap_or(0));
+                entry.2 = entry.2.max(captures[2].len());
+            }
+        }
+        let best = prefixes
+            .into_iter()
+            .max_by(|(a_prefix, a), (b_prefix, b)| a.0.cmp(&b.0).then_with(|| b_prefix.cmp(a_prefix)));
+        match best {
+            Some((prefix, (_, max, width))) => Self { prefix, next: max + 1, width, taken: existing },
+            None => Self { prefix: "orcas-".to_string(), next: 1, width: 0, taken: existing },
+        }
+    }
+
+    fn next_id(&mut self) -> String {
+        loop {
+            let id = format!("{}{:0width$}", self.prefix, self.next, width = self.width);
+            self.next += 1;
+            if self.taken.insert(id.clone()) {
+                return id;
+            }
+        }
+    }
+}
+
+fn file_name_for(chalk_id: &str) -> String {
+    let safe: String = chalk_id
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+        .collect();
+    format!("{}.md", safe)
+}
+
+/// Write a space's tasks and subtasks to a repo's .chalk/tasks folder. Items
+/// that came from the folder keep their ids, file names and any fields Orcas
+/// does not use; new ones get the next free id. Files changed since they were
+/// last imported or exported are left alone and reported as conflicts.
+#[tauri::command]
+pub async fn export_chalk_tasks(space_id: i64, repo_path: String) -> Result<ChalkExportReport, String> {
+    let dir = tasks_dir(&repo_path)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let pool = get_db_pool()?;
+    let mut report = ChalkExportReport::default();
+
+    let tasks = sqlx::query_as::<_, TaskRow>(
+        "SELECT id, title, description, status, priority, task_type FROM tasks WHERE space_id = ? ORDER BY id",
+    )
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load tasks: {}", e))?;
+    let subtasks = sqlx::query_as::<_, SubtaskRow>(
+        "SELECT s.id, s.task_id, s.parent_subtask_id, s.title, s.description, s.completed, s.priority
+         FROM subtasks s JOIN tasks t ON t.id = s.task_id
+         WHERE t.space_id = ?
+         ORDER BY s.task_id, s.position, s.id",
+    )
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load subtasks: {}", e))?;
+    let dependencies = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT d.subtask_id, d.depends_on_subtask_id FROM subtask_dependencies d
+         JOIN subtasks s ON s.id = d.subtask_id JOIN tasks t ON t.id = s.task_id
+         WHERE t.space_id = ?
+         ORDER BY d.subtask_id, d.depends_on_subtask_id",
+    )
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load dependencies: {}", e))?;
+
+    let links = load_links(space_id).await?;
+    let task_set: HashSet<i64> = tasks.iter().map(|t| t.id).collect();
+    let subtask_set: HashSet<i64> = subtasks.iter().map(|s| s.id).collect();
+    let mut by_entity: HashMap<(&str, i64), &ChalkLink> = HashMap::new();
+    for link in links.values() {
+        let exists = match link.entity_type.as_str() {
+            "task" => task_set.contains(&link.entity_id),
+            _ => subtask_set.contains(&link.entity_id),
+        };
+        if exists {
+            by_entity.insert((link.entity_type.as_str(), link.entity_id), link);
+        } else {
+            report.warnings.push(format!(
+                "{} was left in place; its {} no longer exists",
+                link.file_name, link.entity_type
+            ));
+        }
+    }
+
+    // Ids already in the folder are taken even if this space does not own them
+    let mut existing: HashSet<String> = links.keys().cloned().collect();
+    let mut ignored = Vec::new();
+    if let Ok(items) = read_items(&dir, &mut ignored) {
+        existing.extend(items.into_iter().map(|(item, _)| item.id));
+    }
+    let mut ids = IdGenerator::new(existing);
+    let mut chalk_ids: HashMap<(&str, i64), String> = HashMap::new();
+    for (entity_type, id) in tasks
+        .iter()
+        .map(|t| ("task", t.id))
+        .chain(subtasks.iter().map(|s| ("subtask", s.id)))
+    {
+        let chalk_id = match by_entity.get(&(entity_type, id)) {
+            Some(link) => link.chalk_id.clone(),
+            None => ids.next_id(),
+        };
+        chalk_ids.insert((entity_type, id), chalk_id);
+    }
+
+    let mut items: Vec<(ChalkItem, (&str, i64), Vec<String>)> = Vec::new();
+    for task in &tasks {
+        let link = by_entity.get(&("task", task.id)).copied();
+        let has_children = subtasks.iter().any(|s| s.task_id == task.id);
+        let item_type = match (link, task.task_type.as_deref().map(normalize)) {
+            (Some(link), _) => link.item_type.clone(),
+            (None, Some(task_type)) if ITEM_TYPES.contains(&task_type.as_str()) => task_type,
+            _ if has_children => "epic".to_string(),
+            _ => "task".to_string(),
+        };
+        let chalk_id = chalk_ids[&("task", task.id)].clone();
+        let unmapped: Vec<String> = link
+            .map(|l| serde_json::from_str(&l.unmapped_blocked_by).unwrap_or_default())
+            .unwrap_or_default();
+        items.push((
+            ChalkItem {
+                file_name: link.map_or_else(|| file_name_for(&chalk_id), |l| l.file_name.clone()),
+                id: chalk_id,
+                title: task.title.clone(),
+                item_type,
+                status: Some(preserved(link.and_then(|l| l.status.as_deref()), &task.status, task_status)),
+                priority: Some(preserved(
+                    link.and_then(|l| l.priority.as_deref()),
+                    task.priority.as_deref().unwrap_or("medium"),
+                    priority_level,
+                )),
+                blocked_by: unmapped.clone(),
+                parent: None,
+                body: task.description.clone().unwrap_or_default(),
+                extra: link.map(|l| frontmatter::fields_from_json(&l.extra_fields)).unwrap_or_default(),
+            },
+            ("task", task.id),
+            unmapped,
+        ));
+    }
+    for subtask in &subtasks {
+        let link = by_entity.get(&("subtask", subtask.id)).copied();
+        let chalk_id = chalk_ids[&("subtask", subtask.id)].clone();
+        let parent = match subtask.parent_subtask_id {
+            Some(parent) => chalk_ids.get(&("subtask", parent)).cloned(),
+            None => None,
+        }
+        .unwrap_or_else(|| chalk_ids[&("task", subtask.task_id)].clone());
+        let unmapped: Vec<String> = link
+            .map(|l| serde_json::from_str(&l.unmapped_blocked_by).unwrap_or_default())
+            .unwrap_or_default();
+        let mut blocked_by: Vec<String> = dependencies
+            .iter()
+            .filter(|(from, _)| *from == subtask.id)
+            .filter_map(|(_, to)| chalk_ids.get(&("subtask", *to)).cloned())
+            .collect();
+        blocked_by.extend(unmapped.iter().cloned());
+        let status = if subtask.completed { "done" } else { "todo" };
+        // Any open status read from the file (e.g. 'in_progress') still fits
+        let status = match link.and_then(|l| l.status.as_deref()) {
+            Some(stored) if (task_status(Some(stored)) == "done") == subtask.completed => stored.to_string(),
+            _ => status.to_string(),
+        };
+        items.push((
+            ChalkItem {
+                file_name: link.map_or_else(|| file_name_for(&chalk_id), |l| l.file_name.clone()),
+                id: chalk_id,
+                title: subtask.title.clone(),
+                item_type: link.map_or_else(|| "task".to_string(), |l| l.item_type.clone()),
+                status: Some(status),
+                priority: subtask
+                    .priority
+                    .as_deref()
+                    .map(|p| preserved(link.and_then(|l| l.priority.as_deref()), p, priority_level)),
+                blocked_by,
+                parent: Some(parent),
+                body: subtask.description.clone().unwrap_or_default(),
+                extra: link.map(|l| frontmatter::fields_from_json(&l.extra_fields)).unwrap_or_default(),
+            },
+            ("subtask", subtask.id),
+            unmapped,
+        ));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    for (item, entity, unmapped) in &items {
+        let content = render_item(item);
+        let path = dir.join(&item.file_name);
+        let synced = by_entity.get(entity).and_then(|link| link.synced_content.as_deref());
+        match std::fs::read_to_string(&path).ok() {
+            Some(current) if current == content => report.unchanged += 1,
+            // Changed since it was last synced, or not written by this space at all
+            Some(current) if synced != Some(current.as_str()) => {
+                report.conflicts.push(item.file_name.clone());
+                continue;
+            }
+            _ => {
+                std::fs::write(&path, &content).map_err(|e| format!("Failed to write {}: {}", item.file_name, e))?;
+                report.written.push(item.file_name.clone());
+            }
+        }
+        save_link(&mut tx, space_id, item, *entity, unmapped, &content).await?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit export: {}", e))?;
+    Ok(report)
+}
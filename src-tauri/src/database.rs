@@ -44,17 +44,29 @@ pub struct NewTask {
 }
 
 // Subtask-related structs
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
 pub struct SubTask {
     pub id: i64,
     pub task_id: i64,
     pub title: String,
     pub description: Option<String>,
     pub completed: bool,
+    pub agent_id: Option<i64>,
+    pub parent_subtask_id: Option<i64>,
+    pub position: i64,
+    pub estimated_minutes: Option<i64>,
+    pub due_date: Option<String>,
+    pub priority: Option<String>,
+    pub scheduled_date: Option<String>,
+    pub execution_status: String,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Column list matching `SubTask`, for `query_as` selects
+pub const SUBTASK_COLUMNS: &str = "id, task_id, title, description, completed, agent_id, parent_subtask_id, position,
+     estimated_minutes, due_date, priority, scheduled_date, execution_status, created_at, updated_at";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewSubTask {
     pub task_id: i64,
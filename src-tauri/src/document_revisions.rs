@@ -0,0 +1,303 @@
+use crate::edit_locks::ensure_notes_writable;
+use crate::resource_locks::{self, ensure_writable};
+use crate::settings::get_db_pool;
+use crate::text_diff::{unified_diff, DiffStats};
+use serde::{Deserialize, Serialize};
+
+/// Documents that keep a revision history, and what their id refers to
+pub const TASK_NOTES: &str = "task_notes"; // tasks.id
+pub const SPACE_CONTEXT: &str = "space_context"; // spaces.id
+
+const DOCUMENT_TYPES: [&str; 2] = [TASK_NOTES, SPACE_CONTEXT];
+
+/// Lines of unchanged context around each change in a revision diff
+const DIFF_CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct DocumentRevision {
+    pub id: i64,
+    pub document_type: String,
+    pub document_id: i64,
+    pub content: String,
+    pub author: String, // 'user', 'agent', 'system'
+    pub agent_id: Option<i64>,
+    pub background_task_run_id: Option<i64>,
+    pub summary: Option<String>,
+    pub restored_from: Option<i64>,
+    pub created_at: String,
+}
+
+/// A revision without its content, for listing history
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct RevisionSummary {
+    pub id: i64,
+    pub author: String,
+    pub agent_id: Option<i64>,
+    pub background_task_run_id: Option<i64>,
+    pub summary: Option<String>,
+    pub restored_from: Option<i64>,
+    pub length: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RevisionDiff {
+    pub from_revision_id: Option<i64>,
+    pub to_revision_id: i64,
+    /// Unified diff by line; empty when the revisions are identical
+    pub diff: String,
+    pub stats: DiffStats,
+}
+
+/// Who made a revision and why
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevisionAttribution {
+    #[serde(default = "default_author")]
+    pub author: String, // 'user', 'agent'
+    #[serde(default)]
+    pub agent_id: Option<i64>,
+    /// Background task run that made the change, if any
+    #[serde(default)]
+    pub background_task_run_id: Option<i64>,
+    /// Why the change was made
+    #[serde(default)]
+    pub summary: Option<String>,
+}
+
+fn default_author() -> String {
+    "user".to_string()
+}
+
+impl RevisionAttribution {
+    pub fn user() -> Self {
+        Self {
+            author: default_author(),
+            agent_id: None,
+            background_task_run_id: None,
+            summary: None,
+        }
+    }
+
+    pub fn agent(agent_id: Option<i64>) -> Self {
+        Self {
+            author: "agent".to_string(),
+            agent_id,
+            background_task_run_id: None,
+            summary: None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.author != "user" && self.author != "agent" {
+            return Err(format!("author must be 'user' or 'agent' (got '{}')", self.author));
+        }
+        Ok(())
+    }
+
+    /// Build from command arguments; the author defaults to the user
+    pub fn from_args(author: Option<String>, agent_id: Option<i64>) -> Result<Self, String> {
+        match author.as_deref().unwrap_or(if agent_id.is_some() { "agent" } else { "user" }) {
+            "user" => Ok(Self::user()),
+            "agent" => Ok(Self::agent(agent_id)),
+            other => Err(format!("author must be 'user' or 'agent' (got '{}')", other)),
+        }
+    }
+}
+
+fn validate_document_type(document_type: &str) -> Result<(), String> {
+    if !DOCUMENT_TYPES.contains(&document_type) {
+        return Err(format!(
+            "Unknown document type '{}' (expected one of: {})",
+            document_type,
+            DOCUMENT_TYPES.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Record a new revision of a document being saved as `content`, given what it
+/// held before. Saves that change nothing are not recorded. The first time a
+/// document with existing content is saved, that content is kept as a
+/// 'system' revision so it can still be restored.
+pub async fn record_revision(
+    conn: &mut sqlx::SqliteConnection,
+    document_type: &str,
+    document_id: i64,
+    previous: &str,
+    content: &str,
+    attribution: &RevisionAttribution,
+    restored_from: Option<i64>,
+) -> Result<(), String> {
+    if previous == content {
+        return Ok(());
+    }
+
+    let has_history: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM document_revisions WHERE document_type = ? AND document_id = ?)",
+    )
+    .bind(document_type)
+    .bind(document_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to check revision history: {}", e))?;
+
+    if !has_history && !previous.is_empty() {
+        sqlx::query(
+            "INSERT INTO document_revisions (document_type, document_id, content, author)
+             VALUES (?, ?, ?, 'system')",
+        )
+        .bind(document_type)
+        .bind(document_id)
+        .bind(previous)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to save revision: {}", e))?;
+    }
+
+    sqlx::query(
+        "INSERT INTO document_revisions
+           (document_type, document_id, content, author, agent_id, background_task_run_id, summary, restored_from)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(document_type)
+    .bind(document_id)
+    .bind(content)
+    .bind(&attribution.author)
+    .bind(attribution.agent_id)
+    .bind(attribution.background_task_run_id)
+    .bind(&attribution.summary)
+    .bind(restored_from)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to save revision: {}", e))?;
+
+    Ok(())
+}
+
+/// List a document's revisions, newest first
+#[tauri::command]
+pub async fn list_document_revisions(
+    document_type: String,
+    document_id: i64,
+) -> Result<Vec<RevisionSummary>, String> {
+    validate_document_type(&document_type)?;
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, RevisionSummary>(
+        "SELECT id, author, agent_id, background_task_run_id, summary, restored_from,
+                LENGTH(content) AS length, created_at
+         FROM document_revisions
+         WHERE document_type = ? AND document_id = ?
+         ORDER BY id DESC",
+    )
+    .bind(&document_type)
+    .bind(document_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list revisions: {}", e))
+}
+
+/// The revision saved just before `revision` for the same document
+async fn previous_revision_id(revision: &DocumentRevision) -> Result<Option<i64>, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_scalar(
+        "SELECT MAX(id) FROM document_revisions WHERE document_type = ? AND document_id = ? AND id < ?",
+    )
+    .bind(&revision.document_type)
+    .bind(revision.document_id)
+    .bind(revision.id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to find previous revision: {}", e))
+}
+
+/// Get one revision, including its content
+#[tauri::command]
+pub async fn get_document_revision(revision_id: i64) -> Result<DocumentRevision, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, DocumentRevision>(
+        "SELECT id, document_type, document_id, content, author, agent_id, background_task_run_id,
+                summary, restored_from, created_at
+         FROM document_revisions WHERE id = ?",
+    )
+    .bind(revision_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to get revision: {}", e))?
+    .ok_or_else(|| format!("Revision {} not found", revision_id))
+}
+
+/// Diff two revisions of the same document, from the older to the newer.
+/// Without `from_revision_id`, shows what `to_revision_id` itself changed.
+#[tauri::command]
+pub async fn diff_document_revisions(
+    from_revision_id: Option<i64>,
+    to_revision_id: i64,
+) -> Result<RevisionDiff, String> {
+    let to = get_document_revision(to_revision_id).await?;
+    let from_revision_id = match from_revision_id {
+        Some(id) => Some(id),
+        None => previous_revision_id(&to).await?,
+    };
+
+    // The first revision of a document is diffed against an empty one
+    let from_content = match from_revision_id {
+        Some(id) => {
+            let from = get_document_revision(id).await?;
+            if from.document_type != to.document_type || from.document_id != to.document_id {
+                return Err(format!(
+                    "Revisions {} and {} belong to different documents",
+                    id, to_revision_id
+                ));
+            }
+            from.content
+        }
+        None => String::new(),
+    };
+
+    let (diff, stats) = unified_diff(&from_content, &to.content, DIFF_CONTEXT_LINES);
+    Ok(RevisionDiff {
+        from_revision_id,
+        to_revision_id,
+        diff,
+        stats,
+    })
+}
+
+/// Put a document back to an earlier revision. The restore is saved as a new
+/// revision, so it can itself be undone.
+#[tauri::command]
+pub async fn restore_document_revision(
+    revision_id: i64,
+    attribution: Option<RevisionAttribution>,
+) -> Result<(), String> {
+    let revision = get_document_revision(revision_id).await?;
+    let attribution = attribution.unwrap_or_else(RevisionAttribution::user);
+    attribution.validate()?;
+
+    match revision.document_type.as_str() {
+        TASK_NOTES => {
+            ensure_notes_writable(revision.document_id, None).await?;
+            crate::task_notes::save_task_notes(
+                revision.document_id as i32,
+                &revision.content,
+                &attribution,
+                Some(revision_id),
+            )
+            .await
+        }
+        SPACE_CONTEXT => {
+            ensure_writable(resource_locks::SPACE_CONTEXT, &revision.document_id.to_string(), None).await?;
+            crate::space_context::save_space_context(
+                revision.document_id as i32,
+                &revision.content,
+                &attribution,
+                Some(revision_id),
+            )
+            .await
+        }
+        other => Err(format!("Cannot restore documents of type '{}'", other)),
+    }
+}
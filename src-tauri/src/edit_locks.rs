@@ -1,12 +1,25 @@
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
-use tauri::{AppHandle, Manager};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+use tokio::time::Instant;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Lease length when the caller does not ask for one
+const DEFAULT_LEASE_SECONDS: i64 = 120;
+/// Longest lease a caller can take without renewing
+const MAX_LEASE_SECONDS: i64 = 3600;
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
 pub struct EditLock {
     pub task_id: i64,
     pub locked_by: String,
+    pub owner_id: String,
     pub locked_at: String,
+    pub renewed_at: String,
+    pub expires_at: String,
     pub original_content: Option<String>,
 }
 
@@ -14,8 +27,77 @@ pub struct EditLock {
 pub struct LockStatus {
     pub is_locked: bool,
     pub locked_by: Option<String>,
+    pub owner_id: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+/// Payload of the `edit-lock-changed` event
+#[derive(Debug, Serialize, Clone)]
+pub struct EditLockChangedEvent {
+    pub task_id: i64,
+    pub locked: bool,
+    pub locked_by: Option<String>,
+    pub owner_id: Option<String>,
+    pub expires_at: Option<String>,
+    pub reason: String, // 'acquired', 'released', 'expired'
+}
+
+// Owners waiting for each task's lock, in arrival order
+static WAITERS: OnceLock<Mutex<HashMap<i64, VecDeque<String>>>> = OnceLock::new();
+// Woken whenever a lock is released or expires
+static LOCK_FREED: OnceLock<Notify> = OnceLock::new();
+
+fn waiters() -> &'static Mutex<HashMap<i64, VecDeque<String>>> {
+    WAITERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn lock_freed() -> &'static Notify {
+    LOCK_FREED.get_or_init(Notify::new)
+}
+
+/// First owner in line for a task's lock
+fn next_in_line(task_id: i64) -> Option<String> {
+    waiters()
+        .lock()
+        .ok()
+        .and_then(|queues| queues.get(&task_id).and_then(|queue| queue.front().cloned()))
+}
+
+fn leave_queue(task_id: i64, owner_id: &str) {
+    if let Ok(mut queues) = waiters().lock() {
+        if let Some(queue) = queues.get_mut(&task_id) {
+            queue.retain(|owner| owner != owner_id);
+            if queue.is_empty() {
+                queues.remove(&task_id);
+            }
+        }
+    }
+    // The next waiter may now be first in line
+    lock_freed().notify_waiters();
+}
+
+fn emit_lock_changed(app_handle: &AppHandle, event: EditLockChangedEvent) {
+    let _ = app_handle.emit("edit-lock-changed", event);
+}
+
+fn emit_unlocked(app_handle: &AppHandle, task_id: i64, reason: &str) {
+    emit_lock_changed(
+        app_handle,
+        EditLockChangedEvent {
+            task_id,
+            locked: false,
+            locked_by: None,
+            owner_id: None,
+            expires_at: None,
+            reason: reason.to_string(),
+        },
+    );
+    lock_freed().notify_waiters();
 }
 
+const LOCK_COLUMNS: &str =
+    "task_id, locked_by, owner_id, locked_at, renewed_at, expires_at, original_content";
+
 /// Get the database pool from app data directory
 async fn get_db_pool(app_handle: &AppHandle) -> Result<SqlitePool, String> {
     let app_data_dir = app_handle
@@ -31,66 +113,342 @@ async fn get_db_pool(app_handle: &AppHandle) -> Result<SqlitePool, String> {
         .map_err(|e| format!("Failed to connect to database: {}", e))
 }
 
-/// Acquire an edit lock for a task
+pub(crate) fn lease_seconds(ttl_seconds: Option<i64>) -> Result<i64, String> {
+    match ttl_seconds {
+        None => Ok(DEFAULT_LEASE_SECONDS),
+        Some(ttl) if (1..=MAX_LEASE_SECONDS).contains(&ttl) => Ok(ttl),
+        Some(ttl) => Err(format!(
+            "ttl_seconds must be between 1 and {} (got {})",
+            MAX_LEASE_SECONDS, ttl
+        )),
+    }
+}
+
+pub(crate) fn validate_owner(owner_id: &str) -> Result<(), String> {
+    if owner_id.trim().is_empty() {
+        return Err("owner_id cannot be empty".to_string());
+    }
+    Ok(())
+}
+
+/// The unexpired lock on a task, if any
+async fn active_lock(pool: &SqlitePool, task_id: i64) -> Result<Option<EditLock>, String> {
+    sqlx::query_as::<_, EditLock>(&format!(
+        "SELECT {} FROM agent_edit_locks WHERE task_id = ? AND expires_at > datetime('now')",
+        LOCK_COLUMNS
+    ))
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to check lock: {}", e))
+}
+
+/// Fail if someone other than `owner_id` holds an unexpired lock on a task's
+/// notes. Writers that do not hold the lock pass `None` and are only let
+/// through while the notes are unlocked.
+pub async fn ensure_notes_writable(task_id: i64, owner_id: Option<&str>) -> Result<(), String> {
+    let pool = crate::settings::get_db_pool()?;
+
+    match active_lock(pool, task_id).await? {
+        Some(lock) if Some(lock.owner_id.as_str()) != owner_id => Err(format!(
+            "Notes of task {} are being edited by another {} ({}) until {}",
+            task_id, lock.locked_by, lock.owner_id, lock.expires_at
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_lock_request(locked_by: &str, owner_id: &str, ttl_seconds: Option<i64>) -> Result<i64, String> {
+    // Validate locked_by parameter
+    if locked_by != "agent" && locked_by != "user" {
+        return Err("locked_by must be 'agent' or 'user'".to_string());
+    }
+    validate_owner(owner_id)?;
+    lease_seconds(ttl_seconds)
+}
+
+/// Take the lock if it is free or expired, or renew it if the caller holds it.
+/// Callers waiting in `acquire_edit_lock_wait` go first.
+async fn try_acquire(
+    pool: &SqlitePool,
+    task_id: i64,
+    locked_by: &str,
+    owner_id: &str,
+    ttl: i64,
+    original_content: &Option<String>,
+    app_handle: &AppHandle,
+) -> Result<bool, String> {
+    if next_in_line(task_id).is_some_and(|next| next != owner_id) {
+        let held_by_caller = active_lock(pool, task_id)
+            .await?
+            .is_some_and(|lock| lock.owner_id == owner_id);
+        if !held_by_caller {
+            return Ok(false);
+        }
+    }
+
+    // Re-acquiring keeps the original content, or sets it if the lock was
+    // taken without (e.g. after waiting, before the holder read the notes)
+    let result = sqlx::query(
+        "INSERT INTO agent_edit_locks (task_id, locked_by, owner_id, locked_at, renewed_at, expires_at, original_content)
+         VALUES (?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, datetime('now', '+' || ? || ' seconds'), ?)
+         ON CONFLICT(task_id) DO UPDATE SET
+           locked_by = excluded.locked_by,
+           locked_at = CASE WHEN owner_id = excluded.owner_id THEN locked_at ELSE excluded.locked_at END,
+           original_content = CASE WHEN owner_id = excluded.owner_id
+             THEN COALESCE(original_content, excluded.original_content) ELSE excluded.original_content END,
+           owner_id = excluded.owner_id,
+           renewed_at = excluded.renewed_at,
+           expires_at = excluded.expires_at
+         WHERE owner_id = excluded.owner_id OR expires_at <= datetime('now')",
+    )
+    .bind(task_id)
+    .bind(locked_by)
+    .bind(owner_id)
+    .bind(ttl)
+    .bind(original_content)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    if let Some(lock) = active_lock(pool, task_id).await? {
+        emit_lock_changed(
+            app_handle,
+            EditLockChangedEvent {
+                task_id,
+                locked: true,
+                locked_by: Some(lock.locked_by),
+                owner_id: Some(lock.owner_id),
+                expires_at: Some(lock.expires_at),
+                reason: "acquired".to_string(),
+            },
+        );
+    }
+    Ok(true)
+}
+
+/// Acquire an edit lock for a task as a lease held by `owner_id` for
+/// `ttl_seconds`. Succeeds when the task is unlocked, its lease has expired, or
+/// the caller already holds it (which renews the lease and records
+/// `original_content` if the lock was taken without it).
 #[tauri::command]
 pub async fn acquire_edit_lock(
     task_id: i64,
     locked_by: String,
+    owner_id: String,
+    ttl_seconds: Option<i64>,
     original_content: Option<String>,
     app_handle: AppHandle,
 ) -> Result<bool, String> {
+    let ttl = validate_lock_request(&locked_by, &owner_id, ttl_seconds)?;
     let pool = get_db_pool(&app_handle).await?;
 
-    // Validate locked_by parameter
-    if locked_by != "agent" && locked_by != "user" {
-        return Err("locked_by must be 'agent' or 'user'".to_string());
-    }
+    try_acquire(&pool, task_id, &locked_by, &owner_id, ttl, &original_content, &app_handle).await
+}
 
-    // Check if lock already exists
-    let existing_lock: Option<(i64,)> = sqlx::query_as(
-        "SELECT task_id FROM agent_edit_locks WHERE task_id = ?"
-    )
-    .bind(task_id)
-    .fetch_optional(&pool)
+/// Queue for a task's lock and resolve once it is acquired (true) or
+/// `timeout_ms` passes (false). Waiters get the lock in arrival order.
+#[tauri::command]
+pub async fn acquire_edit_lock_wait(
+    task_id: i64,
+    locked_by: String,
+    owner_id: String,
+    ttl_seconds: Option<i64>,
+    original_content: Option<String>,
+    timeout_ms: u64,
+    app_handle: AppHandle,
+) -> Result<bool, String> {
+    let ttl = validate_lock_request(&locked_by, &owner_id, ttl_seconds)?;
+    let pool = get_db_pool(&app_handle).await?;
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+
+    let pool = &pool;
+    let (locked_by, owner, original_content, app_handle) =
+        (&locked_by, &owner_id, &original_content, &app_handle);
+    wait_in_line(task_id, &owner_id, deadline, move |first_in_line| async move {
+        if first_in_line
+            && try_acquire(pool, task_id, locked_by, owner, ttl, original_content, app_handle).await?
+        {
+            return Ok(Turn::Acquired);
+        }
+        // Retry when the current lease runs out without anyone cleaning it up
+        let expires = active_lock(pool, task_id)
+            .await?
+            .and_then(|lock| seconds_until(&lock.expires_at))
+            .map(|secs| Instant::now() + Duration::from_secs(secs + 1));
+        Ok(Turn::Wait(expires))
+    })
     .await
-    .map_err(|e| format!("Failed to check existing lock: {}", e))?;
+}
+
+/// What a waiter found when it checked the lock
+enum Turn {
+    Acquired,
+    /// Not yet; check again when the lock is freed or by this time at the latest
+    Wait(Option<Instant>),
+}
+
+/// A place in a task's lock queue, given up when dropped so an aborted
+/// waiter does not block the queue
+struct QueueEntry {
+    task_id: i64,
+    owner_id: String,
+}
 
-    if existing_lock.is_some() {
-        return Ok(false); // Lock already exists
+impl QueueEntry {
+    fn join(task_id: i64, owner_id: &str) -> Result<Self, String> {
+        let mut queues = waiters()
+            .lock()
+            .map_err(|_| "Edit lock queue poisoned".to_string())?;
+        let queue = queues.entry(task_id).or_default();
+        if queue.iter().any(|owner| owner == owner_id) {
+            return Err(format!("{} is already waiting for the lock on task {}", owner_id, task_id));
+        }
+        queue.push_back(owner_id.to_string());
+        Ok(Self {
+            task_id,
+            owner_id: owner_id.to_string(),
+        })
     }
+}
 
-    // Insert new lock
-    sqlx::query(
-        "INSERT INTO agent_edit_locks (task_id, locked_by, original_content) VALUES (?, ?, ?)"
+impl Drop for QueueEntry {
+    fn drop(&mut self) {
+        leave_queue(self.task_id, &self.owner_id);
+    }
+}
+
+/// Queue behind earlier waiters and call `attempt` (with whether the caller is
+/// first in line) until it acquires the lock or `deadline` passes
+async fn wait_in_line<F, Fut>(
+    task_id: i64,
+    owner_id: &str,
+    deadline: Instant,
+    mut attempt: F,
+) -> Result<bool, String>
+where
+    F: FnMut(bool) -> Fut,
+    Fut: std::future::Future<Output = Result<Turn, String>>,
+{
+    let _entry = QueueEntry::join(task_id, owner_id)?;
+
+    loop {
+        // Register for wake-ups before checking, so a release in between is not missed
+        let freed = lock_freed().notified();
+        tokio::pin!(freed);
+        freed.as_mut().enable();
+
+        let first_in_line = next_in_line(task_id).as_deref() == Some(owner_id);
+        let wake_at = match attempt(first_in_line).await? {
+            Turn::Acquired => return Ok(true),
+            Turn::Wait(retry_at) => retry_at.map_or(deadline, |at| at.min(deadline)),
+        };
+        if Instant::now() >= deadline {
+            return Ok(false);
+        }
+        let _ = tokio::time::timeout_at(wake_at, freed).await;
+    }
+}
+
+/// Seconds from now until a SQLite `datetime` timestamp (UTC)
+fn seconds_until(timestamp: &str) -> Option<u64> {
+    let at = chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S").ok()?;
+    let remaining = at - chrono::Utc::now().naive_utc();
+    Some(remaining.num_seconds().max(0) as u64)
+}
+
+/// Extend the lease on a lock the caller holds
+#[tauri::command]
+pub async fn renew_edit_lock(
+    task_id: i64,
+    owner_id: String,
+    ttl_seconds: Option<i64>,
+    app_handle: AppHandle,
+) -> Result<EditLock, String> {
+    let pool = get_db_pool(&app_handle).await?;
+    let ttl = lease_seconds(ttl_seconds)?;
+
+    let result = sqlx::query(
+        "UPDATE agent_edit_locks SET renewed_at = CURRENT_TIMESTAMP,
+         expires_at = datetime('now', '+' || ? || ' seconds')
+         WHERE task_id = ? AND owner_id = ? AND expires_at > datetime('now')",
     )
+    .bind(ttl)
     .bind(task_id)
-    .bind(&locked_by)
-    .bind(&original_content)
+    .bind(&owner_id)
     .execute(&pool)
     .await
-    .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+    .map_err(|e| format!("Failed to renew lock: {}", e))?;
 
-    Ok(true)
+    if result.rows_affected() == 0 {
+        return Err(format!(
+            "No edit lock on task {} is held by {} (it may have expired)",
+            task_id, owner_id
+        ));
+    }
+
+    active_lock(&pool, task_id)
+        .await?
+        .ok_or_else(|| format!("Edit lock on task {} expired", task_id))
 }
 
-/// Release an edit lock for a task
+/// Release an edit lock for a task. Only the holder can release it; releasing
+/// a lock that is gone or expired is a no-op.
 #[tauri::command]
 pub async fn release_edit_lock(
+    task_id: i64,
+    owner_id: String,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let pool = get_db_pool(&app_handle).await?;
+
+    let result = sqlx::query("DELETE FROM agent_edit_locks WHERE task_id = ? AND owner_id = ?")
+        .bind(task_id)
+        .bind(&owner_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to release lock: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        if let Some(lock) = active_lock(&pool, task_id).await? {
+            return Err(format!(
+                "Edit lock on task {} is held by another {} ({})",
+                task_id, lock.locked_by, lock.owner_id
+            ));
+        }
+        return Ok(());
+    }
+
+    emit_unlocked(&app_handle, task_id, "released");
+    Ok(())
+}
+
+/// Release a task's lock whoever holds it, e.g. when the user takes over from
+/// an agent in the UI
+#[tauri::command]
+pub async fn force_release_edit_lock(
     task_id: i64,
     app_handle: AppHandle,
 ) -> Result<(), String> {
     let pool = get_db_pool(&app_handle).await?;
 
-    sqlx::query("DELETE FROM agent_edit_locks WHERE task_id = ?")
+    let result = sqlx::query("DELETE FROM agent_edit_locks WHERE task_id = ?")
         .bind(task_id)
         .execute(&pool)
         .await
         .map_err(|e| format!("Failed to release lock: {}", e))?;
 
+    if result.rows_affected() > 0 {
+        emit_unlocked(&app_handle, task_id, "released");
+    }
     Ok(())
 }
 
-/// Check if a task has an edit lock and who owns it
+/// Check if a task has an unexpired edit lock and who owns it
 #[tauri::command]
 pub async fn check_edit_lock(
     task_id: i64,
@@ -98,22 +456,18 @@ pub async fn check_edit_lock(
 ) -> Result<LockStatus, String> {
     let pool = get_db_pool(&app_handle).await?;
 
-    let lock: Option<(String,)> = sqlx::query_as(
-        "SELECT locked_by FROM agent_edit_locks WHERE task_id = ?"
-    )
-    .bind(task_id)
-    .fetch_optional(&pool)
-    .await
-    .map_err(|e| format!("Failed to check lock: {}", e))?;
-
-    match lock {
-        Some((locked_by,)) => Ok(LockStatus {
+    match active_lock(&pool, task_id).await? {
+        Some(lock) => Ok(LockStatus {
             is_locked: true,
-            locked_by: Some(locked_by),
+            locked_by: Some(lock.locked_by),
+            owner_id: Some(lock.owner_id),
+            expires_at: Some(lock.expires_at),
         }),
         None => Ok(LockStatus {
             is_locked: false,
             locked_by: None,
+            owner_id: None,
+            expires_at: None,
         }),
     }
 }
@@ -144,30 +498,82 @@ pub async fn force_release_all_locks(
 ) -> Result<i64, String> {
     let pool = get_db_pool(&app_handle).await?;
 
-    let result = sqlx::query("DELETE FROM agent_edit_locks")
-        .execute(&pool)
+    let released: Vec<i64> = sqlx::query_scalar("DELETE FROM agent_edit_locks RETURNING task_id")
+        .fetch_all(&pool)
         .await
         .map_err(|e| format!("Failed to release all locks: {}", e))?;
 
-    Ok(result.rows_affected() as i64)
+    for task_id in &released {
+        emit_unlocked(&app_handle, *task_id, "released");
+    }
+    Ok(released.len() as i64)
 }
 
-/// Clean up stale locks older than timeout_minutes
+/// Delete locks whose lease has run out without being renewed
 #[tauri::command]
 pub async fn cleanup_stale_locks(
-    timeout_minutes: i64,
     app_handle: AppHandle,
 ) -> Result<i64, String> {
     let pool = get_db_pool(&app_handle).await?;
 
-    let result = sqlx::query(
-        "DELETE FROM agent_edit_locks
-         WHERE datetime(locked_at, '+' || ? || ' minutes') < datetime('now')"
+    let expired: Vec<i64> = sqlx::query_scalar(
+        "DELETE FROM agent_edit_locks WHERE expires_at <= datetime('now') RETURNING task_id",
     )
-    .bind(timeout_minutes)
-    .execute(&pool)
+    .fetch_all(&pool)
     .await
     .map_err(|e| format!("Failed to cleanup stale locks: {}", e))?;
 
-    Ok(result.rows_affected() as i64)
+    for task_id in &expired {
+        emit_unlocked(&app_handle, *task_id, "expired");
+    }
+    Ok(expired.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_a_minute() -> Instant {
+        Instant::now() + Duration::from_secs(60)
+    }
+
+    #[tokio::test]
+    async fn dropping_a_waiter_frees_its_place_in_line() {
+        let task_id = -1;
+        // Never gets the lock, so it waits until aborted
+        let stuck = tokio::spawn(async move {
+            wait_in_line(task_id, "stuck", in_a_minute(), |_| async { Ok(Turn::Wait(None)) }).await
+        });
+        while next_in_line(task_id).as_deref() != Some("stuck") {
+            tokio::task::yield_now().await;
+        }
+
+        stuck.abort();
+        assert!(stuck.await.unwrap_err().is_cancelled());
+        assert_eq!(next_in_line(task_id), None);
+
+        let acquired = tokio::time::timeout(
+            Duration::from_secs(1),
+            wait_in_line(task_id, "next", in_a_minute(), |first_in_line| async move {
+                Ok(if first_in_line { Turn::Acquired } else { Turn::Wait(None) })
+            }),
+        )
+        .await
+        .expect("next waiter should not be blocked");
+        assert_eq!(acquired, Ok(true));
+        assert_eq!(next_in_line(task_id), None);
+    }
+
+    #[tokio::test]
+    async fn waiters_take_turns_in_arrival_order() {
+        let task_id = -2;
+        let _first = QueueEntry::join(task_id, "first").unwrap();
+        assert!(QueueEntry::join(task_id, "first").is_err());
+
+        let second = wait_in_line(task_id, "second", Instant::now(), |first_in_line| async move {
+            Ok(if first_in_line { Turn::Acquired } else { Turn::Wait(None) })
+        });
+        assert_eq!(second.await, Ok(false));
+        assert_eq!(next_in_line(task_id).as_deref(), Some("first"));
+    }
 }
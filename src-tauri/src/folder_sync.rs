@@ -0,0 +1,1310 @@
+use crate::document_revisions::RevisionAttribution;
+use crate::frontmatter::{self, Frontmatter, FrontmatterValue};
+use crate::markdown_patch::headings;
+use crate::settings::get_db_pool;
+use crate::text_merge::{three_way_merge, MergeGranularity};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter};
+
+/// How often bound folders and their spaces are compared
+const WATCH_INTERVAL_SECS: u64 = 5;
+
+const TASK_FILE: &str = "task";
+const NOTES_FILE: &str = "notes";
+const NOTES_SUFFIX: &str = ".notes.md";
+const SUBTASKS_HEADING: &str = "Subtasks";
+/// Frontmatter keys written from task fields; any others are carried through
+const TASK_KEYS: [&str; 6] = ["id", "title", "status", "priority", "due", "scheduled"];
+
+const STATUSES: [&str; 4] = ["todo", "in_progress", "for_review", "done"];
+const PRIORITIES: [&str; 3] = ["low", "medium", "high"];
+
+// Only one sync runs at a time, whether from the watcher or a command
+static SYNC_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct SpaceFolder {
+    pub space_id: i64,
+    pub folder_path: String,
+    pub enabled: bool,
+    pub last_synced_at: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct FolderConflict {
+    pub id: i64,
+    pub space_id: i64,
+    pub task_id: i64,
+    pub kind: String, // 'task', 'notes'
+    pub relative_path: String,
+    pub base_content: Option<String>,
+    /// None when the file was deleted
+    pub file_content: Option<String>,
+    /// None when the task was deleted
+    pub database_content: Option<String>,
+    pub detected_at: String,
+}
+
+/// What one sync of a space did
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SyncReport {
+    pub space_id: i64,
+    /// Files written from the database
+    pub exported: usize,
+    /// Database records updated from files
+    pub imported: usize,
+    /// Tasks created from new files
+    pub created: usize,
+    /// Files removed because their task was deleted, or notes cleared because
+    /// their file was deleted. Deleted task files become conflicts instead.
+    pub deleted: usize,
+    /// Files changed on both sides and merged without conflict
+    pub merged: usize,
+    /// Open conflicts after the sync
+    pub conflicts: usize,
+    /// Files that could not be synced, e.g. invalid frontmatter
+    pub errors: Vec<String>,
+}
+
+impl SyncReport {
+    fn changed(&self) -> bool {
+        self.exported + self.imported + self.created + self.deleted + self.merged > 0
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct TaskRow {
+    id: i64,
+    title: String,
+    description: Option<String>,
+    status: String,
+    priority: Option<String>,
+    due_date: Option<String>,
+    scheduled_date: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SubtaskRow {
+    id: i64,
+    task_id: i64,
+    parent_subtask_id: Option<i64>,
+    title: String,
+    completed: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct SyncedFile {
+    task_id: i64,
+    kind: String,
+    relative_path: String,
+    synced_content: String,
+}
+
+struct ParsedSubtask {
+    id: Option<i64>,
+    depth: usize,
+    title: String,
+    completed: bool,
+}
+
+/// A task file read back into task fields
+struct ParsedTask {
+    title: String,
+    description: Option<String>,
+    status: String,
+    priority: String,
+    due_date: Option<String>,
+    scheduled_date: Option<String>,
+    subtasks: Vec<ParsedSubtask>,
+    extra: Vec<(String, FrontmatterValue)>,
+}
+
+/// What to do with one file given its last synced content (`base`), what is
+/// on disk and what the database would write. `None` means missing.
+#[derive(Debug, PartialEq)]
+enum Step {
+    InSync,
+    Forget,
+    WriteFile(String),
+    DeleteFile,
+    WriteDatabase(String),
+    DeleteFromDatabase,
+    Merge(String),
+    Conflict,
+}
+
+fn reconcile(base: Option<&str>, file: Option<&str>, database: Option<&str>) -> Step {
+    if file == database {
+        return if file.is_some() { Step::InSync } else { Step::Forget };
+    }
+    if file == base {
+        return database.map_or(Step::DeleteFile, |d| Step::WriteFile(d.to_string()));
+    }
+    if database == base {
+        return file.map_or(Step::DeleteFromDatabase, |f| Step::WriteDatabase(f.to_string()));
+    }
+    if let (Some(base), Some(file), Some(database)) = (base, file, database) {
+        if let Ok(outcome) =
+            three_way_merge(base, database, file, MergeGranularity::Line, ("database", "file"), &[])
+        {
+            if outcome.conflicts.is_empty() {
+                return Step::Merge(outcome.merged);
+            }
+        }
+    }
+    Step::Conflict
+}
+
+fn checklist_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^([ \t]*)[-*+] \[([ xX])\] (.*?)(?:\s*<!-- id:(\d+) -->)?\s*$").expect("valid regex")
+    })
+}
+
+/// Render a task as a Markdown file: fields in frontmatter, the description
+/// as the body and subtasks as a nested checklist. `extra` holds the file's
+/// other frontmatter keys.
+fn render_task(task: &TaskRow, subtasks: &[&SubtaskRow], extra: &[(String, FrontmatterValue)]) -> String {
+    let mut frontmatter = Frontmatter::default();
+    frontmatter.set("id", FrontmatterValue::Text(task.id.to_string()));
+    frontmatter.set_text("title", Some(&task.title));
+    frontmatter.set_text("status", Some(&task.status));
+    frontmatter.set_text("priority", task.priority.as_deref());
+    frontmatter.set_text("due", task.due_date.as_deref());
+    frontmatter.set_text("scheduled", task.scheduled_date.as_deref());
+    for (key, value) in extra {
+        frontmatter.set(key, value.clone());
+    }
+
+    let mut body = task.description.as_deref().unwrap_or("").trim().to_string();
+    if !subtasks.is_empty() {
+        let mut lines = Vec::new();
+        render_checklist(subtasks, None, 0, &mut lines);
+        if !body.is_empty() {
+            body.push_str("\n\n");
+        }
+        body.push_str(&format!("## {}\n\n{}", SUBTASKS_HEADING, lines.join("\n")));
+    }
+    if !body.is_empty() {
+        body.push('\n');
+    }
+    frontmatter::render(&frontmatter, &body)
+}
+
+fn render_checklist(subtasks: &[&SubtaskRow], parent: Option<i64>, depth: usize, lines: &mut Vec<String>) {
+    for subtask in subtasks.iter().filter(|s| s.parent_subtask_id == parent) {
+        lines.push(format!(
+            "{}- [{}] {} <!-- id:{} -->",
+            "  ".repeat(depth),
+            if subtask.completed { "x" } else { " " },
+            subtask.title.replace('\n', " ").trim(),
+            subtask.id
+        ));
+        render_checklist(subtasks, Some(subtask.id), depth + 1, lines);
+    }
+}
+
+fn parse_task(content: &str, fallback_title: &str) -> Result<ParsedTask, String> {
+    let (frontmatter, body) = frontmatter::parse(content)?;
+
+    let status = frontmatter.text("status").unwrap_or("todo").to_string();
+    if !STATUSES.contains(&status.as_str()) {
+        return Err(format!("status must be one of {} (got '{}')", STATUSES.join(", "), status));
+    }
+    let priority = frontmatter.text("priority").unwrap_or("medium").to_string();
+    if !PRIORITIES.contains(&priority.as_str()) {
+        return Err(format!("priority must be one of {} (got '{}')", PRIORITIES.join(", "), priority));
+    }
+
+    // The last "## Subtasks" section holds the checklist; everything else is
+    // the description
+    let lines: Vec<&str> = body.split_inclusive('\n').collect();
+    let all_headings = headings(&body);
+    let section = all_headings
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(_, h)| h.level == 2 && h.title.eq_ignore_ascii_case(SUBTASKS_HEADING));
+    let (description, subtasks) = match section {
+        Some((index, heading)) => {
+            let start = heading.line - 1;
+            let end = all_headings[index + 1..]
+                .iter()
+                .find(|h| h.level <= 2)
+                .map_or(lines.len(), |h| h.line - 1);
+            let description = format!("{}{}", lines[..start].concat(), lines[end..].concat());
+            let subtasks = lines[start + 1..end]
+                .iter()
+                .filter_map(|line| checklist_regex().captures(line.trim_end_matches(['\n', '\r'])))
+                .map(|captures| ParsedSubtask {
+                    id: captures.get(4).and_then(|id| id.as_str().parse().ok()),
+                    depth: captures[1].replace('\t', "  ").len() / 2,
+                    title: captures[3].trim().to_string(),
+                    completed: &captures[2] != " ",
+                })
+                .filter(|subtask| !subtask.title.is_empty())
+                .collect();
+            (description, subtasks)
+        }
+        None => (body.clone(), Vec::new()),
+    };
+    let mut description = description.trim();
+
+    // Without a title field, a leading "# Title" line (then the file name)
+    // names the task
+    let title = match frontmatter.text("title") {
+        Some(title) => title.to_string(),
+        None => match description.strip_prefix("# ") {
+            Some(rest) => {
+                let (title, rest) = rest.split_once('\n').unwrap_or((rest, ""));
+                description = rest.trim();
+                title.to_string()
+            }
+            None => fallback_title.to_string(),
+        },
+    };
+
+    Ok(ParsedTask {
+        title: title.trim().to_string(),
+        description: (!description.is_empty()).then(|| description.to_string()),
+        status,
+        priority,
+        due_date: frontmatter.text("due").map(str::to_string),
+        scheduled_date: frontmatter.text("scheduled").map(str::to_string),
+        subtasks,
+        extra: frontmatter
+            .fields
+            .iter()
+            .filter(|(key, _)| !TASK_KEYS.contains(&key.as_str()))
+            .cloned()
+            .collect(),
+    })
+}
+
+/// File name for a task that does not have one yet
+fn task_file_name(task_id: i64, title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_end_matches('-').chars().take(60).collect();
+    if slug.is_empty() {
+        format!("{}.md", task_id)
+    } else {
+        format!("{}-{}.md", task_id, slug.trim_end_matches('-'))
+    }
+}
+
+fn notes_path_for(task_path: &str) -> String {
+    format!("{}{}", task_path.trim_end_matches(".md"), NOTES_SUFFIX)
+}
+
+fn read_file(folder: &Path, relative_path: &str) -> Result<Option<String>, String> {
+    match std::fs::read_to_string(folder.join(relative_path)) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", relative_path, e)),
+    }
+}
+
+fn write_file(folder: &Path, relative_path: &str, content: &str) -> Result<(), String> {
+    std::fs::write(folder.join(relative_path), content)
+        .map_err(|e| format!("Failed to write {}: {}", relative_path, e))
+}
+
+fn delete_file(folder: &Path, relative_path: &str) -> Result<(), String> {
+    match std::fs::remove_file(folder.join(relative_path)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to delete {}: {}", relative_path, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Task files in the folder (not descending into subfolders)
+fn list_task_files(folder: &Path) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(folder)
+        .map_err(|e| format!("Failed to read folder {}: {}", folder.display(), e))?;
+    let mut files: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".md") && !name.ends_with(NOTES_SUFFIX) && !name.starts_with('.'))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+async fn load_folder(space_id: i64) -> Result<SpaceFolder, String> {
+    let pool = get_db_pool()?;
+    sqlx::query_as::<_, SpaceFolder>(
+        "SELECT space_id, folder_path, enabled, last_synced_at, last_error, created_at
+         FROM space_folders WHERE space_id = ?",
+    )
+    .bind(space_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Space {} is not bound to a folder", space_id))
+}
+
+/// The database side of a space: each task rendered as its file, plus its notes
+async fn render_space(space_id: i64) -> Result<HashMap<i64, (TaskRow, String, Option<String>)>, String> {
+    let pool = get_db_pool()?;
+
+    let tasks = sqlx::query_as::<_, TaskRow>(
+        "SELECT id, title, description, status, priority, due_date, scheduled_date
+         FROM tasks WHERE space_id = ?",
+    )
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load tasks: {}", e))?;
+
+    let subtasks = sqlx::query_as::<_, SubtaskRow>(
+        "SELECT s.id, s.task_id, s.parent_subtask_id, s.title, s.completed
+         FROM subtasks s JOIN tasks t ON t.id = s.task_id
+         WHERE t.space_id = ?
+         ORDER BY s.position ASC, s.id ASC",
+    )
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load subtasks: {}", e))?;
+
+    let notes: HashMap<i64, String> = sqlx::query_as::<_, (i64, Option<String>)>(
+        "SELECT n.task_id, n.content FROM task_notes n JOIN tasks t ON t.id = n.task_id
+         WHERE t.space_id = ?",
+    )
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load task notes: {}", e))?
+    .into_iter()
+    .filter_map(|(task_id, content)| content.filter(|c| !c.trim().is_empty()).map(|c| (task_id, c)))
+    .collect();
+
+    let extra: HashMap<i64, Vec<(String, FrontmatterValue)>> = sqlx::query_as::<_, (i64, String)>(
+        "SELECT task_id, extra_fields FROM space_folder_files WHERE space_id = ? AND kind = ?",
+    )
+    .bind(space_id)
+    .bind(TASK_FILE)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load frontmatter fields: {}", e))?
+    .into_iter()
+    .map(|(task_id, json)| (task_id, frontmatter::fields_from_json(&json)))
+    .collect();
+
+    Ok(tasks
+        .into_iter()
+        .map(|task| {
+            let own: Vec<&SubtaskRow> = subtasks.iter().filter(|s| s.task_id == task.id).collect();
+            let rendered = render_task(&task, &own, extra.get(&task.id).map_or(&[], Vec::as_slice));
+            let task_notes = notes.get(&task.id).cloned();
+            (task.id, (task, rendered, task_notes))
+        })
+        .collect())
+}
+
+/// Write a parsed task file into the database, creating the task when
+/// `task_id` is None or no longer exists. Returns the task's id.
+async fn apply_task(space_id: i64, task_id: Option<i64>, parsed: &ParsedTask) -> Result<i64, String> {
+    let pool = get_db_pool()?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // A task deleted since the file was last synced is created again
+    let updated = match task_id {
+        Some(task_id) => {
+            sqlx::query(
+                "UPDATE tasks SET title = ?, description = ?, status = ?, priority = ?, due_date = ?,
+                        scheduled_date = ?, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ? AND space_id = ?",
+            )
+            .bind(&parsed.title)
+            .bind(&parsed.description)
+            .bind(&parsed.status)
+            .bind(&parsed.priority)
+            .bind(&parsed.due_date)
+            .bind(&parsed.scheduled_date)
+            .bind(task_id)
+            .bind(space_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to update task {}: {}", task_id, e))?
+            .rows_affected()
+                > 0
+        }
+        None => false,
+    };
+    let task_id = match task_id.filter(|_| updated) {
+        Some(task_id) => task_id,
+        None => sqlx::query_scalar::<_, i64>(
+            "INSERT INTO tasks (space_id, title, description, status, priority, due_date, scheduled_date)
+             VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING id",
+        )
+        .bind(space_id)
+        .bind(&parsed.title)
+        .bind(&parsed.description)
+        .bind(&parsed.status)
+        .bind(&parsed.priority)
+        .bind(&parsed.due_date)
+        .bind(&parsed.scheduled_date)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to create task: {}", e))?,
+    };
+
+    let existing: HashSet<i64> = sqlx::query_scalar("SELECT id FROM subtasks WHERE task_id = ?")
+        .bind(task_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to load subtasks: {}", e))?
+        .into_iter()
+        .collect();
+
+    // Checklist nesting becomes the subtask tree; lines without a known id
+    // are new subtasks
+    let mut kept = HashSet::new();
+    let mut ancestors: Vec<(usize, i64)> = Vec::new();
+    for (position, subtask) in parsed.subtasks.iter().enumerate() {
+        ancestors.retain(|(depth, _)| *depth < subtask.depth);
+        let parent = ancestors.last().map(|(_, id)| *id);
+        let id = match subtask.id.filter(|id| existing.contains(id) && !kept.contains(id)) {
+            Some(id) => {
+                sqlx::query(
+                    "UPDATE subtasks SET title = ?, completed = ?, parent_subtask_id = ?, position = ?,
+                            updated_at = CURRENT_TIMESTAMP
+                     WHERE id = ?",
+                )
+                .bind(&subtask.title)
+                .bind(subtask.completed)
+                .bind(parent)
+                .bind(position as i64 + 1)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to update subtask {}: {}", id, e))?;
+                id
+            }
+            None => sqlx::query_scalar::<_, i64>(
+                "INSERT INTO subtasks (task_id, title, completed, parent_subtask_id, position)
+                 VALUES (?, ?, ?, ?, ?) RETURNING id",
+            )
+            .bind(task_id)
+            .bind(&subtask.title)
+            .bind(subtask.completed)
+            .bind(parent)
+            .bind(position as i64 + 1)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to create subtask: {}", e))?,
+        };
+        kept.insert(id);
+        ancestors.push((subtask.depth, id));
+    }
+
+    for id in existing.difference(&kept) {
+        sqlx::query("DELETE FROM subtasks WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to delete subtask {}: {}", id, e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit task: {}", e))?;
+    Ok(task_id)
+}
+
+async fn delete_task(task_id: i64) -> Result<(), String> {
+    let pool = get_db_pool()?;
+    sqlx::query("DELETE FROM tasks WHERE id = ?")
+        .bind(task_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete task {}: {}", task_id, e))?;
+    Ok(())
+}
+
+async fn save_notes(task_id: i64, content: &str, relative_path: &str) -> Result<(), String> {
+    let attribution = RevisionAttribution {
+        summary: Some(format!("Synced from {}", relative_path)),
+        ..RevisionAttribution::user()
+    };
+    crate::task_notes::save_task_notes(task_id as i32, content, &attribution, None).await
+}
+
+/// Whether an agent is editing a task's notes right now
+async fn notes_locked(task_id: i64) -> Result<bool, String> {
+    let pool = get_db_pool()?;
+    sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM agent_edit_locks WHERE task_id = ? AND expires_at > datetime('now'))",
+    )
+    .bind(task_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to check notes lock: {}", e))
+}
+
+async fn record_synced(space_id: i64, task_id: i64, kind: &str, relative_path: &str, content: &str) -> Result<(), String> {
+    let pool = get_db_pool()?;
+    sqlx::query(
+        "INSERT INTO space_folder_files (space_id, task_id, kind, relative_path, synced_content, synced_at)
+         VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT(task_id, kind) DO UPDATE SET
+           space_id = excluded.space_id,
+           relative_path = excluded.relative_path,
+           synced_content = excluded.synced_content,
+           synced_at = excluded.synced_at",
+    )
+    .bind(space_id)
+    .bind(task_id)
+    .bind(kind)
+    .bind(relative_path)
+    .bind(content)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record synced file: {}", e))?;
+
+    // Both sides agree again, so any conflict on this file is over
+    sqlx::query(
+        "UPDATE space_folder_conflicts SET resolved_at = CURRENT_TIMESTAMP, resolution = 'in_sync'
+         WHERE task_id = ? AND kind = ? AND resolved_at IS NULL",
+    )
+    .bind(task_id)
+    .bind(kind)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to close conflict: {}", e))?;
+    Ok(())
+}
+
+/// Remember a task file's other frontmatter keys, so the file rendered from
+/// the database keeps them. A file seen for the first time is recorded with
+/// `content` until its sync completes.
+async fn record_extra_fields(
+    space_id: i64,
+    task_id: i64,
+    relative_path: &str,
+    content: &str,
+    extra: &[(String, FrontmatterValue)],
+) -> Result<(), String> {
+    let pool = get_db_pool()?;
+    sqlx::query(
+        "INSERT INTO space_folder_files (space_id, task_id, kind, relative_path, synced_content, extra_fields, synced_at)
+         VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT(task_id, kind) DO UPDATE SET extra_fields = excluded.extra_fields",
+    )
+    .bind(space_id)
+    .bind(task_id)
+    .bind(TASK_FILE)
+    .bind(relative_path)
+    .bind(content)
+    .bind(frontmatter::fields_to_json(extra))
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record frontmatter fields: {}", e))?;
+    Ok(())
+}
+
+async fn forget_synced(task_id: i64, kind: &str) -> Result<(), String> {
+    let pool = get_db_pool()?;
+    sqlx::query("DELETE FROM space_folder_files WHERE task_id = ? AND kind = ?")
+        .bind(task_id)
+        .bind(kind)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to forget synced file: {}", e))?;
+    sqlx::query(
+        "UPDATE space_folder_conflicts SET resolved_at = CURRENT_TIMESTAMP, resolution = 'in_sync'
+         WHERE task_id = ? AND kind = ? AND resolved_at IS NULL",
+    )
+    .bind(task_id)
+    .bind(kind)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to close conflict: {}", e))?;
+    Ok(())
+}
+
+/// Record a conflict, or refresh the open one for the same file
+async fn record_conflict(
+    space_id: i64,
+    task_id: i64,
+    kind: &str,
+    relative_path: &str,
+    versions: (Option<&str>, Option<&str>, Option<&str>),
+) -> Result<(), String> {
+    let pool = get_db_pool()?;
+    let (base, file, database) = versions;
+
+    let open: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM space_folder_conflicts WHERE task_id = ? AND kind = ? AND resolved_at IS NULL",
+    )
+    .bind(task_id)
+    .bind(kind)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let result = match open {
+        Some(id) => {
+            sqlx::query(
+                "UPDATE space_folder_conflicts SET relative_path = ?, file_content = ?, database_content = ?
+                 WHERE id = ?",
+            )
+            .bind(relative_path)
+            .bind(file)
+            .bind(database)
+            .bind(id)
+            .execute(pool)
+            .await
+        }
+        None => {
+            sqlx::query(
+                "INSERT INTO space_folder_conflicts
+                   (space_id, task_id, kind, relative_path, base_content, file_content, database_content)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(space_id)
+            .bind(task_id)
+            .bind(kind)
+            .bind(relative_path)
+            .bind(base)
+            .bind(file)
+            .bind(database)
+            .execute(pool)
+            .await
+        }
+    };
+    result.map_err(|e| format!("Failed to record conflict: {}", e))?;
+    Ok(())
+}
+
+fn file_stem(relative_path: &str) -> &str {
+    relative_path.trim_end_matches(".md")
+}
+
+/// Sync a task file. `file` is its content on disk and `database` the task
+/// rendered from the database.
+async fn sync_task_file(
+    space_id: i64,
+    folder: &Path,
+    task_id: i64,
+    relative_path: &str,
+    versions: (Option<&str>, Option<&str>, Option<&str>),
+    report: &mut SyncReport,
+) -> Result<(), String> {
+    let (base, file, database) = versions;
+    match reconcile(base, file, database) {
+        Step::InSync => {
+            if base != file {
+                record_synced(space_id, task_id, TASK_FILE, relative_path, file.unwrap_or_default()).await?;
+            }
+        }
+        Step::Forget => forget_synced(task_id, TASK_FILE).await?,
+        Step::WriteFile(content) => {
+            write_file(folder, relative_path, &content)?;
+            record_synced(space_id, task_id, TASK_FILE, relative_path, &content).await?;
+            report.exported += 1;
+        }
+        Step::DeleteFile => {
+            delete_file(folder, relative_path)?;
+            forget_synced(task_id, TASK_FILE).await?;
+            report.deleted += 1;
+        }
+        // A vanished task file may be an unmounted drive or a branch switch
+        // rather than a deletion, so the task is only deleted once the user
+        // resolves the conflict in favour of the file
+        Step::DeleteFromDatabase | Step::Conflict => {
+            record_conflict(space_id, task_id, TASK_FILE, relative_path, versions).await?;
+        }
+        Step::WriteDatabase(content) => {
+            import_task_file(space_id, folder, Some(task_id), relative_path, &content).await?;
+            report.imported += 1;
+        }
+        Step::Merge(content) => {
+            import_task_file(space_id, folder, Some(task_id), relative_path, &content).await?;
+            report.merged += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Write a task file's content into the database, then write the file back
+/// as the database now renders it (so new subtasks get their ids)
+async fn import_task_file(
+    space_id: i64,
+    folder: &Path,
+    task_id: Option<i64>,
+    relative_path: &str,
+    content: &str,
+) -> Result<i64, String> {
+    let parsed = parse_task(content, file_stem(relative_path)).map_err(|e| format!("{}: {}", relative_path, e))?;
+    let task_id = apply_task(space_id, task_id, &parsed).await?;
+    record_extra_fields(space_id, task_id, relative_path, content, &parsed.extra).await?;
+
+    let rendered = render_space(space_id).await?;
+    let (_, rendered, _) = rendered
+        .get(&task_id)
+        .ok_or_else(|| format!("Task {} disappeared while syncing", task_id))?;
+    if rendered != content {
+        write_file(folder, relative_path, rendered)?;
+    }
+    record_synced(space_id, task_id, TASK_FILE, relative_path, rendered).await?;
+    Ok(task_id)
+}
+
+async fn sync_notes_file(
+    space_id: i64,
+    folder: &Path,
+    task_id: i64,
+    relative_path: &str,
+    versions: (Option<&str>, Option<&str>, Option<&str>),
+    report: &mut SyncReport,
+) -> Result<(), String> {
+    let (base, file, database) = versions;
+    let step = reconcile(base, file, database);
+    if matches!(step, Step::WriteDatabase(_) | Step::DeleteFromDatabase | Step::Merge(_)) && notes_locked(task_id).await? {
+        // Picked up on a later pass, once the agent is done
+        return Ok(());
+    }
+    match step {
+        Step::InSync => {
+            if base != file {
+                record_synced(space_id, task_id, NOTES_FILE, relative_path, file.unwrap_or_default()).await?;
+            }
+        }
+        Step::Forget => forget_synced(task_id, NOTES_FILE).await?,
+        Step::WriteFile(content) => {
+            write_file(folder, relative_path, &content)?;
+            record_synced(space_id, task_id, NOTES_FILE, relative_path, &content).await?;
+            report.exported += 1;
+        }
+        Step::DeleteFile => {
+            delete_file(folder, relative_path)?;
+            forget_synced(task_id, NOTES_FILE).await?;
+            report.deleted += 1;
+        }
+        Step::DeleteFromDatabase => {
+            save_notes(task_id, "", relative_path).await?;
+            forget_synced(task_id, NOTES_FILE).await?;
+            report.deleted += 1;
+        }
+        Step::WriteDatabase(content) => {
+            save_notes(task_id, &content, relative_path).await?;
+            record_synced(space_id, task_id, NOTES_FILE, relative_path, &content).await?;
+            report.imported += 1;
+        }
+        Step::Merge(content) => {
+            save_notes(task_id, &content, relative_path).await?;
+            write_file(folder, relative_path, &content)?;
+            record_synced(space_id, task_id, NOTES_FILE, relative_path, &content).await?;
+            report.merged += 1;
+        }
+        Step::Conflict => {
+            record_conflict(space_id, task_id, NOTES_FILE, relative_path, versions).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn open_conflict_count(space_id: i64) -> Result<usize, String> {
+    let pool = get_db_pool()?;
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM space_folder_conflicts WHERE space_id = ? AND resolved_at IS NULL",
+    )
+    .bind(space_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    Ok(count as usize)
+}
+
+/// One pass of two-way sync between a space and its folder. Each file is
+/// compared with what it held at the last sync: a side that changed wins,
+/// changes on both sides are merged by line, and anything that cannot be
+/// merged is recorded as a conflict and left alone.
+async fn sync_space(space_id: i64) -> Result<SyncReport, String> {
+    let folder_row = load_folder(space_id).await?;
+    let folder = PathBuf::from(&folder_row.folder_path);
+    // Recreating a missing folder would make every task look deleted
+    if !folder.is_dir() {
+        return Err(format!(
+            "Folder {} is missing; reconnect it or bind the space to another folder",
+            folder.display()
+        ));
+    }
+
+    let pool = get_db_pool()?;
+    let mut report = SyncReport {
+        space_id,
+        ..SyncReport::default()
+    };
+
+    let database = render_space(space_id).await?;
+    let synced = sqlx::query_as::<_, SyncedFile>(
+        "SELECT task_id, kind, relative_path, synced_content FROM space_folder_files WHERE space_id = ?",
+    )
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load synced files: {}", e))?;
+    let synced: HashMap<(i64, &str), &SyncedFile> =
+        synced.iter().map(|f| ((f.task_id, f.kind.as_str()), f)).collect();
+
+    // Match files to tasks: by the path last synced, else by the id in the
+    // frontmatter (a renamed file, or a folder bound for the first time)
+    let files = list_task_files(&folder)?;
+    let mut paths: HashMap<i64, String> = HashMap::new();
+    let mut unclaimed = Vec::new();
+    for file in &files {
+        match synced.values().find(|s| s.kind == TASK_FILE && &s.relative_path == file) {
+            Some(s) => {
+                paths.insert(s.task_id, file.clone());
+            }
+            None => unclaimed.push(file.clone()),
+        }
+    }
+    let mut new_files = Vec::new();
+    for file in unclaimed {
+        let content = read_file(&folder, &file)?.unwrap_or_default();
+        // Only files with an id key are tasks, so READMEs and other docs kept
+        // in the folder are left alone; an empty `id:` asks for a new task
+        let parsed = frontmatter::parse(&content).ok().map(|(fm, _)| fm);
+        let Some(fm) = parsed.filter(|fm| fm.get("id").is_some()) else {
+            continue;
+        };
+        let id = fm.text("id").and_then(|id| id.parse::<i64>().ok());
+        match id {
+            Some(id) if database.contains_key(&id) && !paths.contains_key(&id) => {
+                paths.insert(id, file);
+            }
+            _ => new_files.push((file, content)),
+        }
+    }
+
+    let task_ids: HashSet<i64> = database
+        .keys()
+        .copied()
+        .chain(synced.keys().map(|(task_id, _)| *task_id))
+        .collect();
+    for task_id in task_ids {
+        let task = database.get(&task_id);
+        let task_path = paths
+            .get(&task_id)
+            .cloned()
+            .or_else(|| synced.get(&(task_id, TASK_FILE)).map(|s| s.relative_path.clone()))
+            .or_else(|| task.map(|(row, _, _)| task_file_name(task_id, &row.title)))
+            .unwrap_or_default();
+        let base = synced.get(&(task_id, TASK_FILE)).map(|s| s.synced_content.as_str());
+        // Follow a task file renamed in the editor
+        if synced
+            .get(&(task_id, TASK_FILE))
+            .is_some_and(|s| s.relative_path != task_path)
+        {
+            sqlx::query("UPDATE space_folder_files SET relative_path = ? WHERE task_id = ? AND kind = ?")
+                .bind(&task_path)
+                .bind(task_id)
+                .bind(TASK_FILE)
+                .execute(pool)
+                .await
+                .map_err(|e| format!("Failed to record renamed file: {}", e))?;
+        }
+        let file = if paths.contains_key(&task_id) {
+            read_file(&folder, &task_path)?
+        } else {
+            None
+        };
+        let rendered = task.map(|(_, rendered, _)| rendered.as_str());
+        if let Err(e) =
+            sync_task_file(space_id, &folder, task_id, &task_path, (base, file.as_deref(), rendered), &mut report).await
+        {
+            report.errors.push(e);
+            continue;
+        }
+
+        let notes_path = synced
+            .get(&(task_id, NOTES_FILE))
+            .map(|s| s.relative_path.clone())
+            .unwrap_or_else(|| notes_path_for(&task_path));
+        let notes_base = synced.get(&(task_id, NOTES_FILE)).map(|s| s.synced_content.as_str());
+        // Notes wait while the deletion of their task file is unconfirmed
+        if task.is_some() && !folder.join(&task_path).exists() {
+            continue;
+        }
+        let notes_file = read_file(&folder, &notes_path)?;
+        // Notes of a task deleted in the app go with it
+        let notes_database = task.and_then(|(_, _, notes)| notes.as_deref());
+        if task.is_none() && notes_file.is_some() && notes_file.as_deref() == notes_base {
+            delete_file(&folder, &notes_path)?;
+            forget_synced(task_id, NOTES_FILE).await?;
+            continue;
+        }
+        if let Err(e) = sync_notes_file(
+            space_id,
+            &folder,
+            task_id,
+            &notes_path,
+            (notes_base, notes_file.as_deref(), notes_database),
+            &mut report,
+        )
+        .await
+        {
+            report.errors.push(e);
+        }
+    }
+
+    // Files nobody has seen before become new tasks, with their notes
+    for (file, content) in new_files {
+        match import_task_file(space_id, &folder, None, &file, &content).await {
+            Ok(task_id) => {
+                report.created += 1;
+                let notes_path = notes_path_for(&file);
+                if let Some(notes) = read_file(&folder, &notes_path)? {
+                    save_notes(task_id, &notes, &notes_path).await?;
+                    record_synced(space_id, task_id, NOTES_FILE, &notes_path, &notes).await?;
+                }
+            }
+            Err(e) => report.errors.push(e),
+        }
+    }
+
+    report.conflicts = open_conflict_count(space_id).await?;
+    Ok(report)
+}
+
+/// Sync a space and record the outcome on its binding
+async fn run_sync(app: &AppHandle, space_id: i64) -> Result<SyncReport, String> {
+    let _guard = SYNC_LOCK.lock().await;
+    let pool = get_db_pool()?;
+
+    let result = sync_space(space_id).await;
+    let last_error = match &result {
+        Ok(report) if report.errors.is_empty() => None,
+        Ok(report) => Some(report.errors.join("\n")),
+        Err(e) => Some(e.clone()),
+    };
+    sqlx::query("UPDATE space_folders SET last_synced_at = CURRENT_TIMESTAMP, last_error = ? WHERE space_id = ?")
+        .bind(&last_error)
+        .bind(space_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update folder binding: {}", e))?;
+
+    let report = result?;
+    if report.changed() {
+        let _ = app.emit("space-folder-synced", &report);
+    }
+    Ok(report)
+}
+
+/// Sync every enabled folder binding on an interval, so edits made in an
+/// editor reach the database and database edits reach the files
+pub fn watch_space_folders(app: AppHandle) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(WATCH_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let Ok(pool) = get_db_pool() else {
+                continue;
+            };
+            let space_ids: Vec<i64> = match sqlx::query_scalar("SELECT space_id FROM space_folders WHERE enabled")
+                .fetch_all(pool)
+                .await
+            {
+                Ok(ids) => ids,
+                Err(e) => {
+                    eprintln!("Warning: Failed to list space folders: {}", e);
+                    continue;
+                }
+            };
+            for space_id in space_ids {
+                if let Err(e) = run_sync(&app, space_id).await {
+                    eprintln!("Warning: Folder sync for space {} failed: {}", space_id, e);
+                }
+            }
+        }
+    });
+}
+
+/// Bind a space to a folder (created if missing) and run a first sync.
+/// Tasks already in the folder are matched by the id in their frontmatter;
+/// Markdown files without an `id` key are not tasks and are never touched.
+#[tauri::command]
+pub async fn bind_space_folder(app: AppHandle, space_id: i64, folder_path: String) -> Result<SyncReport, String> {
+    let path = PathBuf::from(folder_path.trim());
+    if !path.is_absolute() {
+        return Err(format!("Folder path must be absolute (got '{}')", folder_path));
+    }
+    std::fs::create_dir_all(&path).map_err(|e| format!("Failed to create folder {}: {}", path.display(), e))?;
+
+    let pool = get_db_pool()?;
+    let taken: Option<i64> = sqlx::query_scalar("SELECT space_id FROM space_folders WHERE folder_path = ? AND space_id != ?")
+        .bind(path.to_string_lossy().as_ref())
+        .bind(space_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if let Some(other) = taken {
+        return Err(format!("{} is already bound to space {}", path.display(), other));
+    }
+
+    // A new folder starts from scratch
+    sqlx::query("DELETE FROM space_folders WHERE space_id = ?")
+        .bind(space_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to clear previous binding: {}", e))?;
+    sqlx::query("INSERT INTO space_folders (space_id, folder_path) VALUES (?, ?)")
+        .bind(space_id)
+        .bind(path.to_string_lossy().as_ref())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to bind folder: {}", e))?;
+
+    run_sync(&app, space_id).await
+}
+
+/// Stop syncing a space. Files on disk are left as they are.
+#[tauri::command]
+pub async fn unbind_space_folder(space_id: i64) -> Result<(), String> {
+    let pool = get_db_pool()?;
+    sqlx::query("DELETE FROM space_folders WHERE space_id = ?")
+        .bind(space_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to unbind folder: {}", e))?;
+    Ok(())
+}
+
+/// Pause or resume syncing without losing the binding
+#[tauri::command]
+pub async fn set_space_folder_enabled(space_id: i64, enabled: bool) -> Result<(), String> {
+    load_folder(space_id).await?;
+    let pool = get_db_pool()?;
+    sqlx::query("UPDATE space_folders SET enabled = ? WHERE space_id = ?")
+        .bind(enabled)
+        .bind(space_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update folder binding: {}", e))?;
+    Ok(())
+}
+
+/// The folder a space is bound to, if any
+#[tauri::command]
+pub async fn get_space_folder(space_id: i64) -> Result<Option<SpaceFolder>, String> {
+    match load_folder(space_id).await {
+        Ok(folder) => Ok(Some(folder)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Sync a space with its folder now
+#[tauri::command]
+pub async fn sync_space_folder(app: AppHandle, space_id: i64) -> Result<SyncReport, String> {
+    run_sync(&app, space_id).await
+}
+
+/// Open conflicts between a space and its folder
+#[tauri::command]
+pub async fn list_space_folder_conflicts(space_id: i64) -> Result<Vec<FolderConflict>, String> {
+    let pool = get_db_pool()?;
+    sqlx::query_as::<_, FolderConflict>(
+        "SELECT id, space_id, task_id, kind, relative_path, base_content, file_content, database_content, detected_at
+         FROM space_folder_conflicts
+         WHERE space_id = ? AND resolved_at IS NULL
+         ORDER BY id",
+    )
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list conflicts: {}", e))
+}
+
+/// Settle a conflict by keeping the file ('file') or the database
+/// ('database') version; the other side is overwritten with it. Keeping a
+/// deleted task file deletes the task.
+#[tauri::command]
+pub async fn resolve_space_folder_conflict(app: AppHandle, conflict_id: i64, keep: String) -> Result<SyncReport, String> {
+    let pool = get_db_pool()?;
+    let conflict = sqlx::query_as::<_, FolderConflict>(
+        "SELECT id, space_id, task_id, kind, relative_path, base_content, file_content, database_content, detected_at
+         FROM space_folder_conflicts WHERE id = ? AND resolved_at IS NULL",
+    )
+    .bind(conflict_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Open conflict {} not found", conflict_id))?;
+
+    let folder = PathBuf::from(load_folder(conflict.space_id).await?.folder_path);
+    if !folder.is_dir() {
+        return Err(format!("Folder {} is missing", folder.display()));
+    }
+    {
+        let guard = SYNC_LOCK.lock().await;
+        // Keeping a deleted task file confirms the deletion of the task
+        if keep == "file" && conflict.kind == TASK_FILE && read_file(&folder, &conflict.relative_path)?.is_none() {
+            delete_task(conflict.task_id).await?;
+            forget_synced(conflict.task_id, TASK_FILE).await?;
+            sqlx::query("UPDATE space_folder_conflicts SET resolution = 'file' WHERE id = ?")
+                .bind(conflict_id)
+                .execute(pool)
+                .await
+                .map_err(|e| format!("Failed to resolve conflict: {}", e))?;
+            drop(guard);
+            return run_sync(&app, conflict.space_id).await;
+        }
+        // Pretend the side being kept is the only one that changed, so the
+        // next sync copies it over the other
+        let base = match keep.as_str() {
+            "file" => {
+                let database = render_space(conflict.space_id).await?;
+                let task = database.get(&conflict.task_id);
+                if conflict.kind == TASK_FILE {
+                    task.map(|(_, rendered, _)| rendered.clone())
+                } else {
+                    task.and_then(|(_, _, notes)| notes.clone())
+                }
+            }
+            "database" => read_file(&folder, &conflict.relative_path)?,
+            other => return Err(format!("keep must be 'file' or 'database' (got '{}')", other)),
+        };
+        match base {
+            Some(base) => {
+                record_synced(conflict.space_id, conflict.task_id, &conflict.kind, &conflict.relative_path, &base)
+                    .await?
+            }
+            None => forget_synced(conflict.task_id, &conflict.kind).await?,
+        }
+        sqlx::query("UPDATE space_folder_conflicts SET resolution = ? WHERE id = ?")
+            .bind(&keep)
+            .bind(conflict_id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to resolve conflict: {}", e))?;
+    }
+    run_sync(&app, conflict.space_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASK_FILE_CONTENT: &str = "---
+id: 12
+title: Launch site
+status: in_progress
+priority: high
+due: 2026-11-01
+scheduled:
+tags: [web, \"q4, launch\"]
+owner: Sam
+---
+
+Ship the new site.
+
+## Subtasks
+
+- [x] Design <!-- id:3 -->
+  - [ ] Mockups <!-- id:4 -->
+- [ ] Build <!-- id:5 -->
+";
+
+    #[test]
+    fn reconcile_decides_each_step() {
+        let merge_base = "a\nb\nc\n";
+        let cases = [
+            (Some("x"), Some("y"), Some("y"), Step::InSync),
+            (Some("x"), None, None, Step::Forget),
+            (Some("x"), Some("x"), Some("db"), Step::WriteFile("db".to_string())),
+            (None, None, Some("db"), Step::WriteFile("db".to_string())),
+            (Some("x"), Some("x"), None, Step::DeleteFile),
+            (Some("x"), Some("file"), Some("x"), Step::WriteDatabase("file".to_string())),
+            (None, Some("file"), None, Step::WriteDatabase("file".to_string())),
+            (Some("x"), None, Some("x"), Step::DeleteFromDatabase),
+            (Some(merge_base), Some("A\nb\nc\n"), Some("a\nb\nC\n"), Step::Merge("A\nb\nC\n".to_string())),
+            (Some(merge_base), Some("a\nfile\nc\n"), Some("a\ndb\nc\n"), Step::Conflict),
+            (Some("x"), Some("file"), None, Step::Conflict),
+            (None, Some("file"), Some("db"), Step::Conflict),
+        ];
+        for (base, file, database, step) in cases {
+            assert_eq!(reconcile(base, file, database), step, "{:?} {:?} {:?}", base, file, database);
+        }
+    }
+
+    #[test]
+    fn task_file_round_trips() {
+        let parsed = parse_task(TASK_FILE_CONTENT, "fallback").unwrap();
+        assert_eq!(parsed.title, "Launch site");
+        assert_eq!(parsed.description.as_deref(), Some("Ship the new site."));
+        assert_eq!((parsed.status.as_str(), parsed.priority.as_str()), ("in_progress", "high"));
+        assert_eq!((parsed.due_date.as_deref(), parsed.scheduled_date.as_deref()), (Some("2026-11-01"), None));
+        let checklist: Vec<(Option<i64>, usize, &str, bool)> = parsed
+            .subtasks
+            .iter()
+            .map(|s| (s.id, s.depth, s.title.as_str(), s.completed))
+            .collect();
+        assert_eq!(
+            checklist,
+            vec![(Some(3), 0, "Design", true), (Some(4), 1, "Mockups", false), (Some(5), 0, "Build", false)]
+        );
+        assert_eq!(
+            parsed.extra,
+            vec![
+                ("tags".to_string(), FrontmatterValue::List(vec!["web".to_string(), "q4, launch".to_string()])),
+                ("owner".to_string(), FrontmatterValue::Text("Sam".to_string())),
+            ]
+        );
+
+        let task = TaskRow {
+            id: 12,
+            title: parsed.title.clone(),
+            description: parsed.description.clone(),
+            status: parsed.status.clone(),
+            priority: Some(parsed.priority.clone()),
+            due_date: parsed.due_date.clone(),
+            scheduled_date: parsed.scheduled_date.clone(),
+        };
+        let rows = [(3, None, "Design", true), (4, Some(3), "Mockups", false), (5, None, "Build", false)];
+        let subtasks: Vec<SubtaskRow> = rows
+            .into_iter()
+            .map(|(id, parent_subtask_id, title, completed)| SubtaskRow {
+                id,
+                task_id: 12,
+                parent_subtask_id,
+                title: title.to_string(),
+                completed,
+            })
+            .collect();
+        let subtasks: Vec<&SubtaskRow> = subtasks.iter().collect();
+        assert_eq!(render_task(&task, &subtasks, &parsed.extra), TASK_FILE_CONTENT);
+    }
+
+    #[test]
+    fn parse_task_falls_back_to_a_heading_title() {
+        let parsed = parse_task("# From heading\nBody text\n", "file-name").unwrap();
+        assert_eq!((parsed.title.as_str(), parsed.description.as_deref()), ("From heading", Some("Body text")));
+        assert_eq!((parsed.status.as_str(), parsed.priority.as_str()), ("todo", "medium"));
+        assert_eq!(parse_task("Just text\n", "file-name").unwrap().title, "file-name");
+        assert!(parse_task("---\nstatus: later\n---\n", "x").is_err());
+    }
+}
@@ -0,0 +1,310 @@
+/// A frontmatter value: the flat subset of YAML that task files use
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrontmatterValue {
+    Null,
+    Text(String),
+    List(Vec<String>),
+}
+
+impl FrontmatterValue {
+    /// The value as a string, if it is a non-empty scalar
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            FrontmatterValue::Text(text) if !text.is_empty() => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The value as a list; a lone scalar is a list of one
+    pub fn as_list(&self) -> Vec<String> {
+        match self {
+            FrontmatterValue::Null => Vec::new(),
+            FrontmatterValue::Text(text) if text.is_empty() => Vec::new(),
+            FrontmatterValue::Text(text) => vec![text.clone()],
+            FrontmatterValue::List(items) => items.clone(),
+        }
+    }
+}
+
+/// Frontmatter fields in file order
+#[derive(Debug, Clone, Default)]
+pub struct Frontmatter {
+    pub fields: Vec<(String, FrontmatterValue)>,
+}
+
+impl Frontmatter {
+    pub fn get(&self, key: &str) -> Option<&FrontmatterValue> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn text(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(FrontmatterValue::as_text)
+    }
+
+    pub fn list(&self, key: &str) -> Vec<String> {
+        self.get(key).map(FrontmatterValue::as_list).unwrap_or_default()
+    }
+
+    /// Set a field, keeping its place if it already exists
+    pub fn set(&mut self, key: &str, value: FrontmatterValue) {
+        match self.fields.iter_mut().find(|(k, _)| k == key) {
+            Some((_, existing)) => *existing = value,
+            None => self.fields.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn set_text(&mut self, key: &str, value: Option<&str>) {
+        let value = match value {
+            Some(text) if !text.is_empty() => FrontmatterValue::Text(text.to_string()),
+            _ => FrontmatterValue::Null,
+        };
+        self.set(key, value);
+    }
+}
+
+fn unquote(raw: &str) -> String {
+    if raw.len() >= 2 && raw.starts_with('"') && raw.ends_with('"') {
+        let mut text = String::new();
+        let mut chars = raw[1..raw.len() - 1].chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                text.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some(other) => text.push(other),
+                None => text.push('\\'),
+            }
+        }
+        return text;
+    }
+    if raw.len() >= 2 && raw.starts_with('\'') && raw.ends_with('\'') {
+        return raw[1..raw.len() - 1].replace("''", "'");
+    }
+    // A plain scalar ends at a comment
+    match raw.find(" #") {
+        Some(i) => raw[..i].trim_end().to_string(),
+        None => raw.to_string(),
+    }
+}
+
+fn parse_scalar(raw: &str) -> FrontmatterValue {
+    let raw = raw.trim();
+    match raw {
+        "" | "~" | "null" | "Null" | "NULL" => FrontmatterValue::Null,
+        _ => FrontmatterValue::Text(unquote(raw)),
+    }
+}
+
+/// Split a `[a, b, "c, d"]` flow list, respecting quotes
+fn parse_flow_list(inner: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in inner.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                current.push(c);
+            }
+            (c, Some(q)) if c == q => {
+                quote = None;
+                current.push(c);
+            }
+            (',', None) => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+    items
+        .iter()
+        .filter_map(|item| parse_scalar(item).as_text().map(str::to_string))
+        .collect()
+}
+
+/// Split a document into its frontmatter and body. Documents without a
+/// leading `---` block have empty frontmatter.
+pub fn parse(content: &str) -> Result<(Frontmatter, String), String> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return Ok((Frontmatter::default(), content.to_string()));
+    };
+
+    let mut frontmatter = Frontmatter::default();
+    let mut offset = 0;
+    let mut closed = false;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line == "---" || line == "..." {
+            closed = true;
+            break;
+        }
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        // Items of a block list belong to the key above them
+        if let Some(item) = line.trim_start().strip_prefix("- ").or_else(|| {
+            (line.trim() == "-").then_some("")
+        }) {
+            match frontmatter.fields.last_mut() {
+                Some((_, value @ FrontmatterValue::Null)) => {
+                    *value = FrontmatterValue::List(parse_scalar(item).as_list());
+                }
+                Some((_, FrontmatterValue::List(items))) => items.extend(parse_scalar(item).as_list()),
+                _ => return Err(format!("Unexpected list item in frontmatter: {}", line)),
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            return Err(format!("Cannot read frontmatter line: {}", line));
+        };
+        let value = value.trim();
+        let value = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(inner) => FrontmatterValue::List(parse_flow_list(inner)),
+            None => parse_scalar(value),
+        };
+        frontmatter.set(key.trim(), value);
+    }
+    if !closed {
+        return Err("Frontmatter is missing its closing ---".to_string());
+    }
+    Ok((frontmatter, rest[offset..].to_string()))
+}
+
+fn render_scalar(text: &str) -> String {
+    let plain = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '_' | '-' | '.' | '/' | '(' | ')'))
+        && !text.starts_with(['-', ' '])
+        && !text.ends_with(' ')
+        && !matches!(text, "null" | "Null" | "NULL" | "true" | "false" | "yes" | "no");
+    if plain {
+        text.to_string()
+    } else {
+        format!(
+            "\"{}\"",
+            text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\t', "\\t")
+        )
+    }
+}
+
+/// Render frontmatter and body back into a document
+pub fn render(frontmatter: &Frontmatter, body: &str) -> String {
+    let mut out = String::from("---\n");
+    for (key, value) in &frontmatter.fields {
+        let value = match value {
+            FrontmatterValue::Null => String::new(),
+            FrontmatterValue::Text(text) => format!(" {}", render_scalar(text)),
+            FrontmatterValue::List(items) => format!(
+                " [{}]",
+                items.iter().map(|item| render_scalar(item)).collect::<Vec<_>>().join(", ")
+            ),
+        };
+        out.push_str(&format!("{}:{}\n", key, value));
+    }
+    out.push_str("---\n");
+    if !body.is_empty() {
+        out.push('\n');
+        out.push_str(body.trim_start_matches('\n'));
+    }
+    out
+}
+
+/// Fields as a JSON array of `[key, value]` pairs, for storing alongside a
+/// record
+pub fn fields_to_json(fields: &[(String, FrontmatterValue)]) -> String {
+    let fields: Vec<serde_json::Value> = fields
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                FrontmatterValue::Null => serde_json::Value::Null,
+                FrontmatterValue::Text(text) => serde_json::json!(text),
+                FrontmatterValue::List(items) => serde_json::json!(items),
+            };
+            serde_json::json!([key, value])
+        })
+        .collect();
+    serde_json::Value::Array(fields).to_string()
+}
+
+pub fn fields_from_json(json: &str) -> Vec<(String, FrontmatterValue)> {
+    let fields: Vec<(String, serde_json::Value)> = serde_json::from_str(json).unwrap_or_default();
+    fields
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(text) => FrontmatterValue::Text(text),
+                serde_json::Value::Array(items) => FrontmatterValue::List(
+                    items.iter().filter_map(|item| item.as_str().map(str::to_string)).collect(),
+                ),
+                _ => FrontmatterValue::Null,
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> FrontmatterValue {
+        FrontmatterValue::Text(value.to_string())
+    }
+
+    #[test]
+    fn parses_scalars_lists_and_comments() {
+        let content = "\u{feff}---\n# comment\ntitle: \"Say \\\"hi\\\"\"\nnote: it's fine # trailing\n\
+                       quoted: 'it''s'\nempty:\ntags:\n  - one\n  - \"two, three\"\nflow: [a, 'b']\n---\nBody\n";
+        let (frontmatter, body) = parse(content).unwrap();
+        assert_eq!(body, "Body\n");
+        assert_eq!(
+            frontmatter.fields,
+            vec![
+                ("title".to_string(), text("Say \"hi\"")),
+                ("note".to_string(), text("it's fine")),
+                ("quoted".to_string(), text("it's")),
+                ("empty".to_string(), FrontmatterValue::Null),
+                ("tags".to_string(), FrontmatterValue::List(vec!["one".to_string(), "two, three".to_string()])),
+                ("flow".to_string(), FrontmatterValue::List(vec!["a".to_string(), "b".to_string()])),
+            ]
+        );
+    }
+
+    #[test]
+    fn documents_without_frontmatter_are_all_body() {
+        let (frontmatter, body) = parse("# Title\n").unwrap();
+        assert!(frontmatter.fields.is_empty());
+        assert_eq!(body, "# Title\n");
+        assert!(parse("---\ntitle: x\n").is_err());
+        assert!(parse("---\n- stray\n---\n").is_err());
+    }
+
+    #[test]
+    fn render_quotes_only_what_needs_it_and_parses_back() {
+        let mut frontmatter = Frontmatter::default();
+        frontmatter.set("plain", text("Launch site (v2)"));
+        frontmatter.set("colon", text("a: b"));
+        frontmatter.set("keyword", text("true"));
+        frontmatter.set("multiline", text("line\nbreak"));
+        frontmatter.set("list", FrontmatterValue::List(vec!["x".to_string(), "y, z".to_string()]));
+        frontmatter.set_text("none", None);
+
+        let rendered = render(&frontmatter, "Body\n");
+        assert_eq!(
+            rendered,
+            "---\nplain: Launch site (v2)\ncolon: \"a: b\"\nkeyword: \"true\"\n\
+             multiline: \"line\\nbreak\"\nlist: [x, \"y, z\"]\nnone:\n---\n\nBody\n"
+        );
+        let (parsed, body) = parse(&rendered).unwrap();
+        assert_eq!(parsed.fields, frontmatter.fields);
+        assert_eq!(body, "\nBody\n");
+    }
+}
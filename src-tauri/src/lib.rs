@@ -9,9 +9,33 @@ mod settings;
 mod providers;
 mod planning_agent;
 mod edit_locks;
+mod resource_locks;
+mod agents;
 mod task_notes;
+mod document_revisions;
 mod space_context;
 mod calendar;
+mod proposed_plans;
+mod subtask_dependencies;
+mod planning_context;
+mod staged_plan;
+mod plan_templates;
+mod planning_runs;
+mod planning_coordinator;
+mod subtask_scheduling;
+mod subtask_executor;
+mod subtask_tree;
+mod plan_validation;
+mod text_diff;
+mod text_merge;
+mod markdown_patch;
+mod chalk;
+mod folder_sync;
+mod frontmatter;
+mod retrieval;
+mod search;
+mod task_attachments;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct PlanningResult {
     success: bool,
@@ -27,25 +51,123 @@ struct PlanningCompleteEvent {
     message: String,
     subtasks_created: Option<i32>,
     error: Option<String>,
+    proposed_plan_id: Option<i64>,
+    changes: Vec<planning_agent::PlanChange>,
+    planning_run_id: Option<i64>,
+    validation_issues: Vec<plan_validation::PlanIssue>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct PlanningCancelledEvent {
+    task_id: i32,
+    message: String,
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn start_task_planning(
     task_id: i32,
     task_title: String,
     task_description: Option<String>,
     agents: String,
+    draft: Option<bool>,
+    replan: Option<bool>,
+    expand: Option<bool>,
+    attach: Option<bool>,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    // Spawn the planning task in the background
+    let options = planning_agent::PlanningOptions {
+        draft: draft.unwrap_or(false),
+        replan: replan.unwrap_or(false),
+        expand: expand.unwrap_or(false),
+    };
+
+    // Only one planner may work on a task at a time
+    let guard = match planning_coordinator::register(task_id, options.mode())? {
+        planning_coordinator::Registration::Started(guard) => guard,
+        planning_coordinator::Registration::AlreadyRunning(_) if attach.unwrap_or(false) => {
+            // Progress and completion events are keyed by task id, so the caller
+            // only needs to keep listening
+            return Ok("Attached to planning already in progress".to_string());
+        }
+        planning_coordinator::Registration::AlreadyRunning(run) => {
+            return Err(format!(
+                "Planning is already in progress for task {} (started {})",
+                task_id, run.started_at
+            ));
+        }
+    };
+
+    spawn_task_planning(
+        task_id,
+        guard,
+        task_title,
+        task_description,
+        agents,
+        options,
+        None,
+        app_handle,
+    );
+
+    Ok("Task planning started".to_string())
+}
+
+/// Continue a planning run that was cut off when the app quit, from the state
+/// saved after its last iteration
+#[tauri::command]
+async fn resume_planning_run(run_id: i64, app_handle: tauri::AppHandle) -> Result<String, String> {
+    let point = planning_runs::load_resume_point(run_id).await?;
+    let task_id = point.task_id;
+
+    let guard = match planning_coordinator::register(task_id, point.state.options.mode())? {
+        planning_coordinator::Registration::Started(guard) => guard,
+        planning_coordinator::Registration::AlreadyRunning(run) => {
+            return Err(format!(
+                "Planning is already in progress for task {} (started {})",
+                task_id, run.started_at
+            ));
+        }
+    };
+
+    spawn_task_planning(
+        task_id,
+        guard,
+        point.state.task_title.clone(),
+        point.state.task_description.clone(),
+        String::new(),
+        point.state.options.clone(),
+        Some(point),
+        app_handle,
+    );
+
+    Ok("Task planning resumed".to_string())
+}
+
+/// Run planning in the background, holding the task's planning slot until it ends
+#[allow(clippy::too_many_arguments)]
+fn spawn_task_planning(
+    task_id: i32,
+    guard: planning_coordinator::RunGuard,
+    task_title: String,
+    task_description: Option<String>,
+    agents: String,
+    options: planning_agent::PlanningOptions,
+    resume: Option<planning_runs::ResumePoint>,
+    app_handle: tauri::AppHandle,
+) {
+    let run_token = guard.token();
     let app_handle_clone = app_handle.clone();
-    tokio::spawn(async move {
+    let handle = tokio::spawn(async move {
+        let _guard = guard;
         let app_handle_for_error = app_handle_clone.clone();
         if let Err(e) = execute_task_planning(
             task_id,
             task_title,
             task_description,
             agents,
+            options,
+            resume,
+            run_token,
             app_handle_clone,
         )
         .await
@@ -57,25 +179,64 @@ async fn start_task_planning(
                 message: "Task planning failed".to_string(),
                 subtasks_created: None,
                 error: Some(e),
+                proposed_plan_id: None,
+                changes: Vec::new(),
+                planning_run_id: None,
+                validation_issues: Vec::new(),
             };
             let _ = app_handle_for_error.emit("task-planning-complete", error_event);
         }
     });
 
-    Ok("Task planning started".to_string())
+    planning_coordinator::set_abort_handle(task_id, run_token, handle.abort_handle());
 }
 
+/// Cancel a running planning task. Planner changes are only written when a
+/// run succeeds, so aborting it leaves the task's subtasks untouched.
+#[tauri::command]
+async fn cancel_task_planning(
+    task_id: i32,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    match planning_coordinator::cancel(task_id)? {
+        Some(run) => {
+            // Only this run's row: an earlier or later run of the task is not touched
+            if let Some(planning_run_id) = run.planning_run_id {
+                planning_runs::mark_run_cancelled(planning_run_id).await?;
+            }
+            let event = PlanningCancelledEvent {
+                task_id,
+                message: "Task planning cancelled".to_string(),
+            };
+            app_handle
+                .emit("task-planning-cancelled", event)
+                .map_err(|e| format!("Failed to emit cancelled event: {}", e))?;
+            Ok("Task planning cancelled".to_string())
+        }
+        None => Err(format!("No planning run in progress for task {}", task_id)),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn execute_task_planning(
     task_id: i32,
     task_title: String,
     task_description: Option<String>,
     _agents: String, // DEPRECATED: agents now loaded from database
+    options: planning_agent::PlanningOptions,
+    resume: Option<planning_runs::ResumePoint>,
+    run_token: u64,
     app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
     use planning_agent::PlanningAgent;
 
     // Create planning agent instance
-    let planning_agent = PlanningAgent::new(app_handle.clone(), task_id).await?;
+    let planning_agent = match resume {
+        Some(point) => PlanningAgent::resume(app_handle.clone(), point).await?,
+        None => PlanningAgent::new(app_handle.clone(), task_id, options).await?,
+    }
+    .with_coordinator_token(run_token);
+    planning_coordinator::set_planning_run_id(task_id, run_token, planning_agent.run_id());
 
     // Execute AI-powered planning with fallback
     match planning_agent
@@ -90,6 +251,10 @@ async fn execute_task_planning(
                 message: result.message,
                 subtasks_created: Some(result.subtasks_created),
                 error: None,
+                proposed_plan_id: result.proposed_plan_id,
+                changes: result.changes,
+                planning_run_id: Some(planning_agent.run_id()),
+                validation_issues: result.validation_issues,
             };
             app_handle
                 .emit("task-planning-complete", complete_event)
@@ -104,6 +269,10 @@ async fn execute_task_planning(
                 message: "Planning failed".to_string(),
                 subtasks_created: None,
                 error: Some(e),
+                proposed_plan_id: None,
+                changes: Vec::new(),
+                planning_run_id: Some(planning_agent.run_id()),
+                validation_issues: Vec::new(),
             };
             app_handle
                 .emit("task-planning-complete", error_event)
@@ -189,10 +358,16 @@ async fn get_tasks_scheduled_for_date(
                due_date, scheduled_date, created_at, updated_at
         FROM tasks
         WHERE scheduled_date = ?
+           OR EXISTS (
+               SELECT 1 FROM subtasks
+               WHERE subtasks.task_id = tasks.id AND subtasks.completed = FALSE
+                 AND subtasks.scheduled_date = ?
+           )
         ORDER BY created_at DESC
         "#
     )
-    .bind(date)
+    .bind(&date)
+    .bind(&date)
     .fetch_all(pool)
     .await
     .map_err(|e| format!("Database error: {}", e))
@@ -511,6 +686,138 @@ pub fn run() {
             sql: include_str!("../migrations/026_add_updatable_prompt_section.sql"),
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 27,
+            description: "create_proposed_plans",
+            sql: include_str!("../migrations/027_create_proposed_plans.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 28,
+            description: "add_subtask_position",
+            sql: include_str!("../migrations/028_add_subtask_position.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 29,
+            description: "create_subtask_dependencies",
+            sql: include_str!("../migrations/029_create_subtask_dependencies.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 30,
+            description: "create_plan_templates",
+            sql: include_str!("../migrations/030_create_plan_templates.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 31,
+            description: "create_planning_runs",
+            sql: include_str!("../migrations/031_create_planning_runs.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 32,
+            description: "add_subtask_scheduling",
+            sql: include_str!("../migrations/032_add_subtask_scheduling.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 33,
+            description: "create_subtask_executions",
+            sql: include_str!("../migrations/033_create_subtask_executions.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 34,
+            description: "add_parent_subtask_id",
+            sql: include_str!("../migrations/034_add_parent_subtask_id.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 35,
+            description: "add_planning_run_resume_state",
+            sql: include_str!("../migrations/035_add_planning_run_resume_state.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 36,
+            description: "edit_lock_leases",
+            sql: include_str!("../migrations/036_edit_lock_leases.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 37,
+            description: "create_resource_locks",
+            sql: include_str!("../migrations/037_create_resource_locks.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 38,
+            description: "create_document_revisions",
+            sql: include_str!("../migrations/038_create_document_revisions.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 39,
+            description: "add_revision_attribution",
+            sql: include_str!("../migrations/039_add_revision_attribution.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 40,
+            description: "create_search_index",
+            sql: include_str!("../migrations/040_create_search_index.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 41,
+            description: "create_retrieval_index",
+            sql: include_str!("../migrations/041_create_retrieval_index.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 42,
+            description: "create_space_folders",
+            sql: include_str!("../migrations/042_create_space_folders.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 43,
+            description: "create_chalk_items",
+            sql: include_str!("../migrations/043_create_chalk_items.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 44,
+            description: "create_task_attachments",
+            sql: include_str!("../migrations/044_create_task_attachments.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 45,
+            description: "add_space_folder_extra_fields",
+            sql: include_str!("../migrations/045_add_space_folder_extra_fields.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 46,
+            description: "add_chalk_synced_content",
+            sql: include_str!("../migrations/046_add_chalk_synced_content.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 47,
+            description: "key_search_index_by_rowid",
+            sql: include_str!("../migrations/047_key_search_index_by_rowid.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 48,
+            description: "rebuild_retrieval_index",
+            sql: include_str!("../migrations/048_rebuild_retrieval_index.sql"),
+            kind: MigrationKind::Up,
+        },
     ];
 
     tauri::Builder::default()
@@ -536,23 +843,39 @@ pub fn run() {
                     // Non-fatal - the frontend SQL plugin will still work
                 }
 
-                // Clean up stale locks on startup (older than 5 minutes)
+                // Runs still marked as running were cut off by the last shutdown
+                if let Err(e) = planning_runs::mark_interrupted_runs().await {
+                    eprintln!("Warning: Failed to close interrupted planning runs: {}", e);
+                }
+                if let Err(e) = subtask_executor::recover_interrupted_executions().await {
+                    eprintln!("Warning: Failed to close interrupted subtask executions: {}", e);
+                }
+
+                // Clean up locks whose lease ran out while the app was closed
                 let app_handle = app.handle().clone();
-                if let Err(e) = edit_locks::cleanup_stale_locks(5, app_handle).await {
+                if let Err(e) = edit_locks::cleanup_stale_locks(app_handle).await {
                     eprintln!("Warning: Failed to cleanup stale locks: {}", e);
                 }
 
-                // Spawn background task to periodically clean up stale locks
+                // Spawn background task to periodically clean up expired locks
                 let app_handle_bg = app.handle().clone();
                 tokio::spawn(async move {
                     let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
                     loop {
                         interval.tick().await;
-                        if let Err(e) = edit_locks::cleanup_stale_locks(5, app_handle_bg.clone()).await {
+                        if let Err(e) = edit_locks::cleanup_stale_locks(app_handle_bg.clone()).await {
                             eprintln!("Warning: Background cleanup of stale locks failed: {}", e);
                         }
                     }
                 });
+
+                // Keep spaces bound to a folder in sync with their files
+                folder_sync::watch_space_folders(app.handle().clone());
+
+                // Attachments of deleted tasks leave their stored files behind
+                if let Err(e) = task_attachments::remove_orphaned_files(app.handle()).await {
+                    eprintln!("Warning: Failed to clean up attachment files: {}", e);
+                }
             });
 
             Ok(())
@@ -563,19 +886,82 @@ pub fn run() {
             settings::set_setting,
             settings::delete_setting,
             start_task_planning,
+            resume_planning_run,
+            cancel_task_planning,
+            planning_coordinator::get_active_planning_runs,
+            proposed_plans::get_proposed_plan,
+            proposed_plans::update_proposed_plan,
+            proposed_plans::approve_proposed_plan,
+            proposed_plans::discard_proposed_plan,
+            subtask_dependencies::get_subtask_dependencies,
+            subtask_dependencies::set_subtask_dependencies,
+            subtask_dependencies::get_subtask_topological_order,
+            subtask_dependencies::get_unblocked_subtasks,
+            subtask_scheduling::propose_subtask_schedule,
+            subtask_scheduling::set_subtask_schedule,
+            subtask_executor::run_subtask,
+            subtask_executor::run_unblocked_subtasks,
+            subtask_executor::cancel_subtask_execution,
+            subtask_executor::review_subtask_output,
+            subtask_executor::list_subtask_executions,
+            subtask_executor::get_subtask_execution,
+            subtask_tree::get_subtask_tree,
+            subtask_tree::get_subtask_subtree,
+            plan_templates::list_plan_templates,
+            plan_templates::create_plan_template,
+            plan_templates::update_plan_template,
+            plan_templates::delete_plan_template,
+            plan_templates::apply_plan_template,
+            planning_runs::list_planning_runs,
+            planning_runs::get_planning_run,
+            planning_runs::list_resumable_planning_runs,
+            planning_runs::discard_planning_run,
             get_available_models,
             resolve_model_id,
             check_model_supports_tools,
             edit_locks::acquire_edit_lock,
+            edit_locks::acquire_edit_lock_wait,
+            edit_locks::renew_edit_lock,
             edit_locks::release_edit_lock,
+            edit_locks::force_release_edit_lock,
             edit_locks::check_edit_lock,
             edit_locks::get_original_content,
             edit_locks::force_release_all_locks,
             edit_locks::cleanup_stale_locks,
             task_notes::read_task_notes,
             task_notes::write_task_notes,
+            task_notes::merge_task_notes,
+            task_attachments::add_task_attachment_file,
+            task_attachments::add_task_attachment_bytes,
+            task_attachments::list_task_attachments,
+            task_attachments::read_task_attachment,
+            task_attachments::open_task_attachment,
+            task_attachments::delete_task_attachment,
+            document_revisions::list_document_revisions,
+            document_revisions::get_document_revision,
+            document_revisions::diff_document_revisions,
+            document_revisions::restore_document_revision,
+            markdown_patch::list_markdown_headings,
+            markdown_patch::list_document_headings,
+            markdown_patch::patch_markdown,
+            markdown_patch::patch_document,
+            chalk::import_chalk_tasks,
+            chalk::export_chalk_tasks,
+            folder_sync::bind_space_folder,
+            folder_sync::unbind_space_folder,
+            folder_sync::set_space_folder_enabled,
+            folder_sync::get_space_folder,
+            folder_sync::sync_space_folder,
+            folder_sync::list_space_folder_conflicts,
+            folder_sync::resolve_space_folder_conflict,
+            retrieval::retrieve_related_knowledge,
+            search::search,
             space_context::read_space_context,
             space_context::write_space_context,
+            resource_locks::acquire_resource_lock,
+            resource_locks::release_resource_lock,
+            resource_locks::check_resource_lock,
+            agents::update_agent_prompt_section,
             request_calendar_permission,
             get_calendar_list,
             get_events_for_date,
@@ -0,0 +1,381 @@
+use crate::document_revisions::{self, RevisionAttribution};
+use crate::edit_locks::ensure_notes_writable;
+use crate::resource_locks::{self, ensure_writable};
+use crate::text_diff::{is_fence, unified_diff, DiffStats};
+use serde::{Deserialize, Serialize};
+
+/// Lines of unchanged context around each change in a patch diff
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// An ATX heading and the extent of its section
+#[derive(Debug, Serialize, Clone)]
+pub struct MarkdownHeading {
+    pub level: usize,
+    pub title: String,
+    /// Titles of the enclosing headings, ending with this one
+    pub path: Vec<String>,
+    /// 1-based line of the heading
+    pub line: usize,
+    #[serde(skip)]
+    start: usize,
+    // End of the heading's own text, before its first subsection
+    #[serde(skip)]
+    body_end: usize,
+    // End of the whole section, subsections included
+    #[serde(skip)]
+    end: usize,
+}
+
+/// An edit to a Markdown document. Sections are found by heading path: the
+/// titles of a heading and its ancestors, outermost first. A path may leave
+/// out leading ancestors as long as it matches only one heading.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PatchOperation {
+    /// Replace everything under the heading, subsections included
+    ReplaceSection { heading_path: Vec<String>, content: String },
+    /// Insert after the section and its subsections, e.g. a new sibling section
+    InsertAfterSection { heading_path: Vec<String>, content: String },
+    /// Add to the end of the heading's own text, before any subsection
+    AppendToSection { heading_path: Vec<String>, content: String },
+    /// Replace every occurrence of `find`, failing unless there are exactly
+    /// `expected_count` of them
+    FindReplace { find: String, replace: String, expected_count: usize },
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PatchResult {
+    pub content: String,
+    /// Unified diff from the old document to the new one
+    pub diff: String,
+    pub stats: DiffStats,
+    /// Whether the document was saved (false for previews and plain text)
+    pub written: bool,
+}
+
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_end_matches(['\n', '\r']);
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    // Closing #s are not part of the title
+    let title = rest.trim().trim_end_matches('#').trim_end().to_string();
+    Some((level, title))
+}
+
+/// Headings of a Markdown document in order, ignoring fenced code blocks
+pub fn headings(content: &str) -> Vec<MarkdownHeading> {
+    let mut headings: Vec<MarkdownHeading> = Vec::new();
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut offset = 0;
+    let mut in_fence = false;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        if is_fence(line) {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            if let Some((level, title)) = parse_heading(line) {
+                path.retain(|(l, _)| *l < level);
+                path.push((level, title.clone()));
+                headings.push(MarkdownHeading {
+                    level,
+                    title,
+                    path: path.iter().map(|(_, t)| t.clone()).collect(),
+                    line: index + 1,
+                    start: offset,
+                    body_end: content.len(),
+                    end: content.len(),
+                });
+            }
+        }
+        offset += line.len();
+    }
+
+    for i in 0..headings.len() {
+        if let Some(next) = headings.get(i + 1) {
+            headings[i].body_end = next.start;
+        }
+        let level = headings[i].level;
+        if let Some(next) = headings[i + 1..].iter().find(|h| h.level <= level) {
+            headings[i].end = next.start;
+        }
+    }
+    headings
+}
+
+fn same_title(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+fn find_section<'a>(headings: &'a [MarkdownHeading], heading_path: &[String]) -> Result<&'a MarkdownHeading, String> {
+    if heading_path.is_empty() {
+        return Err("heading_path cannot be empty".to_string());
+    }
+    let wanted = heading_path.join(" > ");
+
+    let matches: Vec<&MarkdownHeading> = headings
+        .iter()
+        .filter(|h| {
+            h.path.len() >= heading_path.len()
+                && h.path[h.path.len() - heading_path.len()..]
+                    .iter()
+                    .zip(heading_path)
+                    .all(|(a, b)| same_title(a, b))
+        })
+        .collect();
+
+    match matches.as_slice() {
+        [heading] => Ok(heading),
+        [] => Err(format!(
+            "No heading matches '{}'. Headings: {}",
+            wanted,
+            headings
+                .iter()
+                .map(|h| h.path.join(" > "))
+                .collect::<Vec<_>>()
+                .join("; ")
+        )),
+        several => Err(format!(
+            "'{}' matches {} headings (lines {}); give more of the path",
+            wanted,
+            several.len(),
+            several.iter().map(|h| h.line.to_string()).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// Join three pieces of a document with exactly one blank line between each
+/// non-empty piece
+fn join_blocks(prefix: &str, block: &str, suffix: &str) -> String {
+    let suffix = suffix.trim_start_matches('\n');
+    let parts: Vec<&str> = [prefix.trim_end_matches('\n'), block.trim_matches('\n'), suffix]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect();
+    let mut joined = parts.join("\n\n");
+    // The suffix keeps its own ending
+    if suffix.is_empty() && !joined.is_empty() {
+        joined.push('\n');
+    }
+    joined
+}
+
+/// Apply one patch operation to a Markdown document
+pub fn apply_patch(content: &str, operation: &PatchOperation) -> Result<String, String> {
+    match operation {
+        PatchOperation::ReplaceSection { heading_path, content: text } => {
+            let headings = headings(content);
+            let section = find_section(&headings, heading_path)?;
+            let heading_end = content[section.start..]
+                .find('\n')
+                .map_or(content.len(), |i| section.start + i + 1);
+            Ok(join_blocks(&content[..heading_end], text, &content[section.end..]))
+        }
+        PatchOperation::InsertAfterSection { heading_path, content: text } => {
+            let headings = headings(content);
+            let section = find_section(&headings, heading_path)?;
+            Ok(join_blocks(&content[..section.end], text, &content[section.end..]))
+        }
+        PatchOperation::AppendToSection { heading_path, content: text } => {
+            let headings = headings(content);
+            let section = find_section(&headings, heading_path)?;
+            Ok(join_blocks(&content[..section.body_end], text, &content[section.body_end..]))
+        }
+        PatchOperation::FindReplace { find, replace, expected_count } => {
+            if find.is_empty() {
+                return Err("find cannot be empty".to_string());
+            }
+            let count = content.matches(find.as_str()).count();
+            if count != *expected_count {
+                return Err(format!(
+                    "Expected {} match(es) of {:?} but found {}; nothing was changed",
+                    expected_count, find, count
+                ));
+            }
+            Ok(content.replace(find.as_str(), replace))
+        }
+    }
+}
+
+fn patch_result(before: &str, after: String, written: bool) -> PatchResult {
+    let (diff, stats) = unified_diff(before, &after, DIFF_CONTEXT_LINES);
+    PatchResult {
+        content: after,
+        diff,
+        stats,
+        written,
+    }
+}
+
+async fn read_document(document_type: &str, document_id: i64) -> Result<String, String> {
+    match document_type {
+        document_revisions::TASK_NOTES => crate::task_notes::read_task_notes(document_id as i32).await,
+        document_revisions::SPACE_CONTEXT => {
+            crate::space_context::read_space_context(document_id as i32).await
+        }
+        other => Err(format!("Cannot patch documents of type '{}'", other)),
+    }
+}
+
+/// Headings of a Markdown string
+#[tauri::command]
+pub async fn list_markdown_headings(content: String) -> Result<Vec<MarkdownHeading>, String> {
+    Ok(headings(&content))
+}
+
+/// Headings of a stored document ('task_notes' or 'space_context')
+#[tauri::command]
+pub async fn list_document_headings(
+    document_type: String,
+    document_id: i64,
+) -> Result<Vec<MarkdownHeading>, String> {
+    let content = read_document(&document_type, document_id).await?;
+    Ok(headings(&content))
+}
+
+/// Apply a patch to a Markdown string without saving anything
+#[tauri::command]
+pub async fn patch_markdown(content: String, operation: PatchOperation) -> Result<PatchResult, String> {
+    let patched = apply_patch(&content, &operation)?;
+    Ok(patch_result(&content, patched, false))
+}
+
+/// Apply a patch to a stored document and save it as a new revision, unless
+/// `dry_run` is set. Writes respect the task notes lease or space context lock
+/// held by anyone but `owner_id`, and fail if the document changed meanwhile.
+#[tauri::command]
+pub async fn patch_document(
+    document_type: String,
+    document_id: i64,
+    operation: PatchOperation,
+    owner_id: Option<String>,
+    attribution: Option<RevisionAttribution>,
+    dry_run: Option<bool>,
+) -> Result<PatchResult, String> {
+    let attribution = attribution.unwrap_or_else(RevisionAttribution::user);
+    attribution.validate()?;
+
+    let content = read_document(&document_type, document_id).await?;
+    let patched = apply_patch(&content, &operation)?;
+    if dry_run.unwrap_or(false) || patched == content {
+        return Ok(patch_result(&content, patched, false));
+    }
+
+    // Only saved over the content the patch was applied to
+    match document_type.as_str() {
+        document_revisions::TASK_NOTES => {
+            ensure_notes_writable(document_id, owner_id.as_deref()).await?;
+            crate::task_notes::replace_task_notes(document_id as i32, Some(&content), &patched, &attribution, None)
+                .await?;
+        }
+        // read_document has already rejected any other type
+        _ => {
+            ensure_writable(resource_locks::SPACE_CONTEXT, &document_id.to_string(), owner_id.as_deref()).await?;
+            crate::space_context::replace_space_context(
+                document_id as i32,
+                Some(&content),
+                &patched,
+                &attribution,
+                None,
+            )
+            .await?;
+        }
+    }
+    Ok(patch_result(&content, patched, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# Plan\nIntro\n\n## Tasks\nFirst\n\n### Details\nSmall print\n\n## Notes\nSee below\n\n```md\n# Not a heading\n```\n";
+
+    fn path(titles: &[&str]) -> Vec<String> {
+        titles.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn headings_skip_fenced_code() {
+        let found = headings(DOC);
+        let paths: Vec<String> = found.iter().map(|h| h.path.join(" > ")).collect();
+        assert_eq!(paths, vec!["Plan", "Plan > Tasks", "Plan > Tasks > Details", "Plan > Notes"]);
+        assert_eq!(found.iter().map(|h| h.line).collect::<Vec<_>>(), vec![1, 4, 7, 10]);
+        assert_eq!(headings("#Tag\n## Closed ##\n")[0].title, "Closed");
+    }
+
+    #[test]
+    fn partial_paths_must_match_one_heading() {
+        let doc = "# One\n## Setup\na\n# Two\n## Setup\nb\n";
+        let replace = |titles: &[&str]| {
+            apply_patch(
+                doc,
+                &PatchOperation::ReplaceSection {
+                    heading_path: path(titles),
+                    content: "new".to_string(),
+                },
+            )
+        };
+
+        let error = replace(&["setup"]).unwrap_err();
+        assert!(error.contains("matches 2 headings (lines 2, 5)"), "{}", error);
+        assert_eq!(replace(&["two", "Setup"]).unwrap(), "# One\n## Setup\na\n# Two\n## Setup\n\nnew\n");
+        assert!(replace(&["Three"]).unwrap_err().starts_with("No heading matches 'Three'"));
+        assert!(replace(&[]).is_err());
+    }
+
+    #[test]
+    fn append_goes_before_subsections() {
+        let patched = apply_patch(
+            DOC,
+            &PatchOperation::AppendToSection {
+                heading_path: path(&["Tasks"]),
+                content: "Second\n".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(patched.contains("## Tasks\nFirst\n\nSecond\n\n### Details\n"), "{}", patched);
+    }
+
+    #[test]
+    fn insert_after_section_follows_its_subsections() {
+        let patched = apply_patch(
+            DOC,
+            &PatchOperation::InsertAfterSection {
+                heading_path: path(&["Tasks"]),
+                content: "## Risks\nNone".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(patched.contains("Small print\n\n## Risks\nNone\n\n## Notes\n"), "{}", patched);
+    }
+
+    #[test]
+    fn find_replace_requires_the_expected_count() {
+        let replace = |expected_count| {
+            apply_patch(
+                "a b a",
+                &PatchOperation::FindReplace {
+                    find: "a".to_string(),
+                    replace: "c".to_string(),
+                    expected_count,
+                },
+            )
+        };
+        assert_eq!(replace(2).unwrap(), "c b c");
+        let error = replace(1).unwrap_err();
+        assert!(error.contains("Expected 1 match(es) of \"a\" but found 2"), "{}", error);
+    }
+
+    #[test]
+    fn join_blocks_leaves_one_blank_line_between_parts() {
+        assert_eq!(join_blocks("a\n\n\n", "\nb\n", "\n\nc\n"), "a\n\nb\n\nc\n");
+        assert_eq!(join_blocks("a", "b", ""), "a\n\nb\n");
+        assert_eq!(join_blocks("", "b", ""), "b\n");
+        assert_eq!(join_blocks("a\n", "", "c"), "a\n\nc");
+    }
+}
@@ -0,0 +1,338 @@
+use crate::planning_agent::PlanChange;
+use crate::settings::get_db_pool;
+use crate::staged_plan::{StagedOperation, StagedPlan};
+use crate::subtask_scheduling::{task_deadline, SubtaskSchedule};
+use chrono::Local;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlanTemplateItem {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    // Preferred agent; falls back to round-robin if missing or no longer available
+    #[serde(default)]
+    pub agent_id: Option<i32>,
+    // 1-based positions of earlier items in the template that must finish first
+    #[serde(default)]
+    pub depends_on: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlanTemplate {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub space_id: Option<i64>,
+    pub task_type: Option<String>,
+    pub items: Vec<PlanTemplateItem>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewPlanTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub space_id: Option<i64>,
+    pub task_type: Option<String>,
+    pub items: Vec<PlanTemplateItem>,
+}
+
+/// Generic plan used when no template matches the task
+pub fn default_fallback_items() -> Vec<PlanTemplateItem> {
+    vec![
+        PlanTemplateItem {
+            title: "Research and plan approach".to_string(),
+            description: "Gather requirements, research best practices, and develop a comprehensive execution plan".to_string(),
+            agent_id: None,
+            depends_on: Vec::new(),
+        },
+        PlanTemplateItem {
+            title: "Execute primary deliverables".to_string(),
+            description: "Complete the main task deliverables according to the researched plan and requirements".to_string(),
+            agent_id: None,
+            depends_on: vec![1],
+        },
+        PlanTemplateItem {
+            title: "Review and finalize output".to_string(),
+            description: "Quality check, refinements, and final validation of deliverables".to_string(),
+            agent_id: None,
+            depends_on: vec![2],
+        },
+    ]
+}
+
+const TEMPLATE_COLUMNS: &str =
+    "id, name, description, space_id, task_type, items, created_at, updated_at";
+
+fn row_to_template(row: &sqlx::sqlite::SqliteRow) -> Result<PlanTemplate, String> {
+    let items_json: String = row
+        .try_get("items")
+        .map_err(|e| format!("Failed to extract items: {}", e))?;
+    let items: Vec<PlanTemplateItem> = serde_json::from_str(&items_json)
+        .map_err(|e| format!("Failed to parse plan template items: {}", e))?;
+
+    Ok(PlanTemplate {
+        id: row.try_get("id").map_err(|e| format!("Failed to extract id: {}", e))?,
+        name: row.try_get("name").map_err(|e| format!("Failed to extract name: {}", e))?,
+        description: row.try_get("description").unwrap_or(None),
+        space_id: row.try_get("space_id").unwrap_or(None),
+        task_type: row.try_get("task_type").unwrap_or(None),
+        items,
+        created_at: row.try_get("created_at").unwrap_or_default(),
+        updated_at: row.try_get("updated_at").unwrap_or_default(),
+    })
+}
+
+fn validate_template(template: &NewPlanTemplate) -> Result<String, String> {
+    if template.name.trim().is_empty() {
+        return Err("Template name cannot be empty".to_string());
+    }
+    if template.items.is_empty() {
+        return Err("Template must contain at least one subtask".to_string());
+    }
+    for (index, item) in template.items.iter().enumerate() {
+        if item.title.trim().is_empty() {
+            return Err(format!("Template subtask {} has no title", index + 1));
+        }
+        if let Some(dep) = item.depends_on.iter().find(|d| **d < 1 || **d > index as i64) {
+            return Err(format!(
+                "Template subtask {} can only depend on earlier subtasks (got {})",
+                index + 1,
+                dep
+            ));
+        }
+    }
+
+    serde_json::to_string(&template.items)
+        .map_err(|e| format!("Failed to serialize template items: {}", e))
+}
+
+async fn fetch_template(template_id: i64) -> Result<PlanTemplate, String> {
+    let pool = get_db_pool()?;
+
+    let row = sqlx::query(&format!("SELECT {} FROM plan_templates WHERE id = ?", TEMPLATE_COLUMNS))
+        .bind(template_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Plan template {} not found", template_id))?;
+
+    row_to_template(&row)
+}
+
+/// Find the most specific template for a task: space and task type, then
+/// space only, then task type only, then a global template
+pub async fn find_template_for_task(task_id: i32) -> Result<Option<PlanTemplate>, String> {
+    let pool = get_db_pool()?;
+
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM plan_templates pt
+         WHERE (pt.space_id IS NULL OR pt.space_id = (SELECT space_id FROM tasks WHERE id = ?))
+           AND (pt.task_type IS NULL OR pt.task_type = (SELECT task_type FROM tasks WHERE id = ?))
+         ORDER BY (pt.space_id IS NOT NULL) * 2 + (pt.task_type IS NOT NULL) DESC, pt.updated_at DESC
+         LIMIT 1",
+        TEMPLATE_COLUMNS
+    ))
+    .bind(task_id)
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    row.map(|row| row_to_template(&row)).transpose()
+}
+
+/// Values for `{{task.title}}`-style placeholders
+pub async fn template_variables(task_id: i32) -> Result<HashMap<String, String>, String> {
+    let pool = get_db_pool()?;
+
+    let row = sqlx::query(
+        "SELECT t.title, t.description, t.priority, t.due_date, t.task_type, s.title AS space_title
+         FROM tasks t JOIN spaces s ON s.id = t.space_id
+         WHERE t.id = ?",
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Task {} not found", task_id))?;
+
+    let get = |column: &str| -> String {
+        row.try_get::<Option<String>, _>(column)
+            .unwrap_or(None)
+            .unwrap_or_default()
+    };
+
+    let mut vars = HashMap::new();
+    vars.insert("task.id".to_string(), task_id.to_string());
+    vars.insert("task.title".to_string(), get("title"));
+    vars.insert("task.description".to_string(), get("description"));
+    vars.insert("task.priority".to_string(), get("priority"));
+    vars.insert("task.due_date".to_string(), get("due_date"));
+    vars.insert("task.type".to_string(), get("task_type"));
+    vars.insert("space.title".to_string(), get("space_title"));
+    Ok(vars)
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\{\{\s*([a-z_.]+)\s*\}\}").expect("valid regex"))
+}
+
+/// Replace `{{name}}` placeholders; unknown placeholders are left as they are
+pub fn render(text: &str, vars: &HashMap<String, String>) -> String {
+    placeholder_regex().replace_all(text, |caps: &regex::Captures| {
+        vars.get(&caps[1])
+            .cloned()
+            .unwrap_or_else(|| caps[0].to_string())
+    })
+    .to_string()
+}
+
+pub fn render_items(
+    items: &[PlanTemplateItem],
+    vars: &HashMap<String, String>,
+) -> Vec<PlanTemplateItem> {
+    items
+        .iter()
+        .map(|item| PlanTemplateItem {
+            title: render(&item.title, vars),
+            description: render(&item.description, vars),
+            agent_id: item.agent_id,
+            depends_on: item.depends_on.clone(),
+        })
+        .collect()
+}
+
+/// Use the item's agent if it is still available, otherwise assign round-robin
+pub fn resolve_agent(item: &PlanTemplateItem, index: usize, agent_ids: &[i32]) -> Option<i32> {
+    match item.agent_id {
+        Some(id) if agent_ids.contains(&id) => Some(id),
+        _ if agent_ids.is_empty() => None,
+        _ => Some(agent_ids[index % agent_ids.len()]),
+    }
+}
+
+/// List templates usable in a space (space-specific and global), or all templates
+#[tauri::command]
+pub async fn list_plan_templates(space_id: Option<i64>) -> Result<Vec<PlanTemplate>, String> {
+    let pool = get_db_pool()?;
+
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM plan_templates
+         WHERE ? IS NULL OR space_id IS NULL OR space_id = ?
+         ORDER BY name ASC",
+        TEMPLATE_COLUMNS
+    ))
+    .bind(space_id)
+    .bind(space_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    rows.iter().map(row_to_template).collect()
+}
+
+#[tauri::command]
+pub async fn create_plan_template(template: NewPlanTemplate) -> Result<PlanTemplate, String> {
+    let items_json = validate_template(&template)?;
+    let pool = get_db_pool()?;
+
+    let result = sqlx::query(
+        "INSERT INTO plan_templates (name, description, space_id, task_type, items, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+    )
+    .bind(&template.name)
+    .bind(&template.description)
+    .bind(template.space_id)
+    .bind(&template.task_type)
+    .bind(&items_json)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create plan template: {}", e))?;
+
+    fetch_template(result.last_insert_rowid()).await
+}
+
+#[tauri::command]
+pub async fn update_plan_template(
+    template_id: i64,
+    template: NewPlanTemplate,
+) -> Result<PlanTemplate, String> {
+    let items_json = validate_template(&template)?;
+    let pool = get_db_pool()?;
+
+    let result = sqlx::query(
+        "UPDATE plan_templates SET name = ?, description = ?, space_id = ?, task_type = ?, items = ?,
+         updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(&template.name)
+    .bind(&template.description)
+    .bind(template.space_id)
+    .bind(&template.task_type)
+    .bind(&items_json)
+    .bind(template_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update plan template: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Plan template {} not found", template_id));
+    }
+
+    fetch_template(template_id).await
+}
+
+#[tauri::command]
+pub async fn delete_plan_template(template_id: i64) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query("DELETE FROM plan_templates WHERE id = ?")
+        .bind(template_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete plan template: {}", e))?;
+
+    Ok(())
+}
+
+/// Create a task's subtasks from a template in one transaction
+#[tauri::command]
+pub async fn apply_plan_template(task_id: i32, template_id: i64) -> Result<Vec<PlanChange>, String> {
+    let template = fetch_template(template_id).await?;
+    let vars = template_variables(task_id).await?;
+    let pool = get_db_pool()?;
+
+    let agent_ids: Vec<i32> =
+        sqlx::query_scalar("SELECT id FROM agents WHERE system_role IS NULL ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to load agents: {}", e))?;
+
+    let mut staged = StagedPlan::new();
+    for (index, item) in render_items(&template.items, &vars).into_iter().enumerate() {
+        let agent_id = resolve_agent(&item, index, &agent_ids)
+            .ok_or("No agents available to assign template subtasks to")?;
+        staged.push(StagedOperation::Create {
+            step: index as i64 + 1,
+            title: item.title,
+            description: item.description,
+            agent_id,
+            depends_on: item.depends_on,
+            parent: None,
+            schedule: SubtaskSchedule::default(),
+        });
+    }
+
+    if let Some(deadline) = task_deadline(task_id).await? {
+        staged.spread_due_dates(deadline, Local::now().date_naive())?;
+    }
+
+    staged.commit(task_id).await
+}
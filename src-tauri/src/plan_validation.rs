@@ -0,0 +1,366 @@
+use crate::database::Agent;
+use crate::settings::get_db_pool;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+// Defaults for the plan_* settings
+const DEFAULT_MIN_SUBTASKS: usize = 3;
+const DEFAULT_MAX_SUBTASKS: usize = 7;
+const DEFAULT_DUPLICATE_THRESHOLD: f64 = 0.75;
+
+// An agent is only suggested instead of the assigned one when at least this
+// many of its keywords appear in the subtask
+const MIN_BETTER_AGENT_MATCHES: usize = 2;
+
+// Characters of a word compared when matching keywords ("reviewing" ~ "review")
+const STEM_LENGTH: usize = 6;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "the", "of", "for", "to", "in", "on", "with", "by", "from", "into", "at",
+    "or", "its", "is", "be", "as", "that", "this",
+];
+
+// Words too common in agent prompts and subtask descriptions to say anything about fit
+const GENERIC_WORDS: &[&str] = &[
+    "about", "agent", "also", "any", "based", "best", "complete", "create", "each", "ensure",
+    "every", "final", "from", "have", "help", "make", "more", "need", "other", "should", "some",
+    "subtask", "task", "tasks", "that", "their", "them", "then", "they", "this", "user", "users",
+    "using", "when", "will", "with", "work", "your",
+];
+
+/// Limits a finished plan is checked against
+#[derive(Debug, Clone)]
+pub struct PlanRules {
+    pub min_subtasks: usize,
+    pub max_subtasks: usize,
+    /// Title similarity (0-1) at or above which two subtasks count as duplicates
+    pub duplicate_threshold: f64,
+}
+
+/// A subtask as it will look once the plan is applied
+#[derive(Debug, Clone)]
+pub struct PlanItem {
+    /// How to refer to the subtask in feedback, e.g. "step 2" or "subtask 14"
+    pub label: String,
+    pub title: String,
+    pub description: String,
+    pub agent_id: Option<i32>,
+    pub top_level: bool,
+    /// Created by this run rather than already on the task
+    pub created: bool,
+    /// An existing subtask this run changes
+    pub updated: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PlanIssue {
+    pub kind: String, // 'duplicate', 'too_few', 'too_many', 'agent_fit'
+    pub message: String,
+}
+
+impl PlanIssue {
+    fn new(kind: &str, message: String) -> Self {
+        Self {
+            kind: kind.to_string(),
+            message,
+        }
+    }
+}
+
+/// Load plan rules from the `plan_min_subtasks`, `plan_max_subtasks` and
+/// `plan_duplicate_threshold` settings
+pub async fn load_rules() -> Result<PlanRules, String> {
+    let pool = get_db_pool()?;
+
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT key, value FROM settings
+         WHERE key IN ('plan_min_subtasks', 'plan_max_subtasks', 'plan_duplicate_threshold')",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    let values: HashMap<String, String> = rows.into_iter().collect();
+
+    let min_subtasks = values
+        .get("plan_min_subtasks")
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_MIN_SUBTASKS);
+    let max_subtasks = values
+        .get("plan_max_subtasks")
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|max| *max >= min_subtasks)
+        .unwrap_or(DEFAULT_MAX_SUBTASKS.max(min_subtasks));
+    let duplicate_threshold = values
+        .get("plan_duplicate_threshold")
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|t: &f64| *t > 0.0 && *t <= 1.0)
+        .unwrap_or(DEFAULT_DUPLICATE_THRESHOLD);
+
+    Ok(PlanRules {
+        min_subtasks,
+        max_subtasks,
+        duplicate_threshold,
+    })
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !STOP_WORDS.contains(w))
+        .map(|w| w.to_string())
+        .collect()
+}
+
+fn bigrams(text: &str) -> HashMap<(char, char), usize> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut counts = HashMap::new();
+    for pair in chars.windows(2) {
+        *counts.entry((pair[0], pair[1])).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Similarity of two titles between 0 and 1: the Dice coefficient of the
+/// character bigrams of their distinct, sorted words, so word order and
+/// filler words do not matter
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let normalize = |title: &str| {
+        let mut words: Vec<String> = words(title);
+        words.sort();
+        words.dedup();
+        words.join(" ")
+    };
+    let (a, b) = (bigrams(&normalize(a)), bigrams(&normalize(b)));
+
+    let total: usize = a.values().sum::<usize>() + b.values().sum::<usize>();
+    if total == 0 {
+        return 0.0;
+    }
+    let shared: usize = a
+        .iter()
+        .map(|(pair, count)| (*count).min(b.get(pair).copied().unwrap_or(0)))
+        .sum();
+    2.0 * shared as f64 / total as f64
+}
+
+fn keywords(text: &str) -> HashSet<String> {
+    words(text)
+        .into_iter()
+        .filter(|w| w.len() >= 4 && !GENERIC_WORDS.contains(&w.as_str()))
+        .map(|w| w.chars().take(STEM_LENGTH).collect())
+        .collect()
+}
+
+/// Check a finished plan. `check_count` is off when the run only adds nested
+/// subtasks, since the top-level plan is not being changed.
+pub fn validate_plan(
+    items: &[PlanItem],
+    agents: &[Agent],
+    rules: &PlanRules,
+    check_count: bool,
+) -> Vec<PlanIssue> {
+    let mut issues = Vec::new();
+
+    for (index, item) in items.iter().enumerate() {
+        for other in &items[index + 1..] {
+            // Overlap between subtasks the task already had is not this run's doing
+            if !item.created && !other.created {
+                continue;
+            }
+            if title_similarity(&item.title, &other.title) >= rules.duplicate_threshold {
+                issues.push(PlanIssue::new(
+                    "duplicate",
+                    format!(
+                        "{} '{}' and {} '{}' look like the same work",
+                        item.label, item.title, other.label, other.title
+                    ),
+                ));
+            }
+        }
+    }
+
+    if check_count {
+        let count = items.iter().filter(|item| item.top_level).count();
+        if count < rules.min_subtasks {
+            issues.push(PlanIssue::new(
+                "too_few",
+                format!(
+                    "The plan has {} top-level subtasks; it should have at least {}",
+                    count, rules.min_subtasks
+                ),
+            ));
+        } else if count > rules.max_subtasks {
+            issues.push(PlanIssue::new(
+                "too_many",
+                format!(
+                    "The plan has {} top-level subtasks; it should have at most {}",
+                    count, rules.max_subtasks
+                ),
+            ));
+        }
+    }
+
+    // Agent fit only means something when there is another agent to choose
+    if agents.len() > 1 {
+        let agent_keywords: Vec<(&Agent, HashSet<String>)> = agents
+            .iter()
+            .map(|agent| {
+                let text = format!("{} {}", agent.name, agent.agent_prompt);
+                (agent, keywords(&text))
+            })
+            .collect();
+
+        // Assignments the run did not make are the user's, and the planner
+        // may have no tool to change them
+        for item in items.iter().filter(|item| item.created || item.updated) {
+            let Some(agent_id) = item.agent_id else { continue };
+            let subtask_keywords = keywords(&format!("{} {}", item.title, item.description));
+            let score = |words: &HashSet<String>| subtask_keywords.intersection(words).count();
+
+            let assigned = agent_keywords.iter().find(|(agent, _)| agent.id == agent_id);
+            let Some((assigned, assigned_words)) = assigned else { continue };
+            if score(assigned_words) > 0 {
+                continue;
+            }
+
+            let best = agent_keywords
+                .iter()
+                .filter(|(agent, _)| agent.id != agent_id)
+                .map(|(agent, words)| (agent, score(words)))
+                .max_by_key(|(_, score)| *score);
+            if let Some((better, matches)) = best.filter(|(_, m)| *m >= MIN_BETTER_AGENT_MATCHES) {
+                issues.push(PlanIssue::new(
+                    "agent_fit",
+                    format!(
+                        "{} '{}' is assigned to {} (ID {}), but nothing in it matches that agent's description; {} (ID {}) matches {} of its keywords",
+                        item.label, item.title, assigned.name, assigned.id, better.name, better.id, matches
+                    ),
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+/// User turn asking the planner to fix the problems found in its plan
+pub fn correction_message(issues: &[PlanIssue]) -> String {
+    let list = issues
+        .iter()
+        .map(|issue| format!("- {}", issue.message))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "The plan has the following problems:\n\n{}\n\nPlease fix them with the available tools. Use discard_subtask to drop subtasks created in this run (then create_subtask to recreate one with a different agent), merge duplicates into a single subtask, and keep the number of subtasks within the limits. Reply without tool calls when the plan is done.",
+        list
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> PlanRules {
+        PlanRules {
+            min_subtasks: 1,
+            max_subtasks: 5,
+            duplicate_threshold: DEFAULT_DUPLICATE_THRESHOLD,
+        }
+    }
+
+    fn item(label: &str, title: &str, created: bool) -> PlanItem {
+        PlanItem {
+            label: label.to_string(),
+            title: title.to_string(),
+            description: String::new(),
+            agent_id: None,
+            top_level: true,
+            created,
+            updated: false,
+        }
+    }
+
+    fn agent(id: i32, name: &str, prompt: &str) -> Agent {
+        Agent {
+            id,
+            name: name.to_string(),
+            model_name: "model".to_string(),
+            agent_prompt: prompt.to_string(),
+            system_role: None,
+        }
+    }
+
+    fn kinds(issues: &[PlanIssue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.kind.as_str()).collect()
+    }
+
+    #[test]
+    fn title_similarity_ignores_word_order_and_filler() {
+        assert_eq!(title_similarity("Write the tests", "tests write"), 1.0);
+        assert_eq!(title_similarity("", ""), 0.0);
+        assert!(title_similarity("Write tests", "Deploy to production") < 0.3);
+    }
+
+    #[test]
+    fn duplicates_need_a_subtask_created_in_this_run() {
+        let items = vec![
+            item("Subtask 1", "Write unit tests", false),
+            item("Subtask 2", "Write the unit tests", false),
+        ];
+        assert!(validate_plan(&items, &[], &rules(), false).is_empty());
+
+        let items = vec![
+            item("Subtask 1", "Write unit tests", false),
+            item("Step 1", "Write the unit tests", true),
+        ];
+        let issues = validate_plan(&items, &[], &rules(), false);
+        assert_eq!(kinds(&issues), vec!["duplicate"]);
+        assert!(issues[0].message.contains("Subtask 1") && issues[0].message.contains("Step 1"));
+    }
+
+    #[test]
+    fn counts_only_top_level_subtasks() {
+        let mut nested = item("Step 2", "Profile database queries", true);
+        nested.top_level = false;
+        let items = vec![item("Step 1", "Design the schema", true), nested];
+        let rules = PlanRules {
+            min_subtasks: 2,
+            ..rules()
+        };
+        assert_eq!(kinds(&validate_plan(&items, &[], &rules, true)), vec!["too_few"]);
+        assert!(validate_plan(&items, &[], &rules, false).is_empty());
+    }
+
+    #[test]
+    fn suggests_an_agent_that_fits_better() {
+        let agents = vec![
+            agent(1, "Writer", "Drafts blog posts and marketing copy"),
+            agent(2, "Tester", "Runs regression suites and reports failing builds"),
+        ];
+        let mut misassigned = item("Step 1", "Run regression suites", true);
+        misassigned.description = "Report failing builds".to_string();
+        misassigned.agent_id = Some(1);
+        let issues = validate_plan(&[misassigned.clone()], &agents, &rules(), false);
+        assert_eq!(kinds(&issues), vec!["agent_fit"]);
+
+        misassigned.agent_id = Some(2);
+        assert!(validate_plan(&[misassigned], &agents, &rules(), false).is_empty());
+    }
+
+    #[test]
+    fn leaves_agents_of_untouched_subtasks_alone() {
+        let agents = vec![
+            agent(1, "Writer", "Drafts blog posts and marketing copy"),
+            agent(2, "Tester", "Runs regression suites and reports failing builds"),
+        ];
+        let mut existing = item("Subtask 7", "Run regression suites", false);
+        existing.description = "Report failing builds".to_string();
+        existing.agent_id = Some(1);
+        assert!(validate_plan(&[existing.clone()], &agents, &rules(), false).is_empty());
+
+        existing.updated = true;
+        let issues = validate_plan(&[existing], &agents, &rules(), false);
+        assert_eq!(kinds(&issues), vec!["agent_fit"]);
+    }
+}
@@ -2,15 +2,31 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::Row;
 use crate::chat::{send_chat_message, ChatMessage};
-use crate::database::Agent;
+use crate::database::{Agent, SubTask, SUBTASK_COLUMNS};
+use crate::plan_templates;
+use crate::plan_validation::{self, PlanIssue};
+use crate::planning_coordinator;
+use crate::planning_runs::{self, ResumePoint, ResumeState, TokenUsage};
+use crate::proposed_plans::{save_proposed_plan, ProposedPlan};
+use crate::planning_context::{
+    gather_planning_context, list_space_tasks, PlanningContext, DEFAULT_CONTEXT_TOKEN_BUDGET,
+};
+use crate::search::{format_for_agent, search, SearchFilters};
 use crate::settings::get_db_pool;
+use crate::space_context::read_space_context;
+use crate::staged_plan::{StagedOperation, StagedPlan, SubtaskRef};
+use crate::subtask_scheduling::{parse_schedule_input, task_deadline};
+use crate::subtask_tree::{build_tree, completed_descendants, depth_of, SubtaskNode, MAX_SUBTASK_DEPTH};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use chrono::{Local, NaiveDate};
 use tauri::Emitter;
 
 #[derive(Debug, Deserialize)]
 pub struct ClaudeResponse {
     pub content: Vec<ContentBlock>,
     pub stop_reason: String,
-    #[allow(dead_code)]
     pub usage: Option<Usage>,
 }
 
@@ -28,7 +44,6 @@ pub enum ContentBlock {
 }
 
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
 pub struct Usage {
     pub input_tokens: u32,
     pub output_tokens: u32,
@@ -39,6 +54,54 @@ pub struct PlanningResult {
     pub success: bool,
     pub subtasks_created: i32,
     pub message: String,
+    pub proposed_plan_id: Option<i64>,
+    pub changes: Vec<PlanChange>,
+    // Problems still present after the correction turns ran out
+    pub validation_issues: Vec<PlanIssue>,
+}
+
+/// Options controlling how a planning run treats the task's subtasks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlanningOptions {
+    /// Collect subtasks into a proposed plan for user approval instead of inserting them
+    pub draft: bool,
+    /// Revise the existing subtasks instead of appending a fresh plan
+    pub replan: bool,
+    /// Break existing subtasks that are too large into nested subtasks
+    pub expand: bool,
+}
+
+impl PlanningOptions {
+    /// Name of the planning mode as recorded in the run history
+    pub fn mode(&self) -> &'static str {
+        if self.draft {
+            "draft"
+        } else if self.replan {
+            "replan"
+        } else if self.expand {
+            "expand"
+        } else {
+            "standard"
+        }
+    }
+}
+
+/// A single change made to the task's subtasks during a planning run
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlanChange {
+    pub action: String, // 'created', 'updated', 'deleted', 'reordered'
+    pub subtask_id: Option<i64>,
+    pub title: String,
+}
+
+/// Conversation and counters the tool use loop starts from
+struct LoopStart {
+    system_prompt: String,
+    messages: Vec<ChatMessage>,
+    usage: TokenUsage,
+    // Model turns and correction turns already used
+    iterations: usize,
+    correction_turns: usize,
 }
 
 pub struct PlanningAgent {
@@ -47,6 +110,23 @@ pub struct PlanningAgent {
     agent_prompt: String,
     model_name: String,
     available_agents: Vec<Agent>,
+    options: PlanningOptions,
+    // Space context, task notes and user knowledge for the task's space
+    context: PlanningContext,
+    // Subtasks that existed when the run started (only loaded in replan and expand modes)
+    existing_subtasks: Vec<SubTask>,
+    // Tool calls are staged here and only applied once the run succeeds
+    // (or saved as a proposed plan in draft mode)
+    staged: Mutex<StagedPlan>,
+    // Task due date that subtask due dates are planned back from
+    deadline: Option<NaiveDate>,
+    // Row in planning_runs recording this run
+    run_id: i64,
+    used_fallback: AtomicBool,
+    // Saved loop state to continue from, when resuming an interrupted run
+    resume_point: Mutex<Option<ResumePoint>>,
+    // Coordinator registration the run's progress is recorded under
+    coordinator_token: Option<u64>,
 }
 
 impl PlanningAgent {
@@ -54,7 +134,44 @@ impl PlanningAgent {
     pub async fn new(
         app: tauri::AppHandle,
         task_id: i32,
+        options: PlanningOptions,
     ) -> Result<Self, String> {
+        Self::create(app, task_id, options, None).await
+    }
+
+    /// Pick an interrupted run back up where its last iteration left off
+    pub async fn resume(app: tauri::AppHandle, point: ResumePoint) -> Result<Self, String> {
+        let run_id = point.run_id;
+        let agent =
+            Self::create(app, point.task_id, point.state.options.clone(), Some(run_id)).await?;
+
+        *agent
+            .staged
+            .lock()
+            .map_err(|_| "Staged plan lock poisoned".to_string())? = point.state.staged.clone();
+        *agent
+            .resume_point
+            .lock()
+            .map_err(|_| "Resume state lock poisoned".to_string())? = Some(point);
+
+        // Claim the run last, so a failed resume leaves it resumable
+        planning_runs::reopen_run(run_id).await?;
+        Ok(agent)
+    }
+
+    async fn create(
+        app: tauri::AppHandle,
+        task_id: i32,
+        options: PlanningOptions,
+        resume_run_id: Option<i64>,
+    ) -> Result<Self, String> {
+        if options.draft && options.replan {
+            return Err("Draft mode cannot be combined with replanning".to_string());
+        }
+        if options.expand && options.replan {
+            return Err("Expanding subtasks cannot be combined with replanning".to_string());
+        }
+
         let pool = get_db_pool()?;
 
         // Load planning agent from database by system_role
@@ -88,18 +205,56 @@ impl PlanningAgent {
             });
         }
 
+        // Replanning and expanding need to show the model what already exists
+        let existing_subtasks = if options.replan || options.expand {
+            sqlx::query_as::<_, SubTask>(&format!(
+                "SELECT {} FROM subtasks WHERE task_id = ? ORDER BY position ASC, created_at ASC",
+                SUBTASK_COLUMNS
+            ))
+            .bind(task_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to load existing subtasks: {}", e))?
+        } else {
+            Vec::new()
+        };
+
+        let context = gather_planning_context(task_id, DEFAULT_CONTEXT_TOKEN_BUDGET).await?;
+
+        let deadline = task_deadline(task_id).await?;
+
+        let run_id = match resume_run_id {
+            Some(run_id) => run_id,
+            None => planning_runs::start_run(task_id, &model_name, options.mode()).await?,
+        };
+
         Ok(Self {
             app,
             task_id,
             agent_prompt,
             model_name,
             available_agents,
+            options,
+            context,
+            existing_subtasks,
+            staged: Mutex::new(StagedPlan::new()),
+            deadline,
+            run_id,
+            used_fallback: AtomicBool::new(false),
+            resume_point: Mutex::new(None),
+            coordinator_token: None,
         })
     }
 
+    /// Record progress in the planning coordinator under this registration
+    pub fn with_coordinator_token(mut self, token: u64) -> Self {
+        self.coordinator_token = Some(token);
+        self
+    }
+
     /// Get tool definitions for the planning agent (Claude API tool-use format)
     fn get_tool_schemas(&self) -> Vec<serde_json::Value> {
-        vec![json!({
+        let mut tools = vec![json!({
             "name": "create_subtask",
             "description": "Create a new subtask for the task being planned",
             "input_schema": {
@@ -120,11 +275,168 @@ impl PlanningAgent {
                     "agent_id": {
                         "type": "number",
                         "description": "ID of the agent best suited for this subtask"
+                    },
+                    "depends_on": {
+                        "type": "array",
+                        "items": { "type": "number" },
+                        "description": "Step numbers of subtasks created earlier in this run that must be completed before this one can start"
+                    },
+                    "parent_step": {
+                        "type": "number",
+                        "description": "Step number of a subtask created earlier in this run to nest this subtask under"
+                    },
+                    "parent_subtask_id": {
+                        "type": "number",
+                        "description": "ID of an existing subtask to nest this subtask under"
+                    },
+                    "estimated_minutes": {
+                        "type": "number",
+                        "description": "Estimated effort in minutes"
+                    },
+                    "due_days_before_deadline": {
+                        "type": "number",
+                        "description": "How many days before the task's due date this subtask should be finished (0 = on the due date). Only when the task has a due date."
+                    },
+                    "priority": {
+                        "type": "string",
+                        "enum": ["low", "medium", "high"],
+                        "description": "Priority of the subtask"
                     }
                 },
                 "required": ["task_id", "title", "description", "agent_id"]
             }
-        })]
+        })];
+
+        // Read-only workspace tools
+        tools.push(json!({
+            "name": "read_space_context",
+            "description": "Read the full context document of the space this task belongs to (goals, constraints, decisions)",
+            "input_schema": {
+                "type": "object",
+                "properties": {}
+            }
+        }));
+        tools.push(json!({
+            "name": "list_space_tasks",
+            "description": "List the other tasks in this task's space with their status, priority and due date",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "status": {
+                        "type": "string",
+                        "description": "Only list tasks with this status ('todo', 'in_progress' or 'done')"
+                    }
+                }
+            }
+        }));
+        tools.push(json!({
+            "name": "search_workspace",
+            "description": "Full-text search over tasks, subtasks, task notes and space context, e.g. to find similar past work",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Words to search for"
+                    },
+                    "all_spaces": {
+                        "type": "boolean",
+                        "description": "Search every space instead of only this task's space"
+                    },
+                    "status": {
+                        "type": "string",
+                        "description": "Only results from tasks with this status ('todo', 'in_progress' or 'done')"
+                    }
+                },
+                "required": ["query"]
+            }
+        }));
+        tools.push(json!({
+            "name": "discard_subtask",
+            "description": "Discard a subtask created earlier in this run, e.g. a duplicate",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "step": {
+                        "type": "number",
+                        "description": "Step number of the subtask to discard"
+                    }
+                },
+                "required": ["step"]
+            }
+        }));
+
+        if self.options.replan {
+            tools.push(json!({
+                "name": "update_subtask",
+                "description": "Update the title, description or assigned agent of an existing subtask",
+                "input_schema": {
+                    "type": "object",
+                    "properties": {
+                        "subtask_id": {
+                            "type": "number",
+                            "description": "ID of the existing subtask to update"
+                        },
+                        "title": {
+                            "type": "string",
+                            "description": "New title (omit to keep the current one)"
+                        },
+                        "description": {
+                            "type": "string",
+                            "description": "New description (omit to keep the current one)"
+                        },
+                        "agent_id": {
+                            "type": "number",
+                            "description": "ID of the agent to reassign the subtask to (omit to keep the current one)"
+                        }
+                    },
+                    "required": ["subtask_id"]
+                }
+            }));
+            tools.push(json!({
+                "name": "delete_subtask",
+                "description": "Delete an existing subtask that is no longer needed. Completed subtasks cannot be deleted.",
+                "input_schema": {
+                    "type": "object",
+                    "properties": {
+                        "subtask_id": {
+                            "type": "number",
+                            "description": "ID of the subtask to delete"
+                        }
+                    },
+                    "required": ["subtask_id"]
+                }
+            }));
+            tools.push(json!({
+                "name": "reorder_subtasks",
+                "description": "Set the order of the task's subtasks. Subtasks not listed keep their relative order after the listed ones.",
+                "input_schema": {
+                    "type": "object",
+                    "properties": {
+                        "order": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "subtask_id": {
+                                        "type": "number",
+                                        "description": "ID of an existing subtask"
+                                    },
+                                    "step": {
+                                        "type": "number",
+                                        "description": "Step number of a subtask created in this run"
+                                    }
+                                }
+                            },
+                            "description": "Subtasks in the desired order, each given by subtask_id or step"
+                        }
+                    },
+                    "required": ["order"]
+                }
+            }));
+        }
+
+        tools
     }
 
     /// Build system prompt with agent context
@@ -141,14 +453,130 @@ impl PlanningAgent {
             .collect::<Vec<_>>()
             .join("\n\n");
 
-        format!(
-            "{}\n\n## Current Planning Task\n\n**Task ID**: {}\n**Title**: {}\n**Description**: {}\n\n## Available Agents for Assignment\n\n{}\n\n## Instructions\n\nAnalyze this task and create appropriate subtasks using the create_subtask tool. Each subtask should have a clear title, detailed description, and be assigned to the most suitable agent based on their capabilities.",
+        let mut prompt = format!(
+            "{}\n\n## Current Planning Task\n\n**Task ID**: {}\n**Title**: {}\n**Description**: {}\n\n## Available Agents for Assignment\n\n{}",
             self.agent_prompt,
             self.task_id,
             task_title,
             task_description.as_deref().unwrap_or("No description provided"),
             agents_context
-        )
+        );
+
+        if !self.context.prompt_section.is_empty() {
+            prompt.push_str(&format!(
+                "\n\n## Workspace Context\n\nUse this to tailor the plan to the space's goals and constraints. Call read_space_context or list_space_tasks if you need more detail.\n\n{}",
+                self.context.prompt_section
+            ));
+        }
+
+        if !self.context.related_knowledge.is_empty() {
+            prompt.push_str(&format!(
+                "\n\n## Related Knowledge\n\nExcerpts from other tasks and spaces that may be relevant. Reuse what applies and ignore the rest.\n\n{}",
+                self.context.related_knowledge
+            ));
+        }
+
+        prompt.push_str(&self.scheduling_instructions());
+
+        if self.options.replan {
+            prompt.push_str(&format!(
+                "\n\n## Existing Subtasks\n\n{}\n\n## Instructions\n\nThis task already has a plan. Revise it to fit the current title and description rather than starting over. Use update_subtask to adjust subtasks that are still relevant, delete_subtask to remove ones that are obsolete, create_subtask only for work that is not yet covered, and reorder_subtasks if the order should change. Never duplicate an existing subtask, and leave completed subtasks in place.{}",
+                self.format_existing_subtasks(),
+                DEPENDENCY_INSTRUCTIONS
+            ));
+        } else if self.options.expand {
+            prompt.push_str(&format!(
+                "\n\n## Existing Subtasks\n\n{}\n\n## Instructions\n\nBreak down the existing subtasks that are too large for one agent to finish in a single sitting. For each such subtask, call create_subtask with its parent_subtask_id to add smaller child subtasks. If a child you created is still too large, break it down further by passing its step number as parent_step. Subtasks can be nested at most {} levels deep. Leave subtasks that are already small enough, and completed subtasks, alone.{}",
+                self.format_existing_subtasks(),
+                MAX_SUBTASK_DEPTH,
+                DEPENDENCY_INSTRUCTIONS
+            ));
+        } else {
+            prompt.push_str("\n\n## Instructions\n\nAnalyze this task and create appropriate subtasks using the create_subtask tool. Each subtask should have a clear title, detailed description, and be assigned to the most suitable agent based on their capabilities.");
+            prompt.push_str(DEPENDENCY_INSTRUCTIONS);
+        }
+
+        prompt
+    }
+
+    /// Prompt section asking for effort, priority and due dates relative to the deadline
+    fn scheduling_instructions(&self) -> String {
+        match self.deadline {
+            Some(deadline) => format!(
+                "\n\n## Scheduling\n\nThe task is due on {} (today is {}). For each subtask give estimated_minutes and a priority, and plan backward from the due date: set due_days_before_deadline so that later steps have time to finish after the work they depend on.",
+                deadline.format("%Y-%m-%d"),
+                Local::now().format("%Y-%m-%d")
+            ),
+            None => "\n\n## Scheduling\n\nThe task has no due date. For each subtask give estimated_minutes and a priority.".to_string(),
+        }
+    }
+
+    /// Render the existing subtasks as a checklist for the system prompt, with
+    /// nested subtasks indented under their parent
+    fn format_existing_subtasks(&self) -> String {
+        if self.existing_subtasks.is_empty() {
+            return "None yet.".to_string();
+        }
+
+        let mut lines = Vec::new();
+        for node in build_tree(&self.existing_subtasks) {
+            self.format_subtask_node(&node, 0, &mut lines);
+        }
+        lines.join("\n")
+    }
+
+    fn format_subtask_node(&self, node: &SubtaskNode, level: usize, lines: &mut Vec<String>) {
+        let subtask = &node.subtask;
+        let indent = "  ".repeat(level);
+        let agent = subtask
+            .agent_id
+            .and_then(|id| self.available_agents.iter().find(|a| a.id as i64 == id))
+            .map(|a| format!("{} (ID {})", a.name, a.id))
+            .unwrap_or_else(|| "Unassigned".to_string());
+        lines.push(format!(
+            "{}- [{}] **Subtask ID {}**: {} — Agent: {}\n{}  {}",
+            indent,
+            if subtask.completed { "x" } else { " " },
+            subtask.id,
+            subtask.title,
+            agent,
+            indent,
+            subtask.description.as_deref().unwrap_or("No description")
+        ));
+        for child in &node.children {
+            self.format_subtask_node(child, level + 1, lines);
+        }
+    }
+
+    fn validate_agent(&self, agent_id: i32) -> Result<(), String> {
+        if !self.available_agents.iter().any(|a| a.id == agent_id) {
+            return Err(format!("Invalid agent_id: {}", agent_id));
+        }
+        Ok(())
+    }
+
+    /// Load a subtask and make sure it belongs to the task being planned
+    async fn load_own_subtask(&self, subtask_id: i64) -> Result<SubTask, String> {
+        let pool = get_db_pool()?;
+
+        let subtask = sqlx::query_as::<_, SubTask>(&format!(
+            "SELECT {} FROM subtasks WHERE id = ?",
+            SUBTASK_COLUMNS
+        ))
+        .bind(subtask_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Subtask {} not found", subtask_id))?;
+
+        if subtask.task_id != self.task_id as i64 {
+            return Err(format!("Subtask {} does not belong to task {}", subtask_id, self.task_id));
+        }
+        if self.is_staged_for_deletion(subtask_id) {
+            return Err(format!("Subtask {} has already been deleted in this run", subtask_id));
+        }
+
+        Ok(subtask)
     }
 
     /// Execute create_subtask tool call
@@ -164,32 +592,243 @@ impl PlanningAgent {
         let agent_id: i32 = input["agent_id"]
             .as_i64()
             .ok_or("Missing agent_id")? as i32;
+        let depends_on: Vec<i64> = match input["depends_on"].as_array() {
+            Some(values) => values
+                .iter()
+                .map(|v| v.as_i64().ok_or_else(|| "depends_on must contain step numbers".to_string()))
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        let schedule = parse_schedule_input(&input, self.deadline)?;
+        let parent_step = input["parent_step"].as_i64();
+        let parent_subtask_id = input["parent_subtask_id"].as_i64();
 
-        // Validate agent exists
-        if !self
-            .available_agents
-            .iter()
-            .any(|a| a.id == agent_id)
-        {
-            return Err(format!("Invalid agent_id: {}", agent_id));
+        self.validate_agent(agent_id)?;
+        if let Some(id) = parent_subtask_id {
+            if !self.existing_subtasks.iter().any(|s| s.id == id) {
+                return Err(format!("parent_subtask_id {} is not an existing subtask of this task", id));
+            }
+            if self.is_staged_for_deletion(id) {
+                return Err(format!("Subtask {} has already been deleted in this run", id));
+            }
         }
 
-        // Insert into database
-        let pool = get_db_pool()?;
+        let mut staged = self
+            .staged
+            .lock()
+            .map_err(|_| "Staged plan lock poisoned".to_string())?;
+        let step = staged.next_step();
+        let steps = staged.steps();
+        if let Some(dep) = depends_on.iter().find(|d| !steps.contains(d)) {
+            return Err(format!(
+                "depends_on step {} does not refer to a subtask created earlier in this run",
+                dep
+            ));
+        }
 
-        sqlx::query(
-            "INSERT INTO subtasks (task_id, title, description, agent_id, completed, created_at, updated_at)
-             VALUES (?, ?, ?, ?, FALSE, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
-        )
-        .bind(self.task_id)
-        .bind(&title)
-        .bind(&description)
-        .bind(agent_id)
-        .execute(pool)
-        .await
-        .map_err(|e| format!("Failed to create subtask: {}", e))?;
+        let parent = match (parent_subtask_id, parent_step) {
+            (Some(_), Some(_)) => {
+                return Err("Give either parent_step or parent_subtask_id, not both".to_string())
+            }
+            (Some(id), None) => Some(SubtaskRef::Existing(id)),
+            (None, Some(parent)) if steps.contains(&parent) => Some(SubtaskRef::Step(parent)),
+            (None, Some(parent)) => {
+                return Err(format!(
+                    "parent_step {} does not refer to a subtask created earlier in this run",
+                    parent
+                ))
+            }
+            (None, None) => None,
+        };
+
+        // The new subtask sits one level below its parent
+        let mut depth = 1;
+        let mut ancestor = parent;
+        while let Some(reference) = ancestor {
+            match reference {
+                SubtaskRef::Existing(id) => {
+                    depth += depth_of(&self.existing_subtasks, id);
+                    break;
+                }
+                SubtaskRef::Step(parent) => {
+                    depth += 1;
+                    ancestor = staged.parent_of_step(parent);
+                }
+            }
+        }
+        if depth > MAX_SUBTASK_DEPTH {
+            return Err(format!(
+                "Subtasks can be nested at most {} levels deep",
+                MAX_SUBTASK_DEPTH
+            ));
+        }
+
+        staged.push(StagedOperation::Create {
+            step,
+            title: title.clone(),
+            description,
+            agent_id,
+            depends_on,
+            schedule,
+            parent,
+        });
+
+        if self.options.draft {
+            return Ok(format!("Added subtask to the proposed plan (step {}): '{}'", step, title));
+        }
+        Ok(format!("Successfully created subtask (step {}): '{}'", step, title))
+    }
+
+    fn stage(&self, operation: StagedOperation) -> Result<(), String> {
+        self.staged
+            .lock()
+            .map_err(|_| "Staged plan lock poisoned".to_string())?
+            .push(operation);
+        Ok(())
+    }
+
+    fn is_staged_for_deletion(&self, subtask_id: i64) -> bool {
+        self.staged
+            .lock()
+            .map(|staged| staged.is_deleted(subtask_id))
+            .unwrap_or(false)
+    }
+
+    /// Execute discard_subtask tool call
+    async fn execute_discard_subtask(&self, input: serde_json::Value) -> Result<String, String> {
+        let step = input["step"].as_i64().ok_or("Missing step")?;
+        let title = self
+            .staged
+            .lock()
+            .map_err(|_| "Staged plan lock poisoned".to_string())?
+            .discard_step(step)?;
+
+        Ok(format!("Discarded subtask (step {}): '{}'", step, title))
+    }
+
+    /// Check the plan as it stands and list what the planner should fix
+    fn validate_staged(&self, rules: &plan_validation::PlanRules) -> Result<Vec<PlanIssue>, String> {
+        let items = self
+            .staged
+            .lock()
+            .map_err(|_| "Staged plan lock poisoned".to_string())?
+            .plan_items(&self.existing_subtasks);
 
-        Ok(format!("Successfully created subtask: '{}'", title))
+        Ok(plan_validation::validate_plan(
+            &items,
+            &self.available_agents,
+            rules,
+            !self.options.expand,
+        ))
+    }
+
+    /// Execute read_space_context tool call (read-only)
+    async fn execute_read_space_context(&self) -> Result<String, String> {
+        let content = read_space_context(self.context.space_id as i32).await?;
+        if content.trim().is_empty() {
+            return Ok(format!("The space '{}' has no context document yet.", self.context.space_title));
+        }
+        Ok(content)
+    }
+
+    /// Execute search_workspace tool call (read-only)
+    async fn execute_search_workspace(&self, input: serde_json::Value) -> Result<String, String> {
+        let query = input["query"].as_str().ok_or("Missing query")?;
+        let filters = SearchFilters {
+            space_id: (!input["all_spaces"].as_bool().unwrap_or(false)).then_some(self.context.space_id),
+            status: input["status"].as_str().map(|s| s.to_string()),
+            limit: Some(10),
+            ..Default::default()
+        };
+        let results = search(query.to_string(), Some(filters)).await?;
+        Ok(format_for_agent(&results))
+    }
+
+    /// Execute update_subtask tool call (replan mode)
+    async fn execute_update_subtask(&self, input: serde_json::Value) -> Result<String, String> {
+        let subtask_id = input["subtask_id"]
+            .as_i64()
+            .ok_or("Missing subtask_id")?;
+        let existing = self.load_own_subtask(subtask_id).await?;
+
+        let title = input["title"].as_str().map(|t| t.to_string());
+        let description = input["description"].as_str().map(|d| d.to_string());
+        let agent_id = match input["agent_id"].as_i64() {
+            Some(id) => {
+                self.validate_agent(id as i32)?;
+                Some(id as i32)
+            }
+            None => None,
+        };
+
+        let display_title = title.clone().unwrap_or(existing.title);
+        self.stage(StagedOperation::Update {
+            subtask_id,
+            title,
+            description,
+            agent_id,
+            display_title: display_title.clone(),
+        })?;
+
+        Ok(format!("Successfully updated subtask {}: '{}'", subtask_id, display_title))
+    }
+
+    /// Execute delete_subtask tool call (replan mode)
+    async fn execute_delete_subtask(&self, input: serde_json::Value) -> Result<String, String> {
+        let subtask_id = input["subtask_id"]
+            .as_i64()
+            .ok_or("Missing subtask_id")?;
+        let existing = self.load_own_subtask(subtask_id).await?;
+
+        if existing.completed {
+            return Err(format!("Subtask {} is completed and cannot be deleted", subtask_id));
+        }
+        let completed = completed_descendants(get_db_pool()?, subtask_id).await?;
+        if !completed.is_empty() {
+            return Err(format!(
+                "Subtask {} has completed subtasks under it ({}) and cannot be deleted",
+                subtask_id,
+                completed.join(", ")
+            ));
+        }
+
+        self.stage(StagedOperation::Delete {
+            subtask_id,
+            title: existing.title.clone(),
+        })?;
+
+        Ok(format!("Successfully deleted subtask {}: '{}'", subtask_id, existing.title))
+    }
+
+    /// Execute reorder_subtasks tool call (replan mode)
+    async fn execute_reorder_subtasks(&self, input: serde_json::Value) -> Result<String, String> {
+        let entries = input["order"].as_array().ok_or("Missing order")?;
+        let steps = self
+            .staged
+            .lock()
+            .map_err(|_| "Staged plan lock poisoned".to_string())?
+            .steps();
+
+        let mut order = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let reference = match (entry["subtask_id"].as_i64(), entry["step"].as_i64()) {
+                (Some(id), _) => {
+                    self.load_own_subtask(id).await?;
+                    SubtaskRef::Existing(id)
+                }
+                (None, Some(step)) if steps.contains(&step) => SubtaskRef::Step(step),
+                (None, Some(step)) => {
+                    return Err(format!("Step {} does not refer to a subtask created in this run", step))
+                }
+                (None, None) => return Err("Each order entry needs a subtask_id or step".to_string()),
+            };
+            order.push(reference);
+        }
+
+        let count = order.len();
+        self.stage(StagedOperation::Reorder { order })?;
+
+        Ok(format!("Successfully reordered {} subtasks", count))
     }
 
     /// Emit progress event
@@ -207,6 +846,9 @@ impl PlanningAgent {
             "progress": progress.clamp(0.0, 1.0),
             "current_step": current_step
         });
+        if let Some(token) = self.coordinator_token {
+            planning_coordinator::record_progress(self.task_id, token, &event);
+        }
 
         self.app
             .emit("task-planning-progress", event)
@@ -219,29 +861,134 @@ impl PlanningAgent {
         task_title: String,
         task_description: Option<String>,
     ) -> Result<PlanningResult, String> {
+        let rules = plan_validation::load_rules().await?;
+        let resume_point = self
+            .resume_point
+            .lock()
+            .map_err(|_| "Resume state lock poisoned".to_string())?
+            .take();
+
+        if let Some(point) = resume_point {
+            self.emit_progress("resuming", "Resuming interrupted planning...", 0.2, Some("Resume"))
+                .await?;
+            let start = LoopStart {
+                system_prompt: point.system_prompt,
+                messages: point.transcript,
+                usage: point.usage,
+                iterations: point.state.iterations,
+                correction_turns: point.state.correction_turns,
+            };
+            return self.run_loop(&task_title, &task_description, &rules, start).await;
+        }
+
         self.emit_progress("analyzing", "Initializing AI planning agent...", 0.1, Some("Initialization"))
             .await?;
 
         let system_prompt = self.build_system_prompt(&task_title, &task_description);
 
-        let user_message = "Please analyze this task and create a comprehensive breakdown using the create_subtask tool. Create 3-7 subtasks that cover the complete workflow, and assign each to the most appropriate agent.".to_string();
+        let user_message = if self.options.replan {
+            "The requirements for this task may have changed. Please review the existing subtasks and revise the plan using the available tools so that it covers the complete workflow without duplicates.".to_string()
+        } else if self.options.expand {
+            "Please review the existing subtasks and break down any that are too large into nested subtasks using the create_subtask tool.".to_string()
+        } else {
+            format!("Please analyze this task and create a comprehensive breakdown using the create_subtask tool. Create {}-{} subtasks that cover the complete workflow, and assign each to the most appropriate agent.", rules.min_subtasks, rules.max_subtasks)
+        };
 
         // Build initial messages
-        let mut conversation_messages: Vec<ChatMessage> = vec![ChatMessage {
-            role: "user".to_string(),
-            content: serde_json::Value::String(user_message),
-        }];
+        let start = LoopStart {
+            system_prompt,
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: serde_json::Value::String(user_message),
+            }],
+            usage: TokenUsage::default(),
+            iterations: 0,
+            correction_turns: 0,
+        };
+
+        self.emit_progress("planning", "AI agent analyzing task...", 0.2, Some("Analysis"))
+            .await?;
+
+        self.run_loop(&task_title, &task_description, &rules, start).await
+    }
+
+    /// Loop state to save with the transcript
+    fn resume_state(
+        &self,
+        task_title: &str,
+        task_description: &Option<String>,
+        iterations: usize,
+        correction_turns: usize,
+    ) -> Result<ResumeState, String> {
+        Ok(ResumeState {
+            task_title: task_title.to_string(),
+            task_description: task_description.clone(),
+            options: self.options.clone(),
+            staged: self
+                .staged
+                .lock()
+                .map_err(|_| "Staged plan lock poisoned".to_string())?
+                .clone(),
+            iterations,
+            correction_turns,
+        })
+    }
 
+    /// Tool use loop, starting from a fresh conversation or from the saved state
+    /// of an interrupted run
+    async fn run_loop(
+        &self,
+        task_title: &str,
+        task_description: &Option<String>,
+        rules: &plan_validation::PlanRules,
+        start: LoopStart,
+    ) -> Result<PlanningResult, String> {
         let mcp_tools = self.get_tool_schemas();
-        let mut subtasks_created = 0;
-        let mut tool_use_iterations = 0;
+        let LoopStart {
+            system_prompt,
+            messages: mut conversation_messages,
+            mut usage,
+            iterations: mut tool_use_iterations,
+            mut correction_turns,
+        } = start;
+        let mut subtasks_created = self
+            .staged
+            .lock()
+            .map_err(|_| "Staged plan lock poisoned".to_string())?
+            .steps()
+            .len();
         const MAX_ITERATIONS: usize = 20;
+        const MAX_CORRECTION_TURNS: usize = 2;
 
-        self.emit_progress("planning", "AI agent analyzing task...", 0.2, Some("Analysis"))
-            .await?;
+        let validation_issues = loop {
+            // The model has answered without tool calls, so the plan is done;
+            // send problems with it back for another pass
+            if conversation_messages.last().is_some_and(|m| m.role == "assistant") {
+                let issues = self.validate_staged(rules)?;
+                if issues.is_empty() || correction_turns >= MAX_CORRECTION_TURNS {
+                    break issues;
+                }
+                correction_turns += 1;
+
+                self.emit_progress(
+                    "validating",
+                    &format!("Plan has {} issues, asking the agent to revise...", issues.len()),
+                    0.8,
+                    Some("Validation"),
+                )
+                .await?;
+
+                conversation_messages.push(ChatMessage {
+                    role: "user".to_string(),
+                    content: serde_json::Value::String(plan_validation::correction_message(&issues)),
+                });
+                let state =
+                    self.resume_state(task_title, task_description, tool_use_iterations, correction_turns)?;
+                planning_runs::record_transcript(self.run_id, &system_prompt, &conversation_messages, usage, &state)
+                    .await?;
+                continue;
+            }
 
-        // Tool use loop
-        loop {
             tool_use_iterations += 1;
             if tool_use_iterations > MAX_ITERATIONS {
                 return Err(format!("Planning exceeded maximum iterations ({})", MAX_ITERATIONS));
@@ -262,10 +1009,23 @@ impl PlanningAgent {
             let response: ClaudeResponse = serde_json::from_str(&response_text)
                 .map_err(|e| format!("Failed to parse Claude response: {}", e))?;
 
+            if let Some(response_usage) = &response.usage {
+                usage.input_tokens += response_usage.input_tokens as i64;
+                usage.output_tokens += response_usage.output_tokens as i64;
+            }
+
             // Check stop reason
             if response.stop_reason == "end_turn" {
                 // Agent finished planning
-                break;
+                conversation_messages.push(ChatMessage {
+                    role: "assistant".to_string(),
+                    content: serde_json::json!(response.content),
+                });
+                let state =
+                    self.resume_state(task_title, task_description, tool_use_iterations, correction_turns)?;
+                planning_runs::record_transcript(self.run_id, &system_prompt, &conversation_messages, usage, &state)
+                    .await?;
+                continue;
             }
 
             if response.stop_reason != "tool_use" {
@@ -294,43 +1054,61 @@ impl PlanningAgent {
             // Execute tool calls
             let mut tool_results = Vec::new();
             for (tool_id, tool_name, tool_input) in tool_calls {
-                if tool_name == "create_subtask" {
-                    match self.execute_create_subtask(tool_input).await {
-                        Ok(result) => {
+                let outcome = match tool_name.as_str() {
+                    "create_subtask" => self.execute_create_subtask(tool_input).await,
+                    "discard_subtask" => self.execute_discard_subtask(tool_input).await,
+                    "read_space_context" => self.execute_read_space_context().await,
+                    "list_space_tasks" => {
+                        list_space_tasks(self.context.space_id, tool_input["status"].as_str()).await
+                    }
+                    "search_workspace" => self.execute_search_workspace(tool_input).await,
+                    "update_subtask" if self.options.replan => self.execute_update_subtask(tool_input).await,
+                    "delete_subtask" if self.options.replan => self.execute_delete_subtask(tool_input).await,
+                    "reorder_subtasks" if self.options.replan => self.execute_reorder_subtasks(tool_input).await,
+                    _ => {
+                        tool_results.push(json!({
+                            "type": "tool_result",
+                            "tool_use_id": tool_id,
+                            "is_error": true,
+                            "content": format!("Unknown tool: {}", tool_name)
+                        }));
+                        continue;
+                    }
+                };
+
+                match outcome {
+                    Ok(result) => {
+                        if tool_name == "create_subtask" {
                             subtasks_created += 1;
 
                             // Update progress
                             let progress = 0.2 + (0.6 * (subtasks_created as f32 / 5.0)).min(0.6);
                             self.emit_progress(
                                 "creating",
-                                &format!("Created subtask {} of estimated 3-7...", subtasks_created),
+                                &format!(
+                                    "Created subtask {} of estimated {}-{}...",
+                                    subtasks_created, rules.min_subtasks, rules.max_subtasks
+                                ),
                                 progress,
                                 Some("Subtask Creation"),
                             )
                             .await?;
-
-                            tool_results.push(json!({
-                                "type": "tool_result",
-                                "tool_use_id": tool_id,
-                                "content": result
-                            }));
-                        }
-                        Err(e) => {
-                            tool_results.push(json!({
-                                "type": "tool_result",
-                                "tool_use_id": tool_id,
-                                "is_error": true,
-                                "content": format!("Tool execution error: {}", e)
-                            }));
                         }
+
+                        tool_results.push(json!({
+                            "type": "tool_result",
+                            "tool_use_id": tool_id,
+                            "content": result
+                        }));
+                    }
+                    Err(e) => {
+                        tool_results.push(json!({
+                            "type": "tool_result",
+                            "tool_use_id": tool_id,
+                            "is_error": true,
+                            "content": format!("Tool execution error: {}", e)
+                        }));
                     }
-                } else {
-                    tool_results.push(json!({
-                        "type": "tool_result",
-                        "tool_use_id": tool_id,
-                        "is_error": true,
-                        "content": format!("Unknown tool: {}", tool_name)
-                    }));
                 }
             }
 
@@ -345,7 +1123,12 @@ impl PlanningAgent {
                 role: "user".to_string(),
                 content: serde_json::json!(tool_results),
             });
-        }
+
+            let state =
+                self.resume_state(task_title, task_description, tool_use_iterations, correction_turns)?;
+            planning_runs::record_transcript(self.run_id, &system_prompt, &conversation_messages, usage, &state)
+                .await?;
+        };
 
         self.emit_progress(
             "finalizing",
@@ -355,13 +1138,29 @@ impl PlanningAgent {
         )
         .await?;
 
+        // Discarded subtasks do not count
+        let subtasks_created = self
+            .staged
+            .lock()
+            .map_err(|_| "Staged plan lock poisoned".to_string())?
+            .steps()
+            .len() as i32;
+
+        let mut message = format!(
+            "Successfully created {} subtasks using AI planning agent",
+            subtasks_created
+        );
+        if !validation_issues.is_empty() {
+            message.push_str(&format!(" ({} plan issues remain)", validation_issues.len()));
+        }
+
         Ok(PlanningResult {
             success: true,
             subtasks_created,
-            message: format!(
-                "Successfully created {} subtasks using AI planning agent",
-                subtasks_created
-            ),
+            message,
+            proposed_plan_id: None,
+            changes: Vec::new(),
+            validation_issues,
         })
     }
 
@@ -373,63 +1172,167 @@ impl PlanningAgent {
     ) -> Result<PlanningResult, String> {
         eprintln!("Warning: Using fallback planning (AI agent unavailable)");
 
-        let subtasks = [
-            ("Research and plan approach", "Gather requirements, research best practices, and develop a comprehensive execution plan"),
-            ("Execute primary deliverables", "Complete the main task deliverables according to the researched plan and requirements"),
-            ("Review and finalize output", "Quality check, refinements, and final validation of deliverables"),
-        ];
+        if self.available_agents.is_empty() {
+            return Err("No agents available for fallback planning".to_string());
+        }
+
+        // Prefer a template configured for this space / task type over the generic plan
+        let (template_name, items) = match plan_templates::find_template_for_task(self.task_id).await? {
+            Some(template) => {
+                let vars = plan_templates::template_variables(self.task_id).await?;
+                (
+                    Some(template.name.clone()),
+                    plan_templates::render_items(&template.items, &vars),
+                )
+            }
+            None => (None, plan_templates::default_fallback_items()),
+        };
+
+        let agent_ids: Vec<i32> = self.available_agents.iter().map(|a| a.id).collect();
+        let total = items.len();
 
         let mut subtasks_created = 0;
-        for (index, (title, description)) in subtasks.iter().enumerate() {
-            // Assign to agents in round-robin fashion
-            let agent_id = if !self.available_agents.is_empty() {
-                self.available_agents[index % self.available_agents.len()].id
-            } else {
-                return Err("No agents available for fallback planning".to_string());
-            };
+        for (index, item) in items.iter().enumerate() {
+            let agent_id = plan_templates::resolve_agent(item, index, &agent_ids)
+                .ok_or("No agents available for fallback planning")?;
 
             let input = json!({
-                "title": title,
-                "description": description,
-                "agent_id": agent_id
+                "title": item.title,
+                "description": item.description,
+                "agent_id": agent_id,
+                "depends_on": item.depends_on
             });
 
             self.execute_create_subtask(input).await?;
             subtasks_created += 1;
 
             // Emit progress
-            let progress = 0.3 + (0.5 * (subtasks_created as f32 / 3.0));
+            let progress = 0.3 + (0.5 * (subtasks_created as f32 / total as f32));
             self.emit_progress(
                 "fallback_creating",
-                &format!("Fallback: Created subtask {}/3", subtasks_created),
+                &format!("Fallback: Created subtask {}/{}", subtasks_created, total),
                 progress,
                 Some("Fallback Planning"),
             )
             .await?;
         }
 
+        let source = template_name
+            .map(|name| format!("template '{}'", name))
+            .unwrap_or_else(|| "fallback planning".to_string());
+
         Ok(PlanningResult {
             success: true,
             subtasks_created,
             message: format!(
-                "Created {} subtasks using fallback planning (AI agent unavailable)",
-                subtasks_created
+                "Created {} subtasks using {} (AI agent unavailable)",
+                subtasks_created, source
             ),
+            proposed_plan_id: None,
+            changes: Vec::new(),
+            validation_issues: Vec::new(),
         })
     }
 
-    /// Plan task with fallback to generic planning if AI fails
+    /// Take the staged operations out of the run, leaving it empty
+    fn take_staged(&self) -> Result<StagedPlan, String> {
+        self.staged
+            .lock()
+            .map(|mut staged| std::mem::take(&mut *staged))
+            .map_err(|_| "Staged plan lock poisoned".to_string())
+    }
+
+    /// Apply the staged operations in one transaction and report what changed
+    async fn commit_staged(&self, mut result: PlanningResult) -> Result<PlanningResult, String> {
+        let staged = self.take_staged()?;
+        result.changes = staged.commit(self.task_id).await?;
+
+        if self.options.replan {
+            result.message = format!(
+                "Revised plan using AI planning agent: {}",
+                summarize_changes(&result.changes)
+            );
+        }
+        Ok(result)
+    }
+
+    /// Whether the task has no subtasks at all, i.e. fallback would not pile onto an existing plan
+    async fn is_clean_slate(&self) -> Result<bool, String> {
+        let pool = get_db_pool()?;
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM subtasks WHERE task_id = ?")
+            .bind(self.task_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(count == 0)
+    }
+
+    /// Persist the collected draft items as a proposed plan and notify the UI
+    async fn finish_draft(&self, mut result: PlanningResult) -> Result<PlanningResult, String> {
+        let items = self.take_staged()?.proposed_items();
+
+        let plan: ProposedPlan = save_proposed_plan(self.task_id, &items).await?;
+
+        self.app
+            .emit("task-planning-proposal", &plan)
+            .map_err(|e| format!("Failed to emit proposal event: {}", e))?;
+
+        result.subtasks_created = 0;
+        result.proposed_plan_id = Some(plan.id);
+        result.message = format!("Proposed {} subtasks for review", items.len());
+        Ok(result)
+    }
+
+    /// Id of the planning_runs row recording this run
+    pub fn run_id(&self) -> i64 {
+        self.run_id
+    }
+
+    /// Plan task with fallback to generic planning if AI fails, recording the run
     pub async fn plan_task_with_fallback(
         &self,
         task_title: String,
         task_description: Option<String>,
+    ) -> Result<PlanningResult, String> {
+        let started = Instant::now();
+        let outcome = self.run_planning(task_title, task_description).await;
+
+        if let Err(e) = planning_runs::finish_run(
+            self.run_id,
+            &outcome,
+            self.used_fallback.load(Ordering::SeqCst),
+            started.elapsed().as_millis() as i64,
+        )
+        .await
+        {
+            eprintln!("Warning: Failed to record planning run: {}", e);
+        }
+
+        outcome
+    }
+
+    async fn run_planning(
+        &self,
+        task_title: String,
+        task_description: Option<String>,
     ) -> Result<PlanningResult, String> {
         // Try AI planning first
-        match self.plan_task(task_title.clone(), task_description.clone()).await {
-            Ok(result) => Ok(result),
+        let result = match self.plan_task(task_title.clone(), task_description.clone()).await {
+            Ok(result) => result,
             Err(e) => {
+                // Nothing from the failed run has been written; discard what it staged
+                self.take_staged()?;
                 eprintln!("AI planning failed: {}", e);
+
+                // Generic fallback subtasks would only pile onto an existing plan
+                if self.options.replan || self.options.expand || !self.is_clean_slate().await? {
+                    return Err(e);
+                }
+
                 eprintln!("Attempting fallback planning...");
+                self.used_fallback.store(true, Ordering::SeqCst);
 
                 self.emit_progress(
                     "fallback",
@@ -439,8 +1342,45 @@ impl PlanningAgent {
                 )
                 .await?;
 
-                self.fallback_planning(&task_title, &task_description).await
+                self.fallback_planning(&task_title, &task_description).await?
             }
+        };
+
+        if let Some(deadline) = self.deadline {
+            self.staged
+                .lock()
+                .map_err(|_| "Staged plan lock poisoned".to_string())?
+                .spread_due_dates(deadline, Local::now().date_naive())?;
+        }
+
+        if self.options.draft {
+            self.finish_draft(result).await
+        } else {
+            self.commit_staged(result).await
         }
     }
 }
+
+const DEPENDENCY_INSTRUCTIONS: &str = " When a subtask can only start after others are finished, pass their step numbers (returned by create_subtask) in depends_on.";
+
+/// Summarize the changes of a planning run, e.g. "2 created, 1 deleted, reordered"
+fn summarize_changes(changes: &[PlanChange]) -> String {
+    let count = |action: &str| changes.iter().filter(|c| c.action == action).count();
+
+    let mut parts = Vec::new();
+    for action in ["created", "updated", "deleted"] {
+        let n = count(action);
+        if n > 0 {
+            parts.push(format!("{} {}", n, action));
+        }
+    }
+    if count("reordered") > 0 {
+        parts.push("reordered".to_string());
+    }
+
+    if parts.is_empty() {
+        "no changes".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
@@ -0,0 +1,168 @@
+use crate::database::Task;
+use crate::retrieval::DEFAULT_RETRIEVAL_TOKEN_BUDGET;
+use crate::settings::get_db_pool;
+use sqlx::Row;
+
+/// Default token budget for the workspace context added to the planning prompt
+pub const DEFAULT_CONTEXT_TOKEN_BUDGET: usize = 6000;
+
+const MAX_TAGGED_EVENTS: i64 = 20;
+
+/// Rough token estimate: ~4 characters per token (matches the frontend heuristic)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Cut text down to roughly `max_tokens`, marking it as truncated
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
+    if estimate_tokens(text) <= max_tokens {
+        return text.to_string();
+    }
+    let truncated: String = text.chars().take(max_tokens * 4).collect();
+    format!("{}\n\n…(truncated)", truncated.trim_end())
+}
+
+/// Workspace context assembled for planning a task
+pub struct PlanningContext {
+    pub space_id: i64,
+    pub space_title: String,
+    pub prompt_section: String,
+    /// Chunks of other tasks' notes and outputs that match this task
+    pub related_knowledge: String,
+}
+
+/// Gather space context, task notes, the user knowledge document and the
+/// space's upcoming tagged calendar events, in that priority order, within
+/// `budget_tokens`. Lower-priority sections are truncated or dropped first.
+pub async fn gather_planning_context(
+    task_id: i32,
+    budget_tokens: usize,
+) -> Result<PlanningContext, String> {
+    let pool = get_db_pool()?;
+
+    let row = sqlx::query(
+        "SELECT s.id, s.title, s.context_markdown
+         FROM tasks t JOIN spaces s ON s.id = t.space_id
+         WHERE t.id = ?",
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Task {} not found", task_id))?;
+
+    let space_id: i64 = row.try_get("id").map_err(|e| format!("Failed to get space id: {}", e))?;
+    let space_title: String = row.try_get("title").unwrap_or_default();
+    let space_context: Option<String> = row.try_get("context_markdown").unwrap_or(None);
+
+    let task_notes: Option<String> =
+        sqlx::query_scalar("SELECT content FROM task_notes WHERE task_id = ?")
+            .bind(task_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .flatten();
+
+    let user_knowledge: Option<String> =
+        sqlx::query_scalar("SELECT value FROM settings WHERE key = 'user_knowledge_document'")
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+    let events = sqlx::query_as::<_, (String, String)>(
+        "SELECT event_title, associated_date FROM event_space_associations
+         WHERE space_id = ? AND associated_date >= date('now')
+         ORDER BY associated_date ASC LIMIT ?",
+    )
+    .bind(space_id)
+    .bind(MAX_TAGGED_EVENTS)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load space events: {}", e))?;
+
+    let events_text = events
+        .iter()
+        .map(|(title, date)| format!("- {}: {}", date, title))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let sources = [
+        (format!("Space Context ({})", space_title), space_context),
+        ("Task Notes".to_string(), task_notes),
+        ("About the User".to_string(), user_knowledge),
+        ("Upcoming Space Events".to_string(), Some(events_text)),
+    ];
+
+    let mut remaining = budget_tokens;
+    let mut sections = Vec::new();
+    for (heading, content) in sources {
+        let Some(content) = content.filter(|c| !c.trim().is_empty()) else {
+            continue;
+        };
+        if remaining == 0 {
+            break;
+        }
+        let content = truncate_to_tokens(content.trim(), remaining);
+        remaining = remaining.saturating_sub(estimate_tokens(&content));
+        sections.push(format!("### {}\n\n{}", heading, content));
+    }
+
+    // Retrieval is a nice-to-have; planning goes ahead without it
+    let related_knowledge = crate::retrieval::related_knowledge(task_id, DEFAULT_RETRIEVAL_TOKEN_BUDGET)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to retrieve related knowledge for task {}: {}", task_id, e);
+            String::new()
+        });
+
+    Ok(PlanningContext {
+        space_id,
+        space_title,
+        prompt_section: sections.join("\n\n"),
+        related_knowledge,
+    })
+}
+
+/// List the tasks of a space as a compact Markdown list (read-only planning tool)
+pub async fn list_space_tasks(space_id: i64, status: Option<&str>) -> Result<String, String> {
+    let pool = get_db_pool()?;
+
+    let tasks = sqlx::query_as::<_, Task>(
+        r#"
+        SELECT id, space_id, title, description, status, priority,
+               due_date, scheduled_date, created_at, updated_at
+        FROM tasks
+        WHERE space_id = ? AND (? IS NULL OR status = ?)
+        ORDER BY updated_at DESC
+        LIMIT 100
+        "#,
+    )
+    .bind(space_id)
+    .bind(status)
+    .bind(status)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if tasks.is_empty() {
+        return Ok("No tasks found in this space.".to_string());
+    }
+
+    Ok(tasks
+        .iter()
+        .map(|task| {
+            format!(
+                "- Task {}: {} [status: {}, priority: {}{}]",
+                task.id,
+                task.title,
+                task.status,
+                task.priority,
+                task.due_date
+                    .as_deref()
+                    .map(|d| format!(", due: {}", d))
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
@@ -0,0 +1,143 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// A planning run currently in progress, as reported to the UI
+#[derive(Debug, Serialize, Clone)]
+pub struct ActivePlanningRun {
+    pub task_id: i32,
+    pub mode: String,
+    pub started_at: String,
+    // Row in planning_runs, once the planner has been initialized
+    pub planning_run_id: Option<i64>,
+    // Last `task-planning-progress` payload, so a re-opened view can restore its indicator
+    pub last_progress: Option<serde_json::Value>,
+}
+
+struct ActiveEntry {
+    token: u64,
+    run: ActivePlanningRun,
+    abort: Option<tokio::task::AbortHandle>,
+}
+
+/// Outcome of asking the coordinator to start planning a task
+pub enum Registration {
+    Started(RunGuard),
+    AlreadyRunning(ActivePlanningRun),
+}
+
+static ACTIVE_RUNS: OnceLock<Mutex<HashMap<i32, ActiveEntry>>> = OnceLock::new();
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+fn active_runs() -> &'static Mutex<HashMap<i32, ActiveEntry>> {
+    ACTIVE_RUNS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn lock_runs() -> Result<std::sync::MutexGuard<'static, HashMap<i32, ActiveEntry>>, String> {
+    active_runs()
+        .lock()
+        .map_err(|_| "Planning coordinator lock poisoned".to_string())
+}
+
+/// Removes the run from the coordinator when the planning task ends,
+/// including when it is aborted or panics
+pub struct RunGuard {
+    task_id: i32,
+    token: u64,
+}
+
+impl RunGuard {
+    pub fn token(&self) -> u64 {
+        self.token
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        if let Ok(mut runs) = active_runs().lock() {
+            // A newer run may already have taken the slot after a cancel
+            if runs.get(&self.task_id).map(|e| e.token) == Some(self.token) {
+                runs.remove(&self.task_id);
+            }
+        }
+    }
+}
+
+/// Reserve the planning slot of a task, or report the run already holding it
+pub fn register(task_id: i32, mode: &str) -> Result<Registration, String> {
+    let mut runs = lock_runs()?;
+
+    if let Some(entry) = runs.get(&task_id) {
+        return Ok(Registration::AlreadyRunning(entry.run.clone()));
+    }
+
+    let token = NEXT_TOKEN.fetch_add(1, Ordering::SeqCst);
+    runs.insert(
+        task_id,
+        ActiveEntry {
+            token,
+            run: ActivePlanningRun {
+                task_id,
+                mode: mode.to_string(),
+                started_at: chrono::Utc::now().to_rfc3339(),
+                planning_run_id: None,
+                last_progress: None,
+            },
+            abort: None,
+        },
+    );
+
+    Ok(Registration::Started(RunGuard { task_id, token }))
+}
+
+/// Attach the spawned task so the run can be cancelled
+pub fn set_abort_handle(task_id: i32, token: u64, handle: tokio::task::AbortHandle) {
+    if let Ok(mut runs) = active_runs().lock() {
+        if let Some(entry) = runs.get_mut(&task_id).filter(|e| e.token == token) {
+            entry.abort = Some(handle);
+        }
+    }
+}
+
+/// Note the planning_runs row of the run holding `token`
+pub fn set_planning_run_id(task_id: i32, token: u64, planning_run_id: i64) {
+    if let Ok(mut runs) = active_runs().lock() {
+        if let Some(entry) = runs.get_mut(&task_id).filter(|e| e.token == token) {
+            entry.run.planning_run_id = Some(planning_run_id);
+        }
+    }
+}
+
+/// Keep the latest progress of the run holding `token`
+pub fn record_progress(task_id: i32, token: u64, progress: &serde_json::Value) {
+    if let Ok(mut runs) = active_runs().lock() {
+        if let Some(entry) = runs.get_mut(&task_id).filter(|e| e.token == token) {
+            entry.run.last_progress = Some(progress.clone());
+        }
+    }
+}
+
+/// Abort the run of a task and free its slot; returns the aborted run, or
+/// None if nothing was running
+pub fn cancel(task_id: i32) -> Result<Option<ActivePlanningRun>, String> {
+    let Some(entry) = lock_runs()?.remove(&task_id) else {
+        return Ok(None);
+    };
+
+    match entry.abort {
+        Some(handle) if !handle.is_finished() => {
+            handle.abort();
+            Ok(Some(entry.run))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// List the planning runs in progress
+#[tauri::command]
+pub async fn get_active_planning_runs() -> Result<Vec<ActivePlanningRun>, String> {
+    let mut runs: Vec<ActivePlanningRun> = lock_runs()?.values().map(|e| e.run.clone()).collect();
+    runs.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(runs)
+}
@@ -0,0 +1,338 @@
+use crate::chat::ChatMessage;
+use crate::planning_agent::{PlanChange, PlanningOptions, PlanningResult};
+use crate::settings::get_db_pool;
+use crate::staged_plan::StagedPlan;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+/// Token usage accumulated over the model calls of a run
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokenUsage {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+}
+
+/// A planning run without its transcript, for listings
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct PlanningRunSummary {
+    pub id: i64,
+    pub task_id: i64,
+    pub model: String,
+    pub mode: String,
+    pub status: String,
+    pub subtasks_created: i64,
+    pub used_fallback: bool,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub error: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+/// A planning run including the prompt, transcript and applied changes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlanningRun {
+    #[serde(flatten)]
+    pub summary: PlanningRunSummary,
+    pub system_prompt: Option<String>,
+    pub transcript: Vec<ChatMessage>,
+    pub changes: Vec<PlanChange>,
+}
+
+/// Where a run's planning loop stands, saved after every iteration so that an
+/// interrupted run can pick up from there
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResumeState {
+    pub task_title: String,
+    pub task_description: Option<String>,
+    pub options: PlanningOptions,
+    pub staged: StagedPlan,
+    pub iterations: usize,
+    pub correction_turns: usize,
+}
+
+/// Everything needed to continue an interrupted run
+#[derive(Debug, Clone)]
+pub struct ResumePoint {
+    pub run_id: i64,
+    pub task_id: i32,
+    pub system_prompt: String,
+    pub transcript: Vec<ChatMessage>,
+    pub usage: TokenUsage,
+    pub state: ResumeState,
+}
+
+const SUMMARY_COLUMNS: &str = "id, task_id, model, mode, status, subtasks_created, used_fallback,
+     input_tokens, output_tokens, error, duration_ms, started_at, finished_at";
+
+/// Record the start of a planning run and return its id
+pub async fn start_run(task_id: i32, model: &str, mode: &str) -> Result<i64, String> {
+    let pool = get_db_pool()?;
+
+    let result = sqlx::query(
+        "INSERT INTO planning_runs (task_id, model, mode, status, started_at)
+         VALUES (?, ?, ?, 'running', CURRENT_TIMESTAMP)",
+    )
+    .bind(task_id)
+    .bind(model)
+    .bind(mode)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record planning run: {}", e))?;
+
+    Ok(result.last_insert_rowid())
+}
+
+/// Save the conversation and loop state so far; called after every model turn
+/// so that failed or cancelled runs keep their partial transcript and
+/// interrupted runs can be resumed
+pub async fn record_transcript(
+    run_id: i64,
+    system_prompt: &str,
+    messages: &[ChatMessage],
+    usage: TokenUsage,
+    state: &ResumeState,
+) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    let transcript = serde_json::to_string(messages)
+        .map_err(|e| format!("Failed to serialize transcript: {}", e))?;
+    let state = serde_json::to_string(state)
+        .map_err(|e| format!("Failed to serialize planning state: {}", e))?;
+
+    sqlx::query(
+        "UPDATE planning_runs SET system_prompt = ?, transcript = ?, input_tokens = ?, output_tokens = ?,
+         resume_state = ?
+         WHERE id = ?",
+    )
+    .bind(system_prompt)
+    .bind(&transcript)
+    .bind(usage.input_tokens)
+    .bind(usage.output_tokens)
+    .bind(&state)
+    .bind(run_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record planning transcript: {}", e))?;
+
+    Ok(())
+}
+
+/// Record how a planning run ended
+pub async fn finish_run(
+    run_id: i64,
+    outcome: &Result<PlanningResult, String>,
+    used_fallback: bool,
+    duration_ms: i64,
+) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    let (status, subtasks_created, changes, error) = match outcome {
+        Ok(result) => (
+            "succeeded",
+            result.subtasks_created as i64,
+            serde_json::to_string(&result.changes)
+                .map_err(|e| format!("Failed to serialize changes: {}", e))?,
+            None,
+        ),
+        Err(e) => ("failed", 0, "[]".to_string(), Some(e.clone())),
+    };
+
+    sqlx::query(
+        "UPDATE planning_runs SET status = ?, subtasks_created = ?, changes = ?, used_fallback = ?,
+         error = ?, duration_ms = ?, resume_state = NULL, finished_at = CURRENT_TIMESTAMP
+         WHERE id = ?",
+    )
+    .bind(status)
+    .bind(subtasks_created)
+    .bind(&changes)
+    .bind(used_fallback)
+    .bind(&error)
+    .bind(duration_ms)
+    .bind(run_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to finish planning run: {}", e))?;
+
+    Ok(())
+}
+
+/// Close a run the user cancelled, if it is still unfinished
+pub async fn mark_run_cancelled(run_id: i64) -> Result<(), String> {
+    close_running_runs(Some(run_id), "cancelled", "Cancelled by user").await
+}
+
+/// Close runs left unfinished when the app quit mid-planning. Their loop state
+/// is kept so they can be resumed or discarded.
+pub async fn mark_interrupted_runs() -> Result<(), String> {
+    close_running_runs(None, "interrupted", "Interrupted before completion").await
+}
+
+async fn close_running_runs(run_id: Option<i64>, status: &str, error: &str) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query(
+        "UPDATE planning_runs SET status = ?, error = ?, finished_at = CURRENT_TIMESTAMP,
+         duration_ms = CAST((julianday('now') - julianday(started_at)) * 86400000 AS INTEGER),
+         resume_state = CASE WHEN ? = 'interrupted' THEN resume_state ELSE NULL END
+         WHERE status = 'running' AND (? IS NULL OR id = ?)",
+    )
+    .bind(status)
+    .bind(error)
+    .bind(status)
+    .bind(run_id)
+    .bind(run_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to close planning runs: {}", e))?;
+
+    Ok(())
+}
+
+/// List the planning runs of a task, newest first
+#[tauri::command]
+pub async fn list_planning_runs(
+    task_id: i64,
+    limit: Option<i64>,
+) -> Result<Vec<PlanningRunSummary>, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, PlanningRunSummary>(&format!(
+        "SELECT {} FROM planning_runs WHERE task_id = ? ORDER BY started_at DESC, id DESC LIMIT ?",
+        SUMMARY_COLUMNS
+    ))
+    .bind(task_id)
+    .bind(limit.unwrap_or(50))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Get a planning run with its full transcript
+#[tauri::command]
+pub async fn get_planning_run(run_id: i64) -> Result<PlanningRun, String> {
+    let pool = get_db_pool()?;
+
+    let row = sqlx::query(&format!(
+        "SELECT {}, system_prompt, transcript, changes FROM planning_runs WHERE id = ?",
+        SUMMARY_COLUMNS
+    ))
+    .bind(run_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Planning run {} not found", run_id))?;
+
+    let summary = <PlanningRunSummary as sqlx::FromRow<_>>::from_row(&row)
+        .map_err(|e| format!("Failed to read planning run: {}", e))?;
+
+    let transcript: String = row.try_get("transcript").unwrap_or_else(|_| "[]".to_string());
+    let changes: String = row.try_get("changes").unwrap_or_else(|_| "[]".to_string());
+
+    Ok(PlanningRun {
+        summary,
+        system_prompt: row.try_get("system_prompt").unwrap_or(None),
+        transcript: serde_json::from_str(&transcript)
+            .map_err(|e| format!("Failed to parse planning transcript: {}", e))?,
+        changes: serde_json::from_str(&changes)
+            .map_err(|e| format!("Failed to parse planning changes: {}", e))?,
+    })
+}
+
+/// Load the saved state of an interrupted run
+pub async fn load_resume_point(run_id: i64) -> Result<ResumePoint, String> {
+    let pool = get_db_pool()?;
+
+    let row = sqlx::query(
+        "SELECT task_id, status, system_prompt, transcript, input_tokens, output_tokens, resume_state
+         FROM planning_runs WHERE id = ?",
+    )
+    .bind(run_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Planning run {} not found", run_id))?;
+
+    let status: String = row.try_get("status").unwrap_or_default();
+    let state: Option<String> = row.try_get("resume_state").unwrap_or(None);
+    let state = match state {
+        Some(state) if status == "interrupted" => state,
+        _ => return Err(format!("Planning run {} cannot be resumed", run_id)),
+    };
+    let transcript: String = row.try_get("transcript").unwrap_or_else(|_| "[]".to_string());
+
+    Ok(ResumePoint {
+        run_id,
+        task_id: row.try_get::<i64, _>("task_id").unwrap_or(0) as i32,
+        system_prompt: row
+            .try_get::<Option<String>, _>("system_prompt")
+            .unwrap_or(None)
+            .unwrap_or_default(),
+        transcript: serde_json::from_str(&transcript)
+            .map_err(|e| format!("Failed to parse planning transcript: {}", e))?,
+        usage: TokenUsage {
+            input_tokens: row.try_get("input_tokens").unwrap_or(0),
+            output_tokens: row.try_get("output_tokens").unwrap_or(0),
+        },
+        state: serde_json::from_str(&state)
+            .map_err(|e| format!("Failed to parse planning state: {}", e))?,
+    })
+}
+
+/// Mark an interrupted run as running again; fails if it was already resumed
+/// or discarded
+pub async fn reopen_run(run_id: i64) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    let result = sqlx::query(
+        "UPDATE planning_runs SET status = 'running', error = NULL, finished_at = NULL, duration_ms = NULL
+         WHERE id = ? AND status = 'interrupted' AND resume_state IS NOT NULL",
+    )
+    .bind(run_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to reopen planning run: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Planning run {} cannot be resumed", run_id));
+    }
+    Ok(())
+}
+
+/// List interrupted runs that can still be resumed or discarded, newest first
+#[tauri::command]
+pub async fn list_resumable_planning_runs() -> Result<Vec<PlanningRunSummary>, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, PlanningRunSummary>(&format!(
+        "SELECT {} FROM planning_runs
+         WHERE status = 'interrupted' AND resume_state IS NOT NULL
+         ORDER BY started_at DESC, id DESC",
+        SUMMARY_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Give up on an interrupted run. Nothing it staged was ever written, so only
+/// its saved state needs to go.
+#[tauri::command]
+pub async fn discard_planning_run(run_id: i64) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    let result = sqlx::query(
+        "UPDATE planning_runs SET resume_state = NULL, error = 'Discarded after interruption'
+         WHERE id = ? AND status = 'interrupted' AND resume_state IS NOT NULL",
+    )
+    .bind(run_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to discard planning run: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Planning run {} has no interrupted state to discard", run_id));
+    }
+    Ok(())
+}
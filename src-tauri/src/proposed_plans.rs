@@ -0,0 +1,308 @@
+use crate::settings::get_db_pool;
+use crate::subtask_dependencies::{add_dependencies, topological_sort};
+use crate::subtask_scheduling::SubtaskSchedule;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProposedSubtask {
+    // Stable key within the plan; items added by the user may leave it at 0
+    #[serde(default)]
+    pub step: i64,
+    pub title: String,
+    pub description: String,
+    pub agent_id: i32,
+    // Steps of other items in the plan that must be completed first
+    #[serde(default)]
+    pub depends_on: Vec<i64>,
+    #[serde(flatten)]
+    pub schedule: SubtaskSchedule,
+    // Parent within the plan (by step) or an existing subtask of the task
+    #[serde(default)]
+    pub parent_step: Option<i64>,
+    #[serde(default)]
+    pub parent_subtask_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProposedPlan {
+    pub id: i64,
+    pub task_id: i64,
+    pub status: String,
+    pub items: Vec<ProposedSubtask>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn row_to_plan(row: &sqlx::sqlite::SqliteRow) -> Result<ProposedPlan, String> {
+    let items_json: String = row
+        .try_get("items")
+        .map_err(|e| format!("Failed to extract items: {}", e))?;
+    let items: Vec<ProposedSubtask> = serde_json::from_str(&items_json)
+        .map_err(|e| format!("Failed to parse proposed plan items: {}", e))?;
+
+    Ok(ProposedPlan {
+        id: row.try_get("id").map_err(|e| format!("Failed to extract id: {}", e))?,
+        task_id: row.try_get("task_id").map_err(|e| format!("Failed to extract task_id: {}", e))?,
+        status: row.try_get("status").map_err(|e| format!("Failed to extract status: {}", e))?,
+        items,
+        created_at: row.try_get("created_at").unwrap_or_default(),
+        updated_at: row.try_get("updated_at").unwrap_or_default(),
+    })
+}
+
+async fn fetch_plan(plan_id: i64) -> Result<ProposedPlan, String> {
+    let pool = get_db_pool()?;
+
+    let row = sqlx::query(
+        "SELECT id, task_id, status, items, created_at, updated_at FROM proposed_plans WHERE id = ?",
+    )
+    .bind(plan_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Proposed plan {} not found", plan_id))?;
+
+    row_to_plan(&row)
+}
+
+async fn fetch_pending_plan(plan_id: i64) -> Result<ProposedPlan, String> {
+    let plan = fetch_plan(plan_id).await?;
+    if plan.status != "pending" {
+        return Err(format!("Proposed plan {} is already {}", plan_id, plan.status));
+    }
+    Ok(plan)
+}
+
+/// Make sure every item references an existing, non-system agent
+async fn validate_items(items: &[ProposedSubtask]) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    let agent_ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM agents WHERE system_role IS NULL")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to load agents: {}", e))?;
+
+    let mut steps = Vec::new();
+    for item in items {
+        if item.title.trim().is_empty() {
+            return Err("Proposed subtask title cannot be empty".to_string());
+        }
+        if !agent_ids.contains(&item.agent_id) {
+            return Err(format!("Invalid agent_id: {}", item.agent_id));
+        }
+        item.schedule.validate()?;
+        if let Some(parent) = item.parent_step.filter(|p| !steps.contains(p)) {
+            return Err(format!(
+                "Proposed subtask '{}' must come after its parent step {}",
+                item.title, parent
+            ));
+        }
+        if item.step != 0 {
+            if steps.contains(&item.step) {
+                return Err(format!("Duplicate step {} in proposed plan", item.step));
+            }
+            steps.push(item.step);
+        }
+    }
+
+    // Dependencies on dropped items are ignored, but the rest must not form a cycle
+    let edges: Vec<(i64, i64)> = items
+        .iter()
+        .filter(|item| item.step != 0)
+        .flat_map(|item| item.depends_on.iter().map(move |dep| (item.step, *dep)))
+        .collect();
+    topological_sort(&steps, &edges)?;
+
+    Ok(())
+}
+
+/// Persist a draft plan for a task, replacing any earlier pending proposal
+pub async fn save_proposed_plan(
+    task_id: i32,
+    items: &[ProposedSubtask],
+) -> Result<ProposedPlan, String> {
+    let pool = get_db_pool()?;
+
+    let items_json = serde_json::to_string(items)
+        .map_err(|e| format!("Failed to serialize proposed plan: {}", e))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    sqlx::query(
+        "UPDATE proposed_plans SET status = 'discarded', updated_at = CURRENT_TIMESTAMP
+         WHERE task_id = ? AND status = 'pending'",
+    )
+    .bind(task_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to discard previous proposal: {}", e))?;
+
+    let result = sqlx::query(
+        "INSERT INTO proposed_plans (task_id, status, items, created_at, updated_at)
+         VALUES (?, 'pending', ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+    )
+    .bind(task_id)
+    .bind(&items_json)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to save proposed plan: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit proposed plan: {}", e))?;
+
+    fetch_plan(result.last_insert_rowid()).await
+}
+
+/// Get the pending proposed plan for a task, if any
+#[tauri::command]
+pub async fn get_proposed_plan(task_id: i32) -> Result<Option<ProposedPlan>, String> {
+    let pool = get_db_pool()?;
+
+    let row = sqlx::query(
+        "SELECT id, task_id, status, items, created_at, updated_at FROM proposed_plans
+         WHERE task_id = ? AND status = 'pending'
+         ORDER BY id DESC LIMIT 1",
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    row.map(|row| row_to_plan(&row)).transpose()
+}
+
+/// Replace the items of a pending proposal (edit, reorder, drop or reassign)
+#[tauri::command]
+pub async fn update_proposed_plan(
+    plan_id: i64,
+    items: Vec<ProposedSubtask>,
+) -> Result<ProposedPlan, String> {
+    fetch_pending_plan(plan_id).await?;
+    validate_items(&items).await?;
+
+    let pool = get_db_pool()?;
+    let items_json = serde_json::to_string(&items)
+        .map_err(|e| format!("Failed to serialize proposed plan: {}", e))?;
+
+    sqlx::query("UPDATE proposed_plans SET items = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(&items_json)
+        .bind(plan_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update proposed plan: {}", e))?;
+
+    fetch_plan(plan_id).await
+}
+
+/// Approve a pending proposal, creating all of its subtasks in one transaction.
+/// If `items` is given it replaces the stored items before approval.
+#[tauri::command]
+pub async fn approve_proposed_plan(
+    plan_id: i64,
+    items: Option<Vec<ProposedSubtask>>,
+) -> Result<i32, String> {
+    let plan = fetch_pending_plan(plan_id).await?;
+    let items = items.unwrap_or(plan.items);
+    validate_items(&items).await?;
+
+    let pool = get_db_pool()?;
+    let items_json = serde_json::to_string(&items)
+        .map_err(|e| format!("Failed to serialize proposed plan: {}", e))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // Claim the proposal first, so a second approval (a double click, another
+    // window) finds it no longer pending instead of creating the subtasks twice
+    let claimed = sqlx::query(
+        "UPDATE proposed_plans SET status = 'approved', items = ?, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ? AND status = 'pending'",
+    )
+    .bind(&items_json)
+    .bind(plan_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to approve proposed plan: {}", e))?;
+    if claimed.rows_affected() == 0 {
+        return Err(format!("Proposed plan {} is no longer pending", plan_id));
+    }
+
+    let existing_ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM subtasks WHERE task_id = ?")
+        .bind(plan.task_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut ids_by_step: HashMap<i64, i64> = HashMap::new();
+    for item in &items {
+        if let Some(parent) = item.parent_subtask_id.filter(|id| !existing_ids.contains(id)) {
+            return Err(format!("Parent subtask {} does not belong to this task", parent));
+        }
+        let parent_id = item
+            .parent_subtask_id
+            .or_else(|| item.parent_step.and_then(|step| ids_by_step.get(&step).copied()));
+
+        let result = sqlx::query(
+            "INSERT INTO subtasks (task_id, title, description, agent_id, parent_subtask_id, estimated_minutes, due_date, priority, completed, position, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, FALSE, (SELECT COALESCE(MAX(position), 0) + 1 FROM subtasks WHERE task_id = ?), CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+        )
+        .bind(plan.task_id)
+        .bind(&item.title)
+        .bind(&item.description)
+        .bind(item.agent_id)
+        .bind(parent_id)
+        .bind(item.schedule.estimated_minutes)
+        .bind(&item.schedule.due_date)
+        .bind(&item.schedule.priority)
+        .bind(plan.task_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to create subtask: {}", e))?;
+
+        if item.step != 0 {
+            ids_by_step.insert(item.step, result.last_insert_rowid());
+        }
+    }
+
+    for item in items.iter().filter(|item| item.step != 0) {
+        let dependency_ids: Vec<i64> = item
+            .depends_on
+            .iter()
+            .filter_map(|dep| ids_by_step.get(dep).copied())
+            .collect();
+        add_dependencies(&mut tx, ids_by_step[&item.step], &dependency_ids).await?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit approved plan: {}", e))?;
+
+    Ok(items.len() as i32)
+}
+
+/// Discard a pending proposal without creating any subtasks
+#[tauri::command]
+pub async fn discard_proposed_plan(plan_id: i64) -> Result<(), String> {
+    fetch_pending_plan(plan_id).await?;
+
+    let pool = get_db_pool()?;
+
+    sqlx::query(
+        "UPDATE proposed_plans SET status = 'discarded', updated_at = CURRENT_TIMESTAMP
+         WHERE id = ? AND status = 'pending'",
+    )
+    .bind(plan_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to discard proposed plan: {}", e))?;
+
+    Ok(())
+}
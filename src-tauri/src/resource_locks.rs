@@ -0,0 +1,153 @@
+use crate::edit_locks::{lease_seconds, validate_owner};
+use crate::settings::get_db_pool;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Kinds of document that can be locked, and what their id refers to
+pub const SPACE_CONTEXT: &str = "space_context"; // spaces.id
+pub const SETTING: &str = "setting"; // settings.key
+pub const AGENT_PROMPT: &str = "agent_prompt"; // agents.id
+
+const RESOURCE_TYPES: [&str; 3] = [SPACE_CONTEXT, SETTING, AGENT_PROMPT];
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct ResourceLock {
+    pub resource_type: String,
+    pub resource_id: String,
+    pub locked_by: String,
+    pub owner_id: String,
+    pub locked_at: String,
+    pub renewed_at: String,
+    pub expires_at: String,
+}
+
+const LOCK_COLUMNS: &str =
+    "resource_type, resource_id, locked_by, owner_id, locked_at, renewed_at, expires_at";
+
+fn validate_resource_type(resource_type: &str) -> Result<(), String> {
+    if !RESOURCE_TYPES.contains(&resource_type) {
+        return Err(format!(
+            "Unknown resource type '{}' (expected one of: {})",
+            resource_type,
+            RESOURCE_TYPES.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// The unexpired lock on a resource, if any
+async fn active_lock(
+    pool: &SqlitePool,
+    resource_type: &str,
+    resource_id: &str,
+) -> Result<Option<ResourceLock>, String> {
+    sqlx::query_as::<_, ResourceLock>(&format!(
+        "SELECT {} FROM resource_locks
+         WHERE resource_type = ? AND resource_id = ? AND expires_at > datetime('now')",
+        LOCK_COLUMNS
+    ))
+    .bind(resource_type)
+    .bind(resource_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to check lock: {}", e))
+}
+
+/// Fail if someone other than `owner_id` holds an unexpired lock on the
+/// resource. Writers that do not hold a lock pass `None` and are only let
+/// through while the resource is unlocked.
+pub async fn ensure_writable(
+    resource_type: &str,
+    resource_id: &str,
+    owner_id: Option<&str>,
+) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    match active_lock(pool, resource_type, resource_id).await? {
+        Some(lock) if Some(lock.owner_id.as_str()) != owner_id => Err(format!(
+            "{} {} is being edited by another {} ({}) until {}",
+            resource_type, resource_id, lock.locked_by, lock.owner_id, lock.expires_at
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Take a lease on a resource for `ttl_seconds`. Succeeds when it is unlocked,
+/// its lease has expired, or the caller already holds it (which renews it).
+#[tauri::command]
+pub async fn acquire_resource_lock(
+    resource_type: String,
+    resource_id: String,
+    locked_by: String,
+    owner_id: String,
+    ttl_seconds: Option<i64>,
+) -> Result<bool, String> {
+    validate_resource_type(&resource_type)?;
+    validate_owner(&owner_id)?;
+    let ttl = lease_seconds(ttl_seconds)?;
+    let pool = get_db_pool()?;
+
+    let result = sqlx::query(
+        "INSERT INTO resource_locks (resource_type, resource_id, locked_by, owner_id, locked_at, renewed_at, expires_at)
+         VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, datetime('now', '+' || ? || ' seconds'))
+         ON CONFLICT(resource_type, resource_id) DO UPDATE SET
+           locked_by = excluded.locked_by,
+           locked_at = CASE WHEN owner_id = excluded.owner_id THEN locked_at ELSE excluded.locked_at END,
+           owner_id = excluded.owner_id,
+           renewed_at = excluded.renewed_at,
+           expires_at = excluded.expires_at
+         WHERE owner_id = excluded.owner_id OR expires_at <= datetime('now')",
+    )
+    .bind(&resource_type)
+    .bind(&resource_id)
+    .bind(&locked_by)
+    .bind(&owner_id)
+    .bind(ttl)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Release a resource lock held by `owner_id`; releasing a lock that is gone
+/// or expired is a no-op
+#[tauri::command]
+pub async fn release_resource_lock(
+    resource_type: String,
+    resource_id: String,
+    owner_id: String,
+) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    let result = sqlx::query(
+        "DELETE FROM resource_locks WHERE resource_type = ? AND resource_id = ? AND owner_id = ?",
+    )
+    .bind(&resource_type)
+    .bind(&resource_id)
+    .bind(&owner_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to release lock: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        if let Some(lock) = active_lock(pool, &resource_type, &resource_id).await? {
+            return Err(format!(
+                "Lock on {} {} is held by another {} ({})",
+                resource_type, resource_id, lock.locked_by, lock.owner_id
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The unexpired lock on a resource, if any
+#[tauri::command]
+pub async fn check_resource_lock(
+    resource_type: String,
+    resource_id: String,
+) -> Result<Option<ResourceLock>, String> {
+    validate_resource_type(&resource_type)?;
+    let pool = get_db_pool()?;
+    active_lock(pool, &resource_type, &resource_id).await
+}
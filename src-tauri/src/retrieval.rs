@@ -0,0 +1,294 @@
+use crate::planning_context::{estimate_tokens, truncate_to_tokens};
+use crate::settings::get_db_pool;
+use crate::task_attachments::content_hash;
+use crate::text_diff::split_blocks;
+use std::collections::{HashMap, HashSet};
+
+/// Token budget for retrieved material when the caller does not give one
+pub const DEFAULT_RETRIEVAL_TOKEN_BUDGET: usize = 1500;
+
+/// Largest chunk stored in the index
+const MAX_CHUNK_TOKENS: usize = 250;
+/// Chunks considered for a prompt, best first
+const TOP_K: i64 = 8;
+/// Query terms taken from the task
+const MAX_QUERY_TERMS: usize = 16;
+/// Score multiplier for chunks from the task's own space
+const SAME_SPACE_BOOST: f64 = 1.5;
+
+/// Chunk rowids reserved for each source; chunks past this are not indexed
+const CHUNKS_PER_SOURCE: i64 = 1 << 16;
+
+// Position gives each type its place in the chunk rowids (see migration 048)
+const SOURCE_TYPES: [&str; 3] = ["task_notes", "space_context", "subtask_output"];
+
+// Documents that are chunked, with their content
+const SOURCES_SQL: &str = "
+    SELECT 'task_notes', task_id, content FROM task_notes
+    WHERE TRIM(COALESCE(content, '')) != ''
+    UNION ALL
+    SELECT 'space_context', id, context_markdown FROM spaces
+    WHERE TRIM(COALESCE(context_markdown, '')) != ''
+    UNION ALL
+    SELECT 'subtask_output', id, output FROM subtask_executions
+    WHERE status = 'succeeded' AND output_target = 'artifact' AND TRIM(COALESCE(output, '')) != ''";
+
+const STOP_WORDS: [&str; 32] = [
+    "the", "and", "for", "with", "that", "this", "from", "into", "are", "was", "will", "should",
+    "can", "all", "any", "our", "out", "not", "but", "about", "have", "has", "its", "their", "them",
+    "then", "than", "what", "when", "which", "who", "how",
+];
+
+struct Chunk {
+    heading: String,
+    content: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct RankedChunk {
+    heading: String,
+    content: String,
+    label: String,
+}
+
+/// Split Markdown into chunks of whole blocks, each remembering the heading
+/// path it sits under
+fn chunk_markdown(text: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut current = String::new();
+
+    let flush = |current: &mut String, headings: &[(usize, String)], chunks: &mut Vec<Chunk>| {
+        if !current.trim().is_empty() {
+            chunks.push(Chunk {
+                heading: headings.iter().map(|(_, h)| h.as_str()).collect::<Vec<_>>().join(" > "),
+                content: current.trim().to_string(),
+            });
+        }
+        current.clear();
+    };
+
+    for block in split_blocks(text) {
+        let trimmed = block.trim_start();
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with([' ', '\t', '\n']) {
+            flush(&mut current, &headings, &mut chunks);
+            headings.retain(|(l, _)| *l < level);
+            headings.push((level, trimmed[level..].trim().trim_end_matches('#').trim().to_string()));
+            continue;
+        }
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(block) > MAX_CHUNK_TOKENS {
+            flush(&mut current, &headings, &mut chunks);
+        }
+        current.push_str(block);
+    }
+    flush(&mut current, &headings, &mut chunks);
+    chunks
+}
+
+/// First rowid of a source's chunks. Each source owns a fixed block of rowids,
+/// so its chunks are replaced by rowid instead of by scanning the UNINDEXED
+/// source columns.
+fn first_chunk_rowid(source_type: &str, source_id: i64) -> i64 {
+    let kind = SOURCE_TYPES.iter().position(|t| *t == source_type).unwrap_or(0) as i64;
+    (source_id * SOURCE_TYPES.len() as i64 + kind) * CHUNKS_PER_SOURCE
+}
+
+async fn clear_chunks<'e>(
+    executor: impl sqlx::SqliteExecutor<'e>,
+    source_type: &str,
+    source_id: i64,
+) -> Result<(), String> {
+    let first = first_chunk_rowid(source_type, source_id);
+    sqlx::query("DELETE FROM retrieval_chunks WHERE rowid BETWEEN ? AND ?")
+        .bind(first)
+        .bind(first + CHUNKS_PER_SOURCE - 1)
+        .execute(executor)
+        .await
+        .map_err(|e| format!("Failed to clear chunks: {}", e))?;
+    Ok(())
+}
+
+/// Re-chunk documents that changed since they were last indexed and drop the
+/// chunks of documents that are gone
+pub async fn refresh_index() -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    let sources: Vec<(String, i64, String)> = sqlx::query_as(SOURCES_SQL)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to list retrieval sources: {}", e))?;
+    let indexed: HashMap<(String, i64), Option<String>> =
+        sqlx::query_as::<_, (String, i64, Option<String>)>(
+            "SELECT source_type, source_id, source_version FROM retrieval_sources",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to load retrieval index: {}", e))?
+        .into_iter()
+        .map(|(source_type, source_id, version)| ((source_type, source_id), version))
+        .collect();
+
+    let current: HashSet<(String, i64)> = sources.iter().map(|(t, id, _)| (t.clone(), *id)).collect();
+
+    for (source_type, source_id, content) in &sources {
+        let version = Some(content_hash(content.as_bytes()));
+        if indexed.get(&(source_type.clone(), *source_id)) == Some(&version) {
+            continue;
+        }
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        clear_chunks(&mut *tx, source_type, *source_id).await?;
+        let first = first_chunk_rowid(source_type, *source_id);
+        for (index, chunk) in chunk_markdown(content).iter().take(CHUNKS_PER_SOURCE as usize).enumerate() {
+            sqlx::query(
+                "INSERT INTO retrieval_chunks (rowid, source_type, source_id, chunk_index, heading, content)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(first + index as i64)
+            .bind(source_type)
+            .bind(source_id)
+            .bind(index as i64)
+            .bind(&chunk.heading)
+            .bind(&chunk.content)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to index chunk: {}", e))?;
+        }
+        sqlx::query(
+            "INSERT INTO retrieval_sources (source_type, source_id, source_version, indexed_at)
+             VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+             ON CONFLICT(source_type, source_id) DO UPDATE SET
+               source_version = excluded.source_version,
+               indexed_at = excluded.indexed_at",
+        )
+        .bind(source_type)
+        .bind(source_id)
+        .bind(version)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to record indexed source: {}", e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit chunks: {}", e))?;
+    }
+
+    for (source_type, source_id) in indexed.keys().filter(|key| !current.contains(*key)) {
+        clear_chunks(pool, source_type, *source_id).await?;
+        sqlx::query("DELETE FROM retrieval_sources WHERE source_type = ? AND source_id = ?")
+            .bind(source_type)
+            .bind(source_id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to clear indexed source: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Distinctive words of a text as an FTS5 query matching any of them
+fn keyword_query(text: &str) -> Option<String> {
+    let mut seen = HashSet::new();
+    let terms: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .map(|word| word.to_lowercase())
+        .filter(|word| word.chars().count() >= 3 && !STOP_WORDS.contains(&word.as_str()))
+        .filter(|word| seen.insert(word.clone()))
+        .take(MAX_QUERY_TERMS)
+        .map(|word| format!("\"{}\"", word))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+/// Material from other tasks' notes, subtask outputs and other spaces'
+/// context that looks relevant to a task, best first, as a Markdown prompt
+/// section within `budget_tokens`. Empty when nothing matches. Ranking is
+/// keyword-based (BM25); there are no embeddings.
+pub async fn related_knowledge(task_id: i32, budget_tokens: usize) -> Result<String, String> {
+    let pool = get_db_pool()?;
+
+    let task: Option<(String, Option<String>, i64)> =
+        sqlx::query_as("SELECT title, description, space_id FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    let Some((title, description, space_id)) = task else {
+        return Err(format!("Task {} not found", task_id));
+    };
+    let Some(query) = keyword_query(&format!("{} {}", title, description.unwrap_or_default())) else {
+        return Ok(String::new());
+    };
+
+    refresh_index().await?;
+
+    // The task's own notes and its space's context are already in the prompt
+    let chunks = sqlx::query_as::<_, RankedChunk>(
+        "SELECT heading, content, label FROM (
+           SELECT c.source_type, c.source_id, c.heading, c.content,
+                  bm25(retrieval_chunks, 0.0, 0.0, 0.0, 2.0, 1.0) AS score,
+                  CASE c.source_type
+                    WHEN 'space_context' THEN c.source_id
+                    WHEN 'task_notes' THEN tn.space_id
+                    ELSE te.space_id
+                  END AS space_id,
+                  CASE c.source_type
+                    WHEN 'task_notes' THEN c.source_id
+                    WHEN 'subtask_output' THEN te.id
+                  END AS task_id,
+                  CASE c.source_type
+                    WHEN 'task_notes' THEN 'Notes of task ' || tn.id || ': ' || tn.title
+                    WHEN 'space_context' THEN 'Context of space ' || sp.title
+                    ELSE 'Output of subtask \"' || COALESCE(st.title, '?') || '\" (task ' || te.id || ': ' || te.title || ')'
+                  END AS label
+           FROM retrieval_chunks c
+           LEFT JOIN tasks tn ON c.source_type = 'task_notes' AND tn.id = c.source_id
+           LEFT JOIN spaces sp ON c.source_type = 'space_context' AND sp.id = c.source_id
+           LEFT JOIN subtask_executions se ON c.source_type = 'subtask_output' AND se.id = c.source_id
+           LEFT JOIN tasks te ON te.id = se.task_id
+           LEFT JOIN subtasks st ON st.id = se.subtask_id
+           WHERE retrieval_chunks MATCH ?
+         )
+         WHERE label IS NOT NULL
+           AND task_id IS NOT ?
+           AND NOT (source_type = 'space_context' AND source_id = ?)
+         ORDER BY score * CASE WHEN space_id = ? THEN ? ELSE 1.0 END
+         LIMIT ?",
+    )
+    .bind(&query)
+    .bind(task_id)
+    .bind(space_id)
+    .bind(space_id)
+    .bind(SAME_SPACE_BOOST)
+    .bind(TOP_K)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Retrieval failed: {}", e))?;
+
+    let mut remaining = budget_tokens;
+    let mut sections = Vec::new();
+    for chunk in chunks {
+        let heading = if chunk.heading.is_empty() {
+            chunk.label
+        } else {
+            format!("{} — {}", chunk.label, chunk.heading)
+        };
+        let header_tokens = estimate_tokens(&heading) + 2;
+        if remaining <= header_tokens {
+            break;
+        }
+        let content = truncate_to_tokens(&chunk.content, remaining - header_tokens);
+        remaining = remaining.saturating_sub(header_tokens + estimate_tokens(&content));
+        sections.push(format!("#### {}\n\n{}", heading, content));
+    }
+    Ok(sections.join("\n\n"))
+}
+
+/// Preview the related material agents would get for a task
+#[tauri::command]
+pub async fn retrieve_related_knowledge(task_id: i32, budget_tokens: Option<usize>) -> Result<String, String> {
+    related_knowledge(task_id, budget_tokens.unwrap_or(DEFAULT_RETRIEVAL_TOKEN_BUDGET)).await
+}
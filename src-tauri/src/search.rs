@@ -0,0 +1,163 @@
+use crate::settings::get_db_pool;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+/// Kinds of entity in the search index
+const ENTITY_TYPES: [&str; 4] = ["task", "subtask", "task_notes", "space"];
+
+/// Optional filters for `search`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SearchFilters {
+    pub space_id: Option<i64>,
+    /// Status of the task a result belongs to; excludes spaces
+    pub status: Option<String>,
+    /// Limit results to these entity types
+    pub entity_types: Option<Vec<String>>,
+    /// Only results updated on or after this date (YYYY-MM-DD)
+    pub updated_after: Option<String>,
+    /// Only results updated on or before this date (YYYY-MM-DD)
+    pub updated_before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct SearchResult {
+    pub entity_type: String, // 'task', 'subtask', 'task_notes', 'space'
+    pub entity_id: i64,
+    pub task_id: Option<i64>,
+    pub space_id: Option<i64>,
+    pub title: String,
+    /// Matching text with matches wrapped in <mark></mark>
+    pub snippet: String,
+    pub status: Option<String>,
+    pub updated_at: Option<String>,
+    /// Lower is a better match
+    pub rank: f64,
+    /// Where the result lives in the app, e.g. /tasks/12/subtasks/40
+    #[sqlx(default)]
+    pub link: String,
+}
+
+/// Turn free text into an FTS5 query: every word must appear, and the last
+/// may be a prefix (so results show up while typing)
+fn to_match_query(query: &str) -> Option<String> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    Some(format!("{}*", words.join(" ")))
+}
+
+fn link_for(result: &SearchResult) -> String {
+    match (result.entity_type.as_str(), result.task_id) {
+        ("subtask", Some(task_id)) => format!("/tasks/{}/subtasks/{}", task_id, result.entity_id),
+        ("task_notes", Some(task_id)) => format!("/tasks/{}/notes", task_id),
+        ("space", _) => format!("/spaces/{}", result.entity_id),
+        _ => format!("/tasks/{}", result.entity_id),
+    }
+}
+
+/// Full-text search over tasks, subtasks, task notes and space context,
+/// best matches first
+#[tauri::command]
+pub async fn search(query: String, filters: Option<SearchFilters>) -> Result<Vec<SearchResult>, String> {
+    let filters = filters.unwrap_or_default();
+    let Some(match_query) = to_match_query(&query) else {
+        return Ok(Vec::new());
+    };
+
+    let entity_types = filters.entity_types.unwrap_or_default();
+    if let Some(unknown) = entity_types.iter().find(|t| !ENTITY_TYPES.contains(&t.as_str())) {
+        return Err(format!(
+            "Unknown entity type '{}' (expected one of: {})",
+            unknown,
+            ENTITY_TYPES.join(", ")
+        ));
+    }
+    let type_filter = if entity_types.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "AND si.entity_type IN ({})",
+            vec!["?"; entity_types.len()].join(", ")
+        )
+    };
+    let limit = filters.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    // Title matches count ten times as much as body matches
+    let sql = format!(
+        "SELECT * FROM (
+           SELECT si.entity_type, si.entity_id, si.task_id,
+                  CASE WHEN si.entity_type = 'space' THEN si.entity_id ELSE t.space_id END AS space_id,
+                  si.title, snippet(search_index, -1, '<mark>', '</mark>', '…', 16) AS snippet,
+                  t.status, si.updated_at,
+                  bm25(search_index, 0.0, 0.0, 0.0, 0.0, 10.0, 1.0) AS rank
+           FROM search_index si
+           LEFT JOIN tasks t ON t.id = si.task_id
+           WHERE search_index MATCH ? {}
+         )
+         WHERE (? IS NULL OR space_id = ?)
+           AND (? IS NULL OR status = ?)
+           AND (? IS NULL OR date(updated_at) >= date(?))
+           AND (? IS NULL OR date(updated_at) <= date(?))
+         ORDER BY rank
+         LIMIT ?",
+        type_filter
+    );
+
+    let mut statement = sqlx::query_as::<_, SearchResult>(&sql).bind(&match_query);
+    for entity_type in &entity_types {
+        statement = statement.bind(entity_type);
+    }
+    let pool = get_db_pool()?;
+    let mut results = statement
+        .bind(filters.space_id)
+        .bind(filters.space_id)
+        .bind(&filters.status)
+        .bind(&filters.status)
+        .bind(&filters.updated_after)
+        .bind(&filters.updated_after)
+        .bind(&filters.updated_before)
+        .bind(&filters.updated_before)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Search failed: {}", e))?;
+
+    for result in &mut results {
+        result.link = link_for(result);
+    }
+    Ok(results)
+}
+
+/// Search results as plain text for an agent
+pub fn format_for_agent(results: &[SearchResult]) -> String {
+    if results.is_empty() {
+        return "No matches found.".to_string();
+    }
+    results
+        .iter()
+        .map(|r| {
+            let snippet = r.snippet.replace("<mark>", "**").replace("</mark>", "**");
+            let label = match r.entity_type.as_str() {
+                "task" => format!("Task {}", r.entity_id),
+                "subtask" => format!("Subtask {} of task {}", r.entity_id, r.task_id.unwrap_or_default()),
+                "task_notes" => format!("Notes of task {}", r.entity_id),
+                _ => format!("Space {} context", r.entity_id),
+            };
+            format!(
+                "- {}: {}{}\n  {}",
+                label,
+                r.title,
+                r.status.as_deref().map(|s| format!(" [{}]", s)).unwrap_or_default(),
+                snippet.replace('\n', " ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
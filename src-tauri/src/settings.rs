@@ -1,3 +1,4 @@
+use crate::resource_locks::{ensure_writable, SETTING};
 use serde::{Deserialize, Serialize};
 use sqlx::{sqlite::SqlitePool, Row};
 use std::sync::OnceLock;
@@ -53,7 +54,9 @@ pub async fn set_setting(
     _app: tauri::AppHandle,
     key: String,
     value: String,
+    owner_id: Option<String>,
 ) -> Result<(), String> {
+    ensure_writable(SETTING, &key, owner_id.as_deref()).await?;
     let pool = get_db_pool()?;
 
     sqlx::query(
@@ -72,6 +75,7 @@ pub async fn set_setting(
 
 #[tauri::command]
 pub async fn delete_setting(_app: tauri::AppHandle, key: String) -> Result<(), String> {
+    ensure_writable(SETTING, &key, None).await?;
     let pool = get_db_pool()?;
 
     sqlx::query("DELETE FROM settings WHERE key = ?")
@@ -1,3 +1,5 @@
+use crate::document_revisions::{self, record_revision, RevisionAttribution};
+use crate::resource_locks::{self, ensure_writable};
 use crate::settings::get_db_pool;
 use sqlx::Row;
 
@@ -23,23 +25,86 @@ pub async fn read_space_context(space_id: i32) -> Result<String, String> {
     }
 }
 
-/// Write context markdown for a space to the database
+/// Write context markdown for a space to the database. Fails while another
+/// owner holds the space context lock. `attribution` says who made the change
+/// and why; it defaults to the user.
 #[tauri::command]
-pub async fn write_space_context(space_id: i32, content: String) -> Result<(), String> {
+pub async fn write_space_context(
+    space_id: i32,
+    content: String,
+    owner_id: Option<String>,
+    attribution: Option<RevisionAttribution>,
+) -> Result<(), String> {
+    let attribution = attribution.unwrap_or_else(RevisionAttribution::user);
+    attribution.validate()?;
+    ensure_writable(resource_locks::SPACE_CONTEXT, &space_id.to_string(), owner_id.as_deref()).await?;
+
+    save_space_context(space_id, &content, &attribution, None).await
+}
+
+/// Save a space's context and record the change in its revision history
+pub async fn save_space_context(
+    space_id: i32,
+    content: &str,
+    attribution: &RevisionAttribution,
+    restored_from: Option<i64>,
+) -> Result<(), String> {
+    replace_space_context(space_id, None, content, attribution, restored_from).await
+}
+
+/// Save a space's context like `save_space_context`, failing without a change
+/// if `expected` is given and the stored context no longer matches it
+pub(crate) async fn replace_space_context(
+    space_id: i32,
+    expected: Option<&str>,
+    content: &str,
+    attribution: &RevisionAttribution,
+    restored_from: Option<i64>,
+) -> Result<(), String> {
     let pool = get_db_pool()?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
 
-    let result = sqlx::query(
+    let previous: Option<Option<String>> =
+        sqlx::query_scalar("SELECT context_markdown FROM spaces WHERE id = ?")
+            .bind(space_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    let Some(previous) = previous else {
+        return Err(format!("Space {} not found", space_id));
+    };
+    let previous = previous.unwrap_or_default();
+    if expected.is_some_and(|expected| expected != previous) {
+        return Err(format!(
+            "Context of space {} changed while it was being edited; nothing was saved",
+            space_id
+        ));
+    }
+
+    sqlx::query(
         "UPDATE spaces SET context_markdown = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
     )
-    .bind(&content)
+    .bind(content)
     .bind(space_id)
-    .execute(pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| format!("Failed to save space context: {}", e))?;
 
-    if result.rows_affected() == 0 {
-        return Err(format!("Space {} not found", space_id));
-    }
+    record_revision(
+        &mut tx,
+        document_revisions::SPACE_CONTEXT,
+        space_id as i64,
+        &previous,
+        content,
+        attribution,
+        restored_from,
+    )
+    .await?;
 
-    Ok(())
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit space context: {}", e))
 }
@@ -0,0 +1,396 @@
+use crate::database::SubTask;
+use crate::plan_validation::PlanItem;
+use crate::planning_agent::PlanChange;
+use crate::proposed_plans::ProposedSubtask;
+use crate::settings::get_db_pool;
+use crate::subtask_dependencies::{add_dependencies, validate_task_dependencies};
+use crate::subtask_scheduling::{parse_date, spread_due_dates, SubtaskSchedule};
+use crate::subtask_tree::completed_descendants;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Reference to a subtask from within a planning run: either one that already
+/// exists in the database or one created earlier in the same run (by step number)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SubtaskRef {
+    Existing(i64),
+    Step(i64),
+}
+
+/// A change requested by the planner, applied only when the run succeeds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StagedOperation {
+    Create {
+        step: i64,
+        title: String,
+        description: String,
+        agent_id: i32,
+        depends_on: Vec<i64>,
+        schedule: SubtaskSchedule,
+        // Subtask to nest under; None for a top-level subtask
+        parent: Option<SubtaskRef>,
+    },
+    Update {
+        subtask_id: i64,
+        title: Option<String>,
+        description: Option<String>,
+        agent_id: Option<i32>,
+        // Title to report in the run summary
+        display_title: String,
+    },
+    Delete {
+        subtask_id: i64,
+        title: String,
+    },
+    Reorder {
+        order: Vec<SubtaskRef>,
+    },
+}
+
+/// Buffer of planner changes for one planning run. Nothing touches the
+/// subtasks table until `commit`, which applies everything in one transaction,
+/// so a failed or cancelled run leaves the task exactly as it was.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct StagedPlan {
+    operations: Vec<StagedOperation>,
+}
+
+impl StagedPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, operation: StagedOperation) {
+        self.operations.push(operation);
+    }
+
+    /// Step number the next created subtask will get (steps are never reused,
+    /// even after a discard)
+    pub fn next_step(&self) -> i64 {
+        self.steps().into_iter().max().unwrap_or(0) + 1
+    }
+
+    /// Step numbers of the subtasks created in this run
+    pub fn steps(&self) -> Vec<i64> {
+        self.operations
+            .iter()
+            .filter_map(|op| match op {
+                StagedOperation::Create { step, .. } => Some(*step),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Parent of a subtask created in this run
+    pub fn parent_of_step(&self, step: i64) -> Option<SubtaskRef> {
+        self.operations.iter().find_map(|op| match op {
+            StagedOperation::Create { step: s, parent, .. } if *s == step => *parent,
+            _ => None,
+        })
+    }
+
+    /// Drop a subtask created earlier in this run and any references to it.
+    /// Returns its title.
+    pub fn discard_step(&mut self, step: i64) -> Result<String, String> {
+        let has_children = self.operations.iter().any(|op| {
+            matches!(op, StagedOperation::Create { parent: Some(SubtaskRef::Step(p)), .. } if *p == step)
+        });
+        if has_children {
+            return Err(format!("Step {} has nested subtasks; discard those first", step));
+        }
+
+        let index = self
+            .operations
+            .iter()
+            .position(|op| matches!(op, StagedOperation::Create { step: s, .. } if *s == step))
+            .ok_or_else(|| format!("Step {} does not refer to a subtask created in this run", step))?;
+        let title = match self.operations.remove(index) {
+            StagedOperation::Create { title, .. } => title,
+            _ => unreachable!(),
+        };
+
+        for operation in &mut self.operations {
+            match operation {
+                StagedOperation::Create { depends_on, .. } => depends_on.retain(|dep| *dep != step),
+                StagedOperation::Reorder { order } => order.retain(|r| *r != SubtaskRef::Step(step)),
+                _ => {}
+            }
+        }
+        Ok(title)
+    }
+
+    /// The task's subtasks as they will be once this plan is applied: existing
+    /// subtasks with staged updates and deletions, followed by created ones
+    pub fn plan_items(&self, existing: &[SubTask]) -> Vec<PlanItem> {
+        let mut items: Vec<PlanItem> = existing
+            .iter()
+            .filter(|subtask| !self.is_deleted(subtask.id))
+            .map(|subtask| {
+                let mut item = PlanItem {
+                    label: format!("Subtask {}", subtask.id),
+                    title: subtask.title.clone(),
+                    description: subtask.description.clone().unwrap_or_default(),
+                    agent_id: subtask.agent_id.map(|id| id as i32),
+                    top_level: subtask.parent_subtask_id.is_none(),
+                    created: false,
+                    updated: false,
+                };
+                for operation in &self.operations {
+                    if let StagedOperation::Update { subtask_id, title, description, agent_id, .. } = operation {
+                        if *subtask_id == subtask.id {
+                            item.title = title.clone().unwrap_or(item.title);
+                            item.description = description.clone().unwrap_or(item.description);
+                            item.agent_id = agent_id.or(item.agent_id);
+                            item.updated = true;
+                        }
+                    }
+                }
+                item
+            })
+            .collect();
+
+        items.extend(self.operations.iter().filter_map(|op| match op {
+            StagedOperation::Create { step, title, description, agent_id, parent, .. } => Some(PlanItem {
+                label: format!("Step {}", step),
+                title: title.clone(),
+                description: description.clone(),
+                agent_id: Some(*agent_id),
+                top_level: parent.is_none(),
+                created: true,
+                updated: false,
+            }),
+            _ => None,
+        }));
+        items
+    }
+
+    pub fn is_deleted(&self, subtask_id: i64) -> bool {
+        self.operations.iter().any(|op| {
+            matches!(op, StagedOperation::Delete { subtask_id: id, .. } if *id == subtask_id)
+        })
+    }
+
+    /// Give created subtasks without a due date one, spread backward from the deadline
+    pub fn spread_due_dates(&mut self, deadline: NaiveDate, today: NaiveDate) -> Result<(), String> {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for operation in &self.operations {
+            if let StagedOperation::Create { step, depends_on, schedule, .. } = operation {
+                nodes.push((
+                    *step,
+                    schedule.estimated_minutes,
+                    schedule.due_date.as_deref().and_then(parse_date),
+                ));
+                edges.extend(depends_on.iter().map(|dep| (*step, *dep)));
+            }
+        }
+
+        let due = spread_due_dates(&nodes, &edges, deadline, today)?;
+
+        for operation in &mut self.operations {
+            if let StagedOperation::Create { step, schedule, .. } = operation {
+                if schedule.due_date.is_none() {
+                    schedule.due_date = due.get(step).map(|date| date.format("%Y-%m-%d").to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The created subtasks as proposed plan items (used by draft runs)
+    pub fn proposed_items(&self) -> Vec<ProposedSubtask> {
+        self.operations
+            .iter()
+            .filter_map(|op| match op {
+                StagedOperation::Create {
+                    step,
+                    title,
+                    description,
+                    agent_id,
+                    depends_on,
+                    schedule,
+                    parent,
+                } => Some(ProposedSubtask {
+                    step: *step,
+                    title: title.clone(),
+                    description: description.clone(),
+                    agent_id: *agent_id,
+                    depends_on: depends_on.clone(),
+                    schedule: schedule.clone(),
+                    parent_step: match parent {
+                        Some(SubtaskRef::Step(step)) => Some(*step),
+                        _ => None,
+                    },
+                    parent_subtask_id: match parent {
+                        Some(SubtaskRef::Existing(id)) => Some(*id),
+                        _ => None,
+                    },
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Apply all staged operations to the task's subtasks in a single transaction
+    pub async fn commit(&self, task_id: i32) -> Result<Vec<PlanChange>, String> {
+        let pool = get_db_pool()?;
+
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let mut ids_by_step: HashMap<i64, i64> = HashMap::new();
+        let mut changes = Vec::new();
+
+        for operation in &self.operations {
+            match operation {
+                StagedOperation::Create {
+                    step,
+                    title,
+                    description,
+                    agent_id,
+                    depends_on,
+                    schedule,
+                    parent,
+                } => {
+                    let parent_id = parent.and_then(|parent| match parent {
+                        SubtaskRef::Existing(id) => Some(id),
+                        SubtaskRef::Step(step) => ids_by_step.get(&step).copied(),
+                    });
+
+                    let result = sqlx::query(
+                        "INSERT INTO subtasks (task_id, title, description, agent_id, parent_subtask_id, estimated_minutes, due_date, priority, completed, position, created_at, updated_at)
+                         VALUES (?, ?, ?, ?, ?, ?, ?, ?, FALSE, (SELECT COALESCE(MAX(position), 0) + 1 FROM subtasks WHERE task_id = ?), CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+                    )
+                    .bind(task_id)
+                    .bind(title)
+                    .bind(description)
+                    .bind(agent_id)
+                    .bind(parent_id)
+                    .bind(schedule.estimated_minutes)
+                    .bind(&schedule.due_date)
+                    .bind(&schedule.priority)
+                    .bind(task_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Failed to create subtask: {}", e))?;
+
+                    let subtask_id = result.last_insert_rowid();
+                    ids_by_step.insert(*step, subtask_id);
+
+                    let dependency_ids: Vec<i64> = depends_on
+                        .iter()
+                        .filter_map(|dep| ids_by_step.get(dep).copied())
+                        .collect();
+                    add_dependencies(&mut tx, subtask_id, &dependency_ids).await?;
+
+                    changes.push(PlanChange {
+                        action: "created".to_string(),
+                        subtask_id: Some(subtask_id),
+                        title: title.clone(),
+                    });
+                }
+                StagedOperation::Update {
+                    subtask_id,
+                    title,
+                    description,
+                    agent_id,
+                    display_title,
+                } => {
+                    sqlx::query(
+                        "UPDATE subtasks SET title = COALESCE(?, title), description = COALESCE(?, description),
+                         agent_id = COALESCE(?, agent_id), updated_at = CURRENT_TIMESTAMP
+                         WHERE id = ? AND task_id = ?",
+                    )
+                    .bind(title)
+                    .bind(description)
+                    .bind(agent_id)
+                    .bind(subtask_id)
+                    .bind(task_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Failed to update subtask: {}", e))?;
+
+                    changes.push(PlanChange {
+                        action: "updated".to_string(),
+                        subtask_id: Some(*subtask_id),
+                        title: display_title.clone(),
+                    });
+                }
+                StagedOperation::Delete { subtask_id, title } => {
+                    // A draft may be approved after work under the subtask was finished
+                    let completed = completed_descendants(&mut *tx, *subtask_id).await?;
+                    if !completed.is_empty() {
+                        return Err(format!(
+                            "Cannot delete subtask '{}': completed subtasks under it would go too ({})",
+                            title,
+                            completed.join(", ")
+                        ));
+                    }
+                    sqlx::query("DELETE FROM subtasks WHERE id = ? AND task_id = ?")
+                        .bind(subtask_id)
+                        .bind(task_id)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(|e| format!("Failed to delete subtask: {}", e))?;
+
+                    changes.push(PlanChange {
+                        action: "deleted".to_string(),
+                        subtask_id: Some(*subtask_id),
+                        title: title.clone(),
+                    });
+                }
+                StagedOperation::Reorder { order } => {
+                    let requested: Vec<i64> = order
+                        .iter()
+                        .filter_map(|r| match r {
+                            SubtaskRef::Existing(id) => Some(*id),
+                            SubtaskRef::Step(step) => ids_by_step.get(step).copied(),
+                        })
+                        .collect();
+
+                    let current: Vec<i64> = sqlx::query_scalar(
+                        "SELECT id FROM subtasks WHERE task_id = ? ORDER BY position ASC, created_at ASC",
+                    )
+                    .bind(task_id)
+                    .fetch_all(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Database error: {}", e))?;
+
+                    // Listed subtasks first, then the rest in their current order
+                    let mut ordered: Vec<i64> = Vec::with_capacity(current.len());
+                    for id in requested.iter().chain(current.iter()) {
+                        if current.contains(id) && !ordered.contains(id) {
+                            ordered.push(*id);
+                        }
+                    }
+
+                    for (index, id) in ordered.iter().enumerate() {
+                        sqlx::query("UPDATE subtasks SET position = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                            .bind(index as i64 + 1)
+                            .bind(id)
+                            .execute(&mut *tx)
+                            .await
+                            .map_err(|e| format!("Failed to reorder subtasks: {}", e))?;
+                    }
+
+                    changes.push(PlanChange {
+                        action: "reordered".to_string(),
+                        subtask_id: None,
+                        title: format!("{} subtasks", ordered.len()),
+                    });
+                }
+            }
+        }
+
+        validate_task_dependencies(&mut tx, task_id as i64).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit planning run: {}", e))?;
+
+        Ok(changes)
+    }
+}
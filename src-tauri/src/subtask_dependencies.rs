@@ -0,0 +1,219 @@
+use crate::database::{SubTask, SUBTASK_COLUMNS};
+use crate::settings::get_db_pool;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct SubtaskDependency {
+    pub subtask_id: i64,
+    pub depends_on_subtask_id: i64,
+}
+
+/// Order nodes so that every node comes after the nodes it depends on.
+/// Edges are `(node, depends_on)` pairs; edges to unknown nodes are ignored.
+/// Ties keep the input order, so callers should pass nodes sorted by position.
+pub fn topological_sort(nodes: &[i64], edges: &[(i64, i64)]) -> Result<Vec<i64>, String> {
+    let known: HashSet<i64> = nodes.iter().copied().collect();
+    let mut remaining: Vec<i64> = nodes.to_vec();
+    let mut done: HashSet<i64> = HashSet::new();
+    let mut order = Vec::with_capacity(nodes.len());
+
+    while !remaining.is_empty() {
+        let ready = remaining.iter().position(|node| {
+            edges
+                .iter()
+                .filter(|(from, _)| from == node)
+                .all(|(_, to)| done.contains(to) || !known.contains(to))
+        });
+
+        match ready {
+            Some(index) => {
+                let node = remaining.remove(index);
+                done.insert(node);
+                order.push(node);
+            }
+            None => {
+                let ids = remaining
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(format!("Dependency cycle detected between subtasks {}", ids));
+            }
+        }
+    }
+
+    Ok(order)
+}
+
+async fn load_subtasks(task_id: i64) -> Result<Vec<SubTask>, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, SubTask>(&format!(
+        "SELECT {} FROM subtasks WHERE task_id = ? ORDER BY position ASC, created_at ASC",
+        SUBTASK_COLUMNS
+    ))
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+async fn load_edges(task_id: i64) -> Result<Vec<(i64, i64)>, String> {
+    let deps = get_subtask_dependencies(task_id).await?;
+    Ok(deps
+        .into_iter()
+        .map(|d| (d.subtask_id, d.depends_on_subtask_id))
+        .collect())
+}
+
+/// Sort a task's subtasks into dependency order
+async fn ordered_subtasks(task_id: i64) -> Result<(Vec<SubTask>, Vec<(i64, i64)>), String> {
+    let mut subtasks = load_subtasks(task_id).await?;
+    let edges = load_edges(task_id).await?;
+
+    let ids: Vec<i64> = subtasks.iter().map(|s| s.id).collect();
+    let order = topological_sort(&ids, &edges)?;
+    subtasks.sort_by_key(|s| order.iter().position(|id| *id == s.id));
+
+    Ok((subtasks, edges))
+}
+
+/// Fail if the dependency graph of a task contains a cycle, reading through
+/// `conn` so uncommitted changes in a transaction are included
+pub async fn validate_task_dependencies(
+    conn: &mut sqlx::SqliteConnection,
+    task_id: i64,
+) -> Result<(), String> {
+    let ids: Vec<i64> = sqlx::query_scalar(
+        "SELECT id FROM subtasks WHERE task_id = ? ORDER BY position ASC, created_at ASC",
+    )
+    .bind(task_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let edges: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT d.subtask_id, d.depends_on_subtask_id FROM subtask_dependencies d
+         JOIN subtasks s ON s.id = d.subtask_id WHERE s.task_id = ?",
+    )
+    .bind(task_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    topological_sort(&ids, &edges).map(|_| ())
+}
+
+/// Record that `subtask_id` depends on each of `depends_on`
+pub async fn add_dependencies(
+    conn: &mut sqlx::SqliteConnection,
+    subtask_id: i64,
+    depends_on: &[i64],
+) -> Result<(), String> {
+    for dep in depends_on {
+        sqlx::query(
+            "INSERT OR IGNORE INTO subtask_dependencies (subtask_id, depends_on_subtask_id) VALUES (?, ?)",
+        )
+        .bind(subtask_id)
+        .bind(dep)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to add subtask dependency: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Get all dependency edges between the subtasks of a task
+#[tauri::command]
+pub async fn get_subtask_dependencies(task_id: i64) -> Result<Vec<SubtaskDependency>, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, SubtaskDependency>(
+        "SELECT d.subtask_id, d.depends_on_subtask_id
+         FROM subtask_dependencies d
+         JOIN subtasks s ON s.id = d.subtask_id
+         WHERE s.task_id = ?
+         ORDER BY d.subtask_id, d.depends_on_subtask_id",
+    )
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Replace the dependencies of a subtask, rejecting changes that would create a cycle
+#[tauri::command]
+pub async fn set_subtask_dependencies(subtask_id: i64, depends_on: Vec<i64>) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    let task_id: i64 = sqlx::query_scalar("SELECT task_id FROM subtasks WHERE id = ?")
+        .bind(subtask_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Subtask {} not found", subtask_id))?;
+
+    let subtasks = load_subtasks(task_id).await?;
+    let ids: Vec<i64> = subtasks.iter().map(|s| s.id).collect();
+
+    if let Some(dep) = depends_on.iter().find(|dep| !ids.contains(dep) || **dep == subtask_id) {
+        return Err(format!("Subtask {} cannot depend on subtask {}", subtask_id, dep));
+    }
+
+    let mut edges: Vec<(i64, i64)> = load_edges(task_id)
+        .await?
+        .into_iter()
+        .filter(|(from, _)| *from != subtask_id)
+        .collect();
+    edges.extend(depends_on.iter().map(|dep| (subtask_id, *dep)));
+    topological_sort(&ids, &edges)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    sqlx::query("DELETE FROM subtask_dependencies WHERE subtask_id = ?")
+        .bind(subtask_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to clear subtask dependencies: {}", e))?;
+
+    add_dependencies(&mut tx, subtask_id, &depends_on).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit subtask dependencies: {}", e))?;
+
+    Ok(())
+}
+
+/// Get a task's subtasks in dependency order
+#[tauri::command]
+pub async fn get_subtask_topological_order(task_id: i64) -> Result<Vec<SubTask>, String> {
+    ordered_subtasks(task_id).await.map(|(subtasks, _)| subtasks)
+}
+
+/// Get the incomplete subtasks of a task whose dependencies are all completed
+#[tauri::command]
+pub async fn get_unblocked_subtasks(task_id: i64) -> Result<Vec<SubTask>, String> {
+    let (subtasks, edges) = ordered_subtasks(task_id).await?;
+
+    let completed: HashSet<i64> = subtasks
+        .iter()
+        .filter(|s| s.completed)
+        .map(|s| s.id)
+        .collect();
+
+    Ok(subtasks
+        .into_iter()
+        .filter(|s| !s.completed)
+        .filter(|s| {
+            edges
+                .iter()
+                .filter(|(from, _)| *from == s.id)
+                .all(|(_, to)| completed.contains(to))
+        })
+        .collect())
+}
@@ -0,0 +1,732 @@
+use crate::chat::{send_chat_message, ChatMessage};
+use crate::database::{SubTask, SUBTASK_COLUMNS};
+use crate::planning_agent::{ClaudeResponse, ContentBlock};
+use crate::planning_context::{estimate_tokens, gather_planning_context, truncate_to_tokens};
+use crate::settings::get_db_pool;
+use crate::subtask_dependencies::get_unblocked_subtasks;
+use crate::document_revisions::RevisionAttribution;
+use crate::edit_locks::{acquire_edit_lock_wait, release_edit_lock};
+use crate::task_attachments::save_attachment;
+use crate::task_notes::{read_task_notes, save_task_notes};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Mutex, OnceLock};
+use tauri::Emitter;
+use tokio::sync::Semaphore;
+
+/// Concurrent agent runs when the `subtask_execution_concurrency` setting is not set
+const DEFAULT_CONCURRENCY: usize = 2;
+
+// Token budgets for the context handed to the executing agent
+const CONTEXT_TOKEN_BUDGET: usize = 4000;
+const PRIOR_OUTPUT_TOKEN_BUDGET: usize = 4000;
+
+const MAX_OUTPUT_TOKENS: u32 = 4096;
+
+/// How long delivery waits for the notes lease before keeping the output as an artifact
+const NOTES_LOCK_TIMEOUT_MS: u64 = 30_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct SubtaskExecution {
+    pub id: i64,
+    pub subtask_id: i64,
+    pub task_id: i64,
+    pub agent_id: i64,
+    pub status: String, // 'queued', 'running', 'succeeded', 'failed', 'cancelled'
+    pub output_target: String, // 'notes' or 'artifact'
+    pub require_review: bool,
+    pub continue_with_unblocked: bool,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+const EXECUTION_COLUMNS: &str = "id, subtask_id, task_id, agent_id, status, output_target, require_review,
+     continue_with_unblocked, output, error, input_tokens, output_tokens, created_at, started_at, finished_at";
+
+/// How an execution delivers its result
+#[derive(Debug, Clone)]
+pub struct ExecutionOptions {
+    pub output_target: String,
+    pub require_review: bool,
+    pub continue_with_unblocked: bool,
+}
+
+impl ExecutionOptions {
+    fn new(
+        output_target: Option<String>,
+        require_review: Option<bool>,
+        continue_with_unblocked: Option<bool>,
+    ) -> Result<Self, String> {
+        let output_target = output_target.unwrap_or_else(|| "notes".to_string());
+        if output_target != "notes" && output_target != "artifact" {
+            return Err("output_target must be 'notes' or 'artifact'".to_string());
+        }
+        Ok(Self {
+            output_target,
+            require_review: require_review.unwrap_or(true),
+            continue_with_unblocked: continue_with_unblocked.unwrap_or(false),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct ExecutionEvent {
+    execution_id: i64,
+    subtask_id: i64,
+    task_id: i64,
+    status: String,
+    message: String,
+}
+
+// Limits how many agents run at once; sized from settings on first use
+static QUEUE: OnceLock<Semaphore> = OnceLock::new();
+
+// Abort handles of queued and running executions, keyed by execution id
+static EXECUTIONS: OnceLock<Mutex<HashMap<i64, tokio::task::AbortHandle>>> = OnceLock::new();
+
+fn executions() -> &'static Mutex<HashMap<i64, tokio::task::AbortHandle>> {
+    EXECUTIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The run queue. Changes to the concurrency setting apply after a restart.
+async fn queue() -> Result<&'static Semaphore, String> {
+    if let Some(queue) = QUEUE.get() {
+        return Ok(queue);
+    }
+
+    let pool = get_db_pool()?;
+    let limit: Option<String> =
+        sqlx::query_scalar("SELECT value FROM settings WHERE key = 'subtask_execution_concurrency'")
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    let limit = limit
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|limit| *limit > 0)
+        .unwrap_or(DEFAULT_CONCURRENCY);
+
+    Ok(QUEUE.get_or_init(|| Semaphore::new(limit)))
+}
+
+fn emit_event(app: &tauri::AppHandle, name: &str, execution: &SubtaskExecution, status: &str, message: &str) {
+    let event = ExecutionEvent {
+        execution_id: execution.id,
+        subtask_id: execution.subtask_id,
+        task_id: execution.task_id,
+        status: status.to_string(),
+        message: message.to_string(),
+    };
+    if let Err(e) = app.emit(name, event) {
+        eprintln!("Warning: Failed to emit {}: {}", name, e);
+    }
+}
+
+async fn load_subtask(subtask_id: i64) -> Result<SubTask, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, SubTask>(&format!("SELECT {} FROM subtasks WHERE id = ?", SUBTASK_COLUMNS))
+        .bind(subtask_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Subtask {} not found", subtask_id))
+}
+
+async fn load_execution(execution_id: i64) -> Result<SubtaskExecution, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, SubtaskExecution>(&format!(
+        "SELECT {} FROM subtask_executions WHERE id = ?",
+        EXECUTION_COLUMNS
+    ))
+    .bind(execution_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Subtask execution {} not found", execution_id))
+}
+
+async fn set_subtask_status(subtask_id: i64, status: &str) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query(
+        "UPDATE subtasks SET execution_status = ?, completed = (? = 'completed' OR completed),
+         updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(status)
+    .bind(status)
+    .bind(subtask_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update subtask status: {}", e))?;
+
+    Ok(())
+}
+
+/// Queue a subtask for its assigned agent. It must be incomplete, idle or
+/// failed, and all of its dependencies must be completed.
+// Boxed because a finished run may queue the next subtasks, which makes this recursive
+fn enqueue(
+    app: tauri::AppHandle,
+    subtask_id: i64,
+    options: ExecutionOptions,
+) -> Pin<Box<dyn Future<Output = Result<i64, String>> + Send>> {
+    Box::pin(async move { enqueue_inner(app, subtask_id, &options).await })
+}
+
+async fn enqueue_inner(
+    app: tauri::AppHandle,
+    subtask_id: i64,
+    options: &ExecutionOptions,
+) -> Result<i64, String> {
+    let subtask = load_subtask(subtask_id).await?;
+
+    if subtask.completed {
+        return Err(format!("Subtask {} is already completed", subtask_id));
+    }
+    if !matches!(subtask.execution_status.as_str(), "idle" | "failed") {
+        return Err(format!(
+            "Subtask {} is already {}",
+            subtask_id, subtask.execution_status
+        ));
+    }
+    let agent_id = subtask
+        .agent_id
+        .ok_or_else(|| format!("Subtask {} has no assigned agent", subtask_id))?;
+
+    let unblocked = get_unblocked_subtasks(subtask.task_id).await?;
+    if !unblocked.iter().any(|s| s.id == subtask_id) {
+        return Err(format!(
+            "Subtask {} is waiting for subtasks it depends on",
+            subtask_id
+        ));
+    }
+
+    let queue = queue().await?;
+    let pool = get_db_pool()?;
+
+    // Claim the subtask and record its execution together, so a failure
+    // cannot leave it queued with nothing to run it
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // Claim the subtask so a second request for it fails instead of running it twice
+    let claimed = sqlx::query(
+        "UPDATE subtasks SET execution_status = 'queued', updated_at = CURRENT_TIMESTAMP
+         WHERE id = ? AND execution_status IN ('idle', 'failed')",
+    )
+    .bind(subtask_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to queue subtask: {}", e))?;
+    if claimed.rows_affected() == 0 {
+        return Err(format!("Subtask {} is already queued or running", subtask_id));
+    }
+
+    let execution = sqlx::query_as::<_, SubtaskExecution>(&format!(
+        "INSERT INTO subtask_executions (subtask_id, task_id, agent_id, status, output_target, require_review,
+         continue_with_unblocked, created_at)
+         VALUES (?, ?, ?, 'queued', ?, ?, ?, CURRENT_TIMESTAMP)
+         RETURNING {}",
+        EXECUTION_COLUMNS
+    ))
+    .bind(subtask_id)
+    .bind(subtask.task_id)
+    .bind(agent_id)
+    .bind(&options.output_target)
+    .bind(options.require_review)
+    .bind(options.continue_with_unblocked)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to queue subtask: {}", e))?;
+    let execution_id = execution.id;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to queue subtask: {}", e))?;
+
+    emit_event(&app, "subtask-execution-progress", &execution, "queued", "Waiting for a free agent slot");
+
+    let handle = tokio::spawn(async move {
+        let Ok(_permit) = queue.acquire().await else {
+            return;
+        };
+        run_execution(app, execution).await;
+        if let Ok(mut running) = executions().lock() {
+            running.remove(&execution_id);
+        }
+    });
+
+    if let Ok(mut running) = executions().lock() {
+        running.insert(execution_id, handle.abort_handle());
+    }
+
+    Ok(execution_id)
+}
+
+/// Outputs of the task's completed subtasks, oldest plan step first
+async fn prior_outputs(task_id: i64, subtask_id: i64) -> Result<String, String> {
+    let pool = get_db_pool()?;
+
+    let rows = sqlx::query(
+        "SELECT s.title, e.output
+         FROM subtasks s
+         JOIN subtask_executions e ON e.id = (
+             SELECT MAX(id) FROM subtask_executions
+             WHERE subtask_id = s.id AND status = 'succeeded'
+         )
+         WHERE s.task_id = ? AND s.id != ? AND s.completed = TRUE
+         ORDER BY s.position ASC",
+    )
+    .bind(task_id)
+    .bind(subtask_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut remaining = PRIOR_OUTPUT_TOKEN_BUDGET;
+    let mut sections = Vec::new();
+    for row in rows {
+        if remaining == 0 {
+            break;
+        }
+        let title: String = row.try_get("title").unwrap_or_default();
+        let output: Option<String> = row.try_get("output").unwrap_or(None);
+        let Some(output) = output.filter(|o| !o.trim().is_empty()) else {
+            continue;
+        };
+        let output = truncate_to_tokens(output.trim(), remaining);
+        remaining = remaining.saturating_sub(estimate_tokens(&output));
+        sections.push(format!("### {}\n\n{}", title, output));
+    }
+
+    Ok(sections.join("\n\n"))
+}
+
+/// Run the subtask's agent once and return its deliverable with token usage
+async fn run_agent(
+    app: &tauri::AppHandle,
+    execution: &SubtaskExecution,
+    subtask: &SubTask,
+) -> Result<(String, i64, i64), String> {
+    let pool = get_db_pool()?;
+
+    let agent = sqlx::query("SELECT name, model_name, agent_prompt FROM agents WHERE id = ?")
+        .bind(execution.agent_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Agent {} not found", execution.agent_id))?;
+    let model_name: String = agent
+        .try_get("model_name")
+        .map_err(|e| format!("Failed to get model_name: {}", e))?;
+    let agent_prompt: String = agent.try_get("agent_prompt").unwrap_or_default();
+
+    let task = sqlx::query("SELECT title, description FROM tasks WHERE id = ?")
+        .bind(execution.task_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Task {} not found", execution.task_id))?;
+    let task_title: String = task.try_get("title").unwrap_or_default();
+    let task_description: Option<String> = task.try_get("description").unwrap_or(None);
+
+    let context = gather_planning_context(execution.task_id as i32, CONTEXT_TOKEN_BUDGET).await?;
+    let prior = prior_outputs(execution.task_id, subtask.id).await?;
+
+    let mut system_prompt = format!(
+        "{}\n\n## Task\n\n**Title**: {}\n**Description**: {}",
+        agent_prompt,
+        task_title,
+        task_description.as_deref().unwrap_or("No description provided")
+    );
+    if !context.prompt_section.is_empty() {
+        system_prompt.push_str(&format!("\n\n## Workspace Context\n\n{}", context.prompt_section));
+    }
+    if !context.related_knowledge.is_empty() {
+        system_prompt.push_str(&format!("\n\n## Related Knowledge\n\n{}", context.related_knowledge));
+    }
+    if !prior.is_empty() {
+        system_prompt.push_str(&format!("\n\n## Output From Completed Subtasks\n\n{}", prior));
+    }
+
+    let user_message = format!(
+        "You have been assigned this subtask of the task above. Complete it and reply with the finished deliverable only, in Markdown.\n\n**Subtask**: {}\n\n{}",
+        subtask.title,
+        subtask.description.as_deref().unwrap_or("")
+    );
+
+    let response_text = send_chat_message(
+        app.clone(),
+        model_name,
+        vec![ChatMessage {
+            role: "user".to_string(),
+            content: serde_json::Value::String(user_message),
+        }],
+        Some(system_prompt),
+        MAX_OUTPUT_TOKENS,
+        None,
+        None,
+    )
+    .await?;
+
+    let response: ClaudeResponse = serde_json::from_str(&response_text)
+        .map_err(|e| format!("Failed to parse agent response: {}", e))?;
+
+    let output = response
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    if output.trim().is_empty() {
+        return Err("Agent returned no output".to_string());
+    }
+
+    let (input_tokens, output_tokens) = response
+        .usage
+        .map(|u| (u.input_tokens as i64, u.output_tokens as i64))
+        .unwrap_or_default();
+
+    Ok((output, input_tokens, output_tokens))
+}
+
+/// Append the deliverable to the task notes, unless someone keeps them locked
+/// for too long. Returns where the output ended up.
+async fn deliver(
+    app: &tauri::AppHandle,
+    execution: &SubtaskExecution,
+    subtask: &SubTask,
+    output: &str,
+) -> Result<String, String> {
+    if execution.output_target != "notes" {
+        return save_artifact(app, execution, subtask, output).await;
+    }
+
+    // Hold the notes lease across the read and the write, so executions of
+    // the same task finishing together append one after the other
+    let owner_id = format!("subtask-execution-{}", execution.id);
+    let acquired = acquire_edit_lock_wait(
+        execution.task_id,
+        "agent".to_string(),
+        owner_id.clone(),
+        None,
+        None,
+        NOTES_LOCK_TIMEOUT_MS,
+        app.clone(),
+    )
+    .await?;
+    if !acquired {
+        return save_artifact(app, execution, subtask, output).await;
+    }
+
+    let appended = append_to_notes(execution, subtask, output).await;
+    if let Err(e) = release_edit_lock(execution.task_id, owner_id, app.clone()).await {
+        eprintln!("Warning: Failed to release notes lease of execution {}: {}", execution.id, e);
+    }
+    appended?;
+
+    let _ = app.emit("task-notes-changed", serde_json::json!({ "taskId": execution.task_id }));
+    Ok("notes".to_string())
+}
+
+async fn append_to_notes(execution: &SubtaskExecution, subtask: &SubTask, output: &str) -> Result<(), String> {
+    let notes = read_task_notes(execution.task_id as i32).await?;
+    let section = format!("## {}\n\n{}\n", subtask.title, output.trim());
+    let content = if notes.trim().is_empty() {
+        section
+    } else {
+        format!("{}\n\n{}", notes.trim_end(), section)
+    };
+    save_task_notes(
+        execution.task_id as i32,
+        &content,
+        &RevisionAttribution::agent(Some(execution.agent_id)),
+        None,
+    )
+    .await
+}
+
+/// Attach output that does not go into the notes to the task as a Markdown file
+async fn save_artifact(
+    app: &tauri::AppHandle,
+    execution: &SubtaskExecution,
+    subtask: &SubTask,
+    output: &str,
+) -> Result<String, String> {
+    if let Err(e) = save_attachment(
+        app,
+        execution.task_id,
+        &format!("{}.md", subtask.title.replace(['/', '\\'], "-")),
+        output.as_bytes(),
+        Some("text/markdown"),
+        &RevisionAttribution::agent(Some(execution.agent_id)),
+        Some(execution.id),
+    )
+    .await
+    {
+        // The output is still kept with the execution
+        eprintln!("Warning: Failed to attach output of execution {}: {}", execution.id, e);
+    }
+    Ok("artifact".to_string())
+}
+
+async fn run_execution(app: tauri::AppHandle, execution: SubtaskExecution) {
+    if let Err(e) = execute(&app, &execution).await {
+        let pool = get_db_pool();
+        if let Ok(pool) = pool {
+            let _ = sqlx::query(
+                "UPDATE subtask_executions SET status = 'failed', error = ?, finished_at = CURRENT_TIMESTAMP WHERE id = ?",
+            )
+            .bind(&e)
+            .bind(execution.id)
+            .execute(pool)
+            .await;
+        }
+        let _ = set_subtask_status(execution.subtask_id, "failed").await;
+        emit_event(&app, "subtask-execution-complete", &execution, "failed", &e);
+    }
+}
+
+async fn execute(app: &tauri::AppHandle, execution: &SubtaskExecution) -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query("UPDATE subtask_executions SET status = 'running', started_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(execution.id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to start execution: {}", e))?;
+    set_subtask_status(execution.subtask_id, "running").await?;
+
+    let subtask = load_subtask(execution.subtask_id).await?;
+    emit_event(app, "subtask-execution-progress", execution, "running", &format!("Working on '{}'", subtask.title));
+
+    let (output, input_tokens, output_tokens) = run_agent(app, execution, &subtask).await?;
+    let target = deliver(app, execution, &subtask, &output).await?;
+
+    sqlx::query(
+        "UPDATE subtask_executions SET status = 'succeeded', output = ?, output_target = ?, input_tokens = ?,
+         output_tokens = ?, finished_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(&output)
+    .bind(&target)
+    .bind(input_tokens)
+    .bind(output_tokens)
+    .bind(execution.id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record execution output: {}", e))?;
+
+    let status = if execution.require_review { "review" } else { "completed" };
+    set_subtask_status(execution.subtask_id, status).await?;
+
+    let message = match (status, target.as_str()) {
+        ("review", "notes") => "Output added to task notes, waiting for review",
+        ("review", _) => "Output saved, waiting for review",
+        (_, "notes") => "Output added to task notes",
+        _ => "Output saved",
+    };
+    emit_event(app, "subtask-execution-complete", execution, status, message);
+
+    if status == "completed" && execution.continue_with_unblocked {
+        continue_task(app.clone(), execution).await;
+    }
+
+    Ok(())
+}
+
+/// Queue the subtasks unblocked by a finished execution, with the same options
+async fn continue_task(app: tauri::AppHandle, execution: &SubtaskExecution) {
+    let options = ExecutionOptions {
+        output_target: execution.output_target.clone(),
+        require_review: execution.require_review,
+        continue_with_unblocked: true,
+    };
+    if let Err(e) = enqueue_unblocked(app, execution.task_id, &options).await {
+        eprintln!("Warning: Failed to continue task {}: {}", execution.task_id, e);
+    }
+}
+
+/// Queue a task's unblocked idle subtasks. One that cannot be queued (e.g.
+/// because a concurrent run claimed it first) is skipped; the call only fails
+/// when none could be queued.
+async fn enqueue_unblocked(
+    app: tauri::AppHandle,
+    task_id: i64,
+    options: &ExecutionOptions,
+) -> Result<Vec<i64>, String> {
+    let mut queued = Vec::new();
+    let mut failures = Vec::new();
+    for subtask in get_unblocked_subtasks(task_id).await? {
+        if subtask.agent_id.is_none() || subtask.execution_status != "idle" {
+            continue;
+        }
+        match enqueue(app.clone(), subtask.id, options.clone()).await {
+            Ok(execution_id) => queued.push(execution_id),
+            Err(e) => failures.push(format!("subtask {}: {}", subtask.id, e)),
+        }
+    }
+
+    if !failures.is_empty() {
+        if queued.is_empty() {
+            return Err(format!("Failed to queue subtasks of task {}: {}", task_id, failures.join("; ")));
+        }
+        eprintln!(
+            "Warning: Skipped subtasks of task {} that could not be queued: {}",
+            task_id,
+            failures.join("; ")
+        );
+    }
+    Ok(queued)
+}
+
+/// Fail executions left queued or running when the app quit
+pub async fn recover_interrupted_executions() -> Result<(), String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query(
+        "UPDATE subtasks SET execution_status = 'idle'
+         WHERE execution_status IN ('queued', 'running')",
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to reset subtask status: {}", e))?;
+
+    sqlx::query(
+        "UPDATE subtask_executions SET status = 'failed', error = 'Interrupted before completion',
+         finished_at = CURRENT_TIMESTAMP WHERE status IN ('queued', 'running')",
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to close interrupted executions: {}", e))?;
+
+    Ok(())
+}
+
+/// Queue a subtask to be carried out by its assigned agent
+#[tauri::command]
+pub async fn run_subtask(
+    subtask_id: i64,
+    output_target: Option<String>,
+    require_review: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<i64, String> {
+    let options = ExecutionOptions::new(output_target, require_review, Some(false))?;
+    enqueue(app_handle, subtask_id, options).await
+}
+
+/// Queue every unblocked subtask of a task that has an agent. With
+/// `continue_with_unblocked`, subtasks unblocked by completed runs are queued too.
+#[tauri::command]
+pub async fn run_unblocked_subtasks(
+    task_id: i64,
+    output_target: Option<String>,
+    require_review: Option<bool>,
+    continue_with_unblocked: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<i64>, String> {
+    let options = ExecutionOptions::new(output_target, require_review, continue_with_unblocked)?;
+    enqueue_unblocked(app_handle, task_id, &options).await
+}
+
+#[tauri::command]
+pub async fn cancel_subtask_execution(
+    execution_id: i64,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let execution = load_execution(execution_id).await?;
+    if !matches!(execution.status.as_str(), "queued" | "running") {
+        return Err(format!("Subtask execution {} is not in progress", execution_id));
+    }
+
+    let handle = executions()
+        .lock()
+        .map_err(|_| "Execution registry lock poisoned".to_string())?
+        .remove(&execution_id);
+    if let Some(handle) = handle {
+        handle.abort();
+    }
+
+    let pool = get_db_pool()?;
+    sqlx::query(
+        "UPDATE subtask_executions SET status = 'cancelled', finished_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(execution_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to cancel execution: {}", e))?;
+    set_subtask_status(execution.subtask_id, "idle").await?;
+
+    emit_event(&app_handle, "subtask-execution-complete", &execution, "cancelled", "Execution cancelled");
+    Ok(())
+}
+
+/// Accept or reject the output of a subtask waiting for review. Rejected
+/// subtasks go back to idle so they can be run again.
+#[tauri::command]
+pub async fn review_subtask_output(
+    subtask_id: i64,
+    approve: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let subtask = load_subtask(subtask_id).await?;
+    if subtask.execution_status != "review" {
+        return Err(format!("Subtask {} is not waiting for review", subtask_id));
+    }
+
+    set_subtask_status(subtask_id, if approve { "completed" } else { "idle" }).await?;
+
+    if approve {
+        let pool = get_db_pool()?;
+        let latest_id: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(id) FROM subtask_executions WHERE subtask_id = ? AND status = 'succeeded'",
+        )
+        .bind(subtask_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        if let Some(latest_id) = latest_id {
+            let execution = load_execution(latest_id).await?;
+            if execution.continue_with_unblocked {
+                continue_task(app_handle, &execution).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// List the executions of a task's subtasks, newest first
+#[tauri::command]
+pub async fn list_subtask_executions(task_id: i64) -> Result<Vec<SubtaskExecution>, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, SubtaskExecution>(&format!(
+        "SELECT {} FROM subtask_executions WHERE task_id = ? ORDER BY created_at DESC, id DESC",
+        EXECUTION_COLUMNS
+    ))
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_subtask_execution(execution_id: i64) -> Result<SubtaskExecution, String> {
+    load_execution(execution_id).await
+}
@@ -0,0 +1,416 @@
+use crate::calendar;
+use crate::database::SubTask;
+use crate::settings::get_db_pool;
+use crate::subtask_dependencies::{get_subtask_dependencies, get_subtask_topological_order, topological_sort};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Effort assumed for subtasks without an estimate
+pub const DEFAULT_ESTIMATE_MINUTES: i64 = 60;
+
+// Focused work per day assumed when spreading due dates back from a deadline
+const FOCUS_MINUTES_PER_DAY: i64 = 240;
+
+// Working hours used to find free time in the calendar
+const WORKDAY_START: (u32, u32) = (9, 0);
+const WORKDAY_END: (u32, u32) = (17, 0);
+
+// How far ahead to schedule when neither the subtask nor the task has a due date
+const DEFAULT_HORIZON_DAYS: i64 = 14;
+
+const PRIORITIES: [&str; 3] = ["low", "medium", "high"];
+
+/// Effort, deadline and priority of a subtask
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SubtaskSchedule {
+    #[serde(default)]
+    pub estimated_minutes: Option<i64>,
+    // YYYY-MM-DD
+    #[serde(default)]
+    pub due_date: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+}
+
+impl SubtaskSchedule {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(minutes) = self.estimated_minutes.filter(|m| *m <= 0) {
+            return Err(format!("Invalid estimated_minutes: {}", minutes));
+        }
+        if let Some(date) = self.due_date.as_deref().filter(|d| parse_date(d).is_none()) {
+            return Err(format!("Invalid due_date '{}', expected YYYY-MM-DD", date));
+        }
+        if let Some(priority) = self.priority.as_deref().filter(|p| !PRIORITIES.contains(p)) {
+            return Err(format!(
+                "Invalid priority '{}', expected 'low', 'medium' or 'high'",
+                priority
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A proposed `scheduled_date` for a subtask
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduleProposal {
+    pub subtask_id: i64,
+    pub title: String,
+    pub scheduled_date: String,
+    pub estimated_minutes: i64,
+    pub due_date: Option<String>,
+    // False when no day before the due date had enough free time
+    pub fits_before_due: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduledSubtask {
+    pub subtask_id: i64,
+    pub scheduled_date: Option<String>,
+}
+
+/// Parse the date part of a stored date (`YYYY-MM-DD` or a full timestamp)
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    value
+        .get(..10)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// Read the scheduling fields of a `create_subtask` call. `due_days_before_deadline`
+/// is resolved against the task's due date.
+pub fn parse_schedule_input(
+    input: &serde_json::Value,
+    deadline: Option<NaiveDate>,
+) -> Result<SubtaskSchedule, String> {
+    let estimated_minutes = match input.get("estimated_minutes").filter(|v| !v.is_null()) {
+        Some(value) => match value.as_i64() {
+            Some(minutes) if minutes > 0 => Some(minutes),
+            _ => return Err("estimated_minutes must be a positive number of minutes".to_string()),
+        },
+        None => None,
+    };
+
+    let due_date = match input.get("due_days_before_deadline").filter(|v| !v.is_null()) {
+        Some(value) => {
+            let days = value
+                .as_i64()
+                .filter(|days| *days >= 0)
+                .ok_or("due_days_before_deadline must be zero or a positive number of days")?;
+            let deadline = deadline
+                .ok_or("The task has no due date, so due_days_before_deadline cannot be used")?;
+            Some(format_date(deadline - Duration::days(days)))
+        }
+        None => None,
+    };
+
+    let priority = match input["priority"].as_str() {
+        Some(priority) if PRIORITIES.contains(&priority) => Some(priority.to_string()),
+        Some(priority) => {
+            return Err(format!(
+                "Invalid priority '{}', expected 'low', 'medium' or 'high'",
+                priority
+            ))
+        }
+        None => None,
+    };
+
+    Ok(SubtaskSchedule {
+        estimated_minutes,
+        due_date,
+        priority,
+    })
+}
+
+fn work_days(minutes: i64) -> i64 {
+    ((minutes + FOCUS_MINUTES_PER_DAY - 1) / FOCUS_MINUTES_PER_DAY).max(1)
+}
+
+/// Work backward from the deadline: the last subtasks are due on the deadline and
+/// each subtask is due early enough for the work that depends on it to fit before
+/// that work's own due date. Nodes are `(id, estimated_minutes, explicit due date)`,
+/// edges `(node, depends_on)`. Explicit dates are kept; computed ones never fall
+/// before `today`.
+pub fn spread_due_dates(
+    nodes: &[(i64, Option<i64>, Option<NaiveDate>)],
+    edges: &[(i64, i64)],
+    deadline: NaiveDate,
+    today: NaiveDate,
+) -> Result<HashMap<i64, NaiveDate>, String> {
+    let ids: Vec<i64> = nodes.iter().map(|(id, _, _)| *id).collect();
+    let order = topological_sort(&ids, edges)?;
+
+    let mut due: HashMap<i64, NaiveDate> = HashMap::new();
+    for id in order.iter().rev() {
+        let explicit = nodes
+            .iter()
+            .find(|(n, _, _)| n == id)
+            .and_then(|(_, _, date)| *date);
+
+        let date = explicit.unwrap_or_else(|| {
+            edges
+                .iter()
+                .filter(|(_, depends_on)| depends_on == id)
+                .filter_map(|(dependent, _)| {
+                    let dependent_due = due.get(dependent)?;
+                    let effort = nodes
+                        .iter()
+                        .find(|(n, _, _)| n == dependent)
+                        .and_then(|(_, minutes, _)| *minutes)
+                        .unwrap_or(DEFAULT_ESTIMATE_MINUTES);
+                    Some(*dependent_due - Duration::days(work_days(effort)))
+                })
+                .min()
+                .unwrap_or(deadline)
+                .max(today)
+        });
+        due.insert(*id, date);
+    }
+
+    Ok(due)
+}
+
+/// The due date of a task, if it has a valid one
+pub async fn task_deadline(task_id: i32) -> Result<Option<NaiveDate>, String> {
+    let pool = get_db_pool()?;
+
+    let due_date: Option<String> = sqlx::query_scalar("SELECT due_date FROM tasks WHERE id = ?")
+        .bind(task_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .flatten();
+
+    Ok(due_date.as_deref().and_then(parse_date))
+}
+
+fn merge_minutes(mut intervals: Vec<(i64, i64)>) -> i64 {
+    intervals.sort();
+    let mut total = 0;
+    let mut current: Option<(i64, i64)> = None;
+    for (start, end) in intervals {
+        current = match current {
+            Some((s, e)) if start <= e => Some((s, e.max(end))),
+            Some((s, e)) => {
+                total += e - s;
+                Some((start, end))
+            }
+            None => Some((start, end)),
+        };
+    }
+    total + current.map(|(s, e)| e - s).unwrap_or(0)
+}
+
+/// Minutes of the working day on `date` not taken by timed calendar events
+fn free_minutes(date: NaiveDate, calendar_ids: &[String]) -> i64 {
+    if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+        return 0;
+    }
+
+    let day_start = NaiveTime::from_hms_opt(WORKDAY_START.0, WORKDAY_START.1, 0).unwrap_or_default();
+    let day_end = NaiveTime::from_hms_opt(WORKDAY_END.0, WORKDAY_END.1, 0).unwrap_or_default();
+    let workday = (day_end - day_start).num_minutes();
+
+    if calendar_ids.is_empty() {
+        return workday;
+    }
+
+    let events = match calendar::macos::get_events_for_date(calendar_ids.to_vec(), format_date(date)) {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("Warning: Could not read calendar for {}: {}", date, e);
+            return workday;
+        }
+    };
+
+    let minutes_of = |value: &str| -> Option<i64> {
+        let local = DateTime::parse_from_rfc3339(value).ok()?.with_timezone(&Local);
+        let time = if local.date_naive() < date {
+            day_start
+        } else if local.date_naive() > date {
+            day_end
+        } else {
+            local.time().clamp(day_start, day_end)
+        };
+        Some((time - day_start).num_minutes())
+    };
+
+    let busy: Vec<(i64, i64)> = events
+        .iter()
+        .filter(|event| !event.is_all_day)
+        .filter_map(|event| Some((minutes_of(&event.start_date)?, minutes_of(&event.end_date)?)))
+        .filter(|(start, end)| end > start)
+        .collect();
+
+    (workday - merge_minutes(busy)).max(0)
+}
+
+async fn selected_calendar_ids() -> Result<Vec<String>, String> {
+    let pool = get_db_pool()?;
+
+    let value: Option<String> =
+        sqlx::query_scalar("SELECT value FROM settings WHERE key = 'selected_calendar_ids'")
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(value
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+/// Minutes already booked per day by scheduled, incomplete subtasks of other tasks
+async fn booked_minutes(task_id: i64) -> Result<HashMap<NaiveDate, i64>, String> {
+    let pool = get_db_pool()?;
+
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT scheduled_date, SUM(COALESCE(estimated_minutes, ?))
+         FROM subtasks
+         WHERE scheduled_date IS NOT NULL AND completed = FALSE AND task_id != ?
+         GROUP BY scheduled_date",
+    )
+    .bind(DEFAULT_ESTIMATE_MINUTES)
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(date, minutes)| Some((parse_date(&date)?, minutes)))
+        .collect())
+}
+
+/// Propose a `scheduled_date` for each incomplete, unscheduled subtask of a task,
+/// in dependency order, using free time in the selected calendars. Nothing is saved;
+/// pass the accepted proposals to `set_subtask_schedule`.
+#[tauri::command]
+pub async fn propose_subtask_schedule(
+    task_id: i64,
+    calendar_ids: Option<Vec<String>>,
+    start_date: Option<String>,
+) -> Result<Vec<ScheduleProposal>, String> {
+    let today = Local::now().date_naive();
+    let start = match start_date.as_deref() {
+        Some(date) => parse_date(date).ok_or("Invalid start_date, expected YYYY-MM-DD")?,
+        None => today,
+    };
+    let deadline = task_deadline(task_id as i32).await?;
+    let calendar_ids = match calendar_ids {
+        Some(ids) => ids,
+        None => selected_calendar_ids().await?,
+    };
+
+    let subtasks: Vec<SubTask> = get_subtask_topological_order(task_id).await?;
+    let edges: Vec<(i64, i64)> = get_subtask_dependencies(task_id)
+        .await?
+        .into_iter()
+        .map(|d| (d.subtask_id, d.depends_on_subtask_id))
+        .collect();
+
+    let mut booked = booked_minutes(task_id).await?;
+    let mut free: HashMap<NaiveDate, i64> = HashMap::new();
+    let mut placed: HashMap<i64, NaiveDate> = HashMap::new();
+
+    // Subtasks that are already scheduled stay where they are
+    for subtask in subtasks.iter().filter(|s| !s.completed) {
+        if let Some(date) = subtask.scheduled_date.as_deref().and_then(parse_date) {
+            placed.insert(subtask.id, date);
+            *booked.entry(date).or_default() +=
+                subtask.estimated_minutes.unwrap_or(DEFAULT_ESTIMATE_MINUTES);
+        }
+    }
+
+    let mut proposals = Vec::new();
+    for subtask in subtasks
+        .iter()
+        .filter(|s| !s.completed && s.scheduled_date.is_none())
+    {
+        let estimate = subtask.estimated_minutes.unwrap_or(DEFAULT_ESTIMATE_MINUTES);
+
+        // Not before the subtasks it depends on
+        let earliest = edges
+            .iter()
+            .filter(|(from, _)| *from == subtask.id)
+            .filter_map(|(_, to)| placed.get(to).copied())
+            .max()
+            .unwrap_or(start)
+            .max(start);
+        let due = subtask.due_date.as_deref().and_then(parse_date).or(deadline);
+        let latest = due
+            .unwrap_or(earliest + Duration::days(DEFAULT_HORIZON_DAYS - 1))
+            .max(earliest);
+
+        let mut best: Option<(NaiveDate, i64)> = None;
+        let mut chosen = None;
+        let mut day = earliest;
+        while day <= latest {
+            let capacity = *free
+                .entry(day)
+                .or_insert_with(|| free_minutes(day, &calendar_ids));
+            let available = capacity - booked.get(&day).copied().unwrap_or(0);
+            if available >= estimate {
+                chosen = Some(day);
+                break;
+            }
+            if best.is_none_or(|(_, most)| available > most) {
+                best = Some((day, available));
+            }
+            day += Duration::days(1);
+        }
+
+        let fits_before_due = chosen.is_some();
+        let date = chosen
+            .or(best.map(|(date, _)| date))
+            .unwrap_or(earliest);
+
+        *booked.entry(date).or_default() += estimate;
+        placed.insert(subtask.id, date);
+
+        proposals.push(ScheduleProposal {
+            subtask_id: subtask.id,
+            title: subtask.title.clone(),
+            scheduled_date: format_date(date),
+            estimated_minutes: estimate,
+            due_date: due.map(format_date),
+            fits_before_due,
+        });
+    }
+
+    Ok(proposals)
+}
+
+/// Save scheduled dates for subtasks (`None` clears the date)
+#[tauri::command]
+pub async fn set_subtask_schedule(schedule: Vec<ScheduledSubtask>) -> Result<(), String> {
+    for entry in &schedule {
+        if let Some(date) = entry.scheduled_date.as_deref() {
+            parse_date(date)
+                .ok_or_else(|| format!("Invalid scheduled_date '{}', expected YYYY-MM-DD", date))?;
+        }
+    }
+
+    let pool = get_db_pool()?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    for entry in &schedule {
+        sqlx::query("UPDATE subtasks SET scheduled_date = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(entry.scheduled_date.as_deref().map(|d| &d[..10]))
+            .bind(entry.subtask_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to schedule subtask: {}", e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit subtask schedule: {}", e))?;
+
+    Ok(())
+}
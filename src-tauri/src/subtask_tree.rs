@@ -0,0 +1,125 @@
+use crate::database::{SubTask, SUBTASK_COLUMNS};
+use crate::settings::get_db_pool;
+use serde::Serialize;
+
+/// Deepest nesting of subtasks below a task (1 = top-level subtasks only)
+pub const MAX_SUBTASK_DEPTH: usize = 3;
+
+/// A subtask with its nested subtasks
+#[derive(Debug, Serialize, Clone)]
+pub struct SubtaskNode {
+    #[serde(flatten)]
+    pub subtask: SubTask,
+    pub children: Vec<SubtaskNode>,
+}
+
+/// Nesting level of a subtask: 1 for top-level, 2 for its children, and so on
+pub fn depth_of(subtasks: &[SubTask], subtask_id: i64) -> usize {
+    let mut depth = 0;
+    let mut current = Some(subtask_id);
+    while let Some(id) = current {
+        depth += 1;
+        // Guard against malformed parent chains
+        if depth > subtasks.len() {
+            break;
+        }
+        current = subtasks
+            .iter()
+            .find(|s| s.id == id)
+            .and_then(|s| s.parent_subtask_id);
+    }
+    depth
+}
+
+fn children_of(subtasks: &[SubTask], parent_id: i64) -> Vec<SubtaskNode> {
+    subtasks
+        .iter()
+        .filter(|s| s.parent_subtask_id == Some(parent_id))
+        .map(|s| SubtaskNode {
+            subtask: s.clone(),
+            children: children_of(subtasks, s.id),
+        })
+        .collect()
+}
+
+/// Arrange subtasks (in position order) into a forest; subtasks whose parent is
+/// not in the list are treated as roots
+pub fn build_tree(subtasks: &[SubTask]) -> Vec<SubtaskNode> {
+    subtasks
+        .iter()
+        .filter(|s| {
+            s.parent_subtask_id
+                .is_none_or(|parent| !subtasks.iter().any(|p| p.id == parent))
+        })
+        .map(|s| SubtaskNode {
+            subtask: s.clone(),
+            children: children_of(subtasks, s.id),
+        })
+        .collect()
+}
+
+/// Titles of the completed subtasks nested anywhere under a subtask, which
+/// deleting it would delete too (children cascade with their parent)
+pub async fn completed_descendants<'e, E: sqlx::SqliteExecutor<'e>>(
+    executor: E,
+    subtask_id: i64,
+) -> Result<Vec<String>, String> {
+    sqlx::query_scalar(
+        "WITH RECURSIVE descendants(id) AS (
+           SELECT id FROM subtasks WHERE parent_subtask_id = ?
+           UNION
+           SELECT s.id FROM subtasks s JOIN descendants d ON s.parent_subtask_id = d.id
+         )
+         SELECT title FROM subtasks WHERE id IN (SELECT id FROM descendants) AND completed = 1
+         ORDER BY position, id",
+    )
+    .bind(subtask_id)
+    .fetch_all(executor)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+async fn load_task_subtasks(task_id: i64) -> Result<Vec<SubTask>, String> {
+    let pool = get_db_pool()?;
+
+    sqlx::query_as::<_, SubTask>(&format!(
+        "SELECT {} FROM subtasks WHERE task_id = ? ORDER BY position ASC, created_at ASC",
+        SUBTASK_COLUMNS
+    ))
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Get a task's subtasks as a tree
+#[tauri::command]
+pub async fn get_subtask_tree(task_id: i64) -> Result<Vec<SubtaskNode>, String> {
+    let subtasks = load_task_subtasks(task_id).await?;
+    Ok(build_tree(&subtasks))
+}
+
+/// Get a subtask together with everything nested under it
+#[tauri::command]
+pub async fn get_subtask_subtree(subtask_id: i64) -> Result<SubtaskNode, String> {
+    let pool = get_db_pool()?;
+
+    let task_id: i64 = sqlx::query_scalar("SELECT task_id FROM subtasks WHERE id = ?")
+        .bind(subtask_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Subtask {} not found", subtask_id))?;
+
+    let subtasks = load_task_subtasks(task_id).await?;
+    let subtask = subtasks
+        .iter()
+        .find(|s| s.id == subtask_id)
+        .cloned()
+        .ok_or_else(|| format!("Subtask {} not found", subtask_id))?;
+
+    Ok(SubtaskNode {
+        children: children_of(&subtasks, subtask_id),
+        subtask,
+    })
+}
@@ -0,0 +1,395 @@
+use crate::document_revisions::RevisionAttribution;
+use crate::settings::get_db_pool;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_opener::OpenerExt;
+
+/// Largest attachment accepted, from a file or pasted
+const MAX_ATTACHMENT_BYTES: usize = 50 * 1024 * 1024;
+/// Largest text handed back when an attachment is read
+const MAX_READ_BYTES: usize = 200_000;
+/// Folder under the attachments directory holding named copies for opening
+const OPENED_DIR: &str = "opened";
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TaskAttachment {
+    pub id: i64,
+    pub task_id: i64,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub author: String, // 'user' or 'agent'
+    pub agent_id: Option<i64>,
+    pub subtask_execution_id: Option<i64>,
+    pub description: Option<String>,
+    pub created_at: String,
+}
+
+const ATTACHMENT_COLUMNS: &str = "id, task_id, file_name, mime_type, size_bytes, sha256, author, agent_id,
+     subtask_execution_id, description, created_at";
+
+/// An attachment with its content, when the content is text
+#[derive(Debug, Serialize)]
+pub struct TaskAttachmentContent {
+    pub attachment: TaskAttachment,
+    /// None for binary files
+    pub text: Option<String>,
+    pub truncated: bool,
+}
+
+fn attachments_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("attachments"))
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+/// Stored files are named by their hash and fanned out by its first byte
+fn blob_path(dir: &Path, sha256: &str) -> PathBuf {
+    dir.join(&sha256[..2]).join(sha256)
+}
+
+/// Hex SHA-256 of some bytes
+pub(crate) fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Write bytes under their hash unless the same content is already stored
+fn store_blob(dir: &Path, sha256: &str, bytes: &[u8]) -> Result<(), String> {
+    let path = blob_path(dir, sha256);
+    if path.exists() {
+        return Ok(());
+    }
+    let parent = path.parent().unwrap_or(dir);
+    std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    // Write beside the final name first so a crash never leaves a partial blob
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, bytes).map_err(|e| format!("Failed to write attachment: {}", e))?;
+    std::fs::rename(&partial, &path).map_err(|e| format!("Failed to store attachment: {}", e))
+}
+
+/// A file name safe to create on any platform, without directories
+fn sanitize_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let cleaned: String = base
+        .chars()
+        .map(|c| match c {
+            ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "attachment".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Guess a MIME type from a file name's extension
+fn guess_mime_type(file_name: &str) -> &'static str {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "md" | "markdown" => "text/markdown",
+        "txt" | "log" => "text/plain",
+        "csv" => "text/csv",
+        "tsv" => "text/tab-separated-values",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "yaml" | "yml" => "application/yaml",
+        "js" | "mjs" => "text/javascript",
+        "ts" | "tsx" | "jsx" | "py" | "rs" | "go" | "java" | "rb" | "sh" | "sql" | "toml" | "c" | "h"
+        | "cpp" | "swift" | "kt" => "text/plain",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+async fn load_attachment(attachment_id: i64) -> Result<TaskAttachment, String> {
+    let pool = get_db_pool()?;
+    sqlx::query_as::<_, TaskAttachment>(&format!(
+        "SELECT {} FROM task_attachments WHERE id = ?",
+        ATTACHMENT_COLUMNS
+    ))
+    .bind(attachment_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Attachment {} not found", attachment_id))
+}
+
+/// Store content and attach it to a task. The attribution's summary becomes
+/// the attachment's description.
+pub async fn save_attachment(
+    app: &AppHandle,
+    task_id: i64,
+    file_name: &str,
+    bytes: &[u8],
+    mime_type: Option<&str>,
+    attribution: &RevisionAttribution,
+    subtask_execution_id: Option<i64>,
+) -> Result<TaskAttachment, String> {
+    attribution.validate()?;
+    if bytes.len() > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "Attachment is {} bytes; the limit is {} bytes",
+            bytes.len(),
+            MAX_ATTACHMENT_BYTES
+        ));
+    }
+    let pool = get_db_pool()?;
+    let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM tasks WHERE id = ?")
+        .bind(task_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if exists.is_none() {
+        return Err(format!("Task {} not found", task_id));
+    }
+
+    let file_name = sanitize_file_name(file_name);
+    let mime_type = match mime_type.map(str::trim) {
+        Some(mime_type) if !mime_type.is_empty() => mime_type.to_string(),
+        _ => guess_mime_type(&file_name).to_string(),
+    };
+    let dir = attachments_dir(app)?;
+    let sha256 = content_hash(bytes);
+
+    // The row goes in before the file is stored, so a delete of another
+    // attachment sharing the file sees it and leaves the file alone
+    let attachment_id: i64 = sqlx::query_scalar(
+        "INSERT INTO task_attachments
+         (task_id, file_name, mime_type, size_bytes, sha256, author, agent_id, subtask_execution_id, description)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+         RETURNING id",
+    )
+    .bind(task_id)
+    .bind(&file_name)
+    .bind(&mime_type)
+    .bind(bytes.len() as i64)
+    .bind(&sha256)
+    .bind(&attribution.author)
+    .bind(attribution.agent_id)
+    .bind(subtask_execution_id)
+    .bind(&attribution.summary)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to save attachment: {}", e))?;
+
+    if let Err(e) = store_blob(&dir, &sha256, bytes) {
+        let _ = sqlx::query("DELETE FROM task_attachments WHERE id = ?")
+            .bind(attachment_id)
+            .execute(pool)
+            .await;
+        return Err(e);
+    }
+
+    let _ = app.emit("task-attachments-changed", serde_json::json!({ "taskId": task_id }));
+    load_attachment(attachment_id).await
+}
+
+/// Attach a copy of a file on disk to a task
+#[tauri::command]
+pub async fn add_task_attachment_file(
+    app: AppHandle,
+    task_id: i64,
+    path: String,
+    attribution: Option<RevisionAttribution>,
+) -> Result<TaskAttachment, String> {
+    let path = PathBuf::from(path.trim());
+    let metadata = std::fs::metadata(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    if metadata.len() > MAX_ATTACHMENT_BYTES as u64 {
+        return Err(format!(
+            "{} is {} bytes; the limit is {} bytes",
+            path.display(),
+            metadata.len(),
+            MAX_ATTACHMENT_BYTES
+        ));
+    }
+    let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let attribution = attribution.unwrap_or_else(RevisionAttribution::user);
+    save_attachment(&app, task_id, &file_name, &bytes, None, &attribution, None).await
+}
+
+/// Attach pasted or generated content to a task under a file name. The
+/// content is sent base64-encoded; the MIME type is guessed from the name
+/// when not given.
+#[tauri::command]
+pub async fn add_task_attachment_bytes(
+    app: AppHandle,
+    task_id: i64,
+    file_name: String,
+    content_base64: String,
+    mime_type: Option<String>,
+    attribution: Option<RevisionAttribution>,
+) -> Result<TaskAttachment, String> {
+    // Checked before decoding so an oversized upload is not held twice
+    if content_base64.len() / 4 * 3 > MAX_ATTACHMENT_BYTES + 2 {
+        return Err(format!(
+            "Attachment is larger than the {} byte limit",
+            MAX_ATTACHMENT_BYTES
+        ));
+    }
+    let content = BASE64
+        .decode(content_base64.trim())
+        .map_err(|e| format!("Attachment content is not valid base64: {}", e))?;
+
+    let attribution = attribution.unwrap_or_else(RevisionAttribution::user);
+    save_attachment(&app, task_id, &file_name, &content, mime_type.as_deref(), &attribution, None).await
+}
+
+/// A task's attachments, newest first
+#[tauri::command]
+pub async fn list_task_attachments(task_id: i64) -> Result<Vec<TaskAttachment>, String> {
+    let pool = get_db_pool()?;
+    sqlx::query_as::<_, TaskAttachment>(&format!(
+        "SELECT {} FROM task_attachments WHERE task_id = ? ORDER BY created_at DESC, id DESC",
+        ATTACHMENT_COLUMNS
+    ))
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list attachments: {}", e))
+}
+
+/// Read an attachment back. Text content is returned up to a limit; binary
+/// content is left out.
+#[tauri::command]
+pub async fn read_task_attachment(app: AppHandle, attachment_id: i64) -> Result<TaskAttachmentContent, String> {
+    let attachment = load_attachment(attachment_id).await?;
+    let path = blob_path(&attachments_dir(&app)?, &attachment.sha256);
+    let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", attachment.file_name, e))?;
+
+    let truncated = bytes.len() > MAX_READ_BYTES;
+    let mut end = bytes.len().min(MAX_READ_BYTES);
+    let text = loop {
+        match std::str::from_utf8(&bytes[..end]) {
+            Ok(text) => break Some(text.to_string()),
+            // A cut may land inside a character; anything else is binary
+            Err(e) if truncated && e.error_len().is_none() => end = e.valid_up_to(),
+            Err(_) => break None,
+        }
+    };
+    let text = text.filter(|text| !text.contains('\0'));
+    Ok(TaskAttachmentContent {
+        truncated: truncated && text.is_some(),
+        attachment,
+        text,
+    })
+}
+
+/// Open an attachment with the system's default app. The stored file has no
+/// extension, so a copy under the original name is opened.
+#[tauri::command]
+pub async fn open_task_attachment(app: AppHandle, attachment_id: i64) -> Result<(), String> {
+    let attachment = load_attachment(attachment_id).await?;
+    let dir = attachments_dir(&app)?;
+    let copy_dir = dir.join(OPENED_DIR).join(attachment.id.to_string());
+    let copy = copy_dir.join(&attachment.file_name);
+
+    let current = std::fs::metadata(&copy).is_ok_and(|m| m.len() == attachment.size_bytes as u64);
+    if !current {
+        std::fs::create_dir_all(&copy_dir)
+            .map_err(|e| format!("Failed to create {}: {}", copy_dir.display(), e))?;
+        std::fs::copy(blob_path(&dir, &attachment.sha256), &copy)
+            .map_err(|e| format!("Failed to prepare {}: {}", attachment.file_name, e))?;
+    }
+    app.opener()
+        .open_path(copy.to_string_lossy().to_string(), None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", attachment.file_name, e))
+}
+
+/// Delete an attachment, and its stored file when nothing else shares it
+#[tauri::command]
+pub async fn delete_task_attachment(app: AppHandle, attachment_id: i64) -> Result<(), String> {
+    let attachment = load_attachment(attachment_id).await?;
+    let dir = attachments_dir(&app)?;
+    let pool = get_db_pool()?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    sqlx::query("DELETE FROM task_attachments WHERE id = ?")
+        .bind(attachment_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to delete attachment: {}", e))?;
+
+    // Counted and removed while the delete holds the write lock, so an
+    // attachment of the same content saved meanwhile is either counted here
+    // or stores the file again after this commits
+    let shared: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM task_attachments WHERE sha256 = ?")
+        .bind(&attachment.sha256)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if shared == 0 {
+        let _ = std::fs::remove_file(blob_path(&dir, &attachment.sha256));
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to delete attachment: {}", e))?;
+
+    let _ = std::fs::remove_dir_all(dir.join(OPENED_DIR).join(attachment.id.to_string()));
+
+    let _ = app.emit("task-attachments-changed", serde_json::json!({ "taskId": attachment.task_id }));
+    Ok(())
+}
+
+/// Remove stored files no attachment refers to any more, such as those of
+/// deleted tasks, and the copies made for opening
+pub async fn remove_orphaned_files(app: &AppHandle) -> Result<(), String> {
+    let dir = attachments_dir(app)?;
+    if !dir.exists() {
+        return Ok(());
+    }
+    let pool = get_db_pool()?;
+    let referenced: HashSet<String> = sqlx::query_scalar("SELECT DISTINCT sha256 FROM task_attachments")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to list attachments: {}", e))?
+        .into_iter()
+        .collect();
+
+    let _ = std::fs::remove_dir_all(dir.join(OPENED_DIR));
+    let fan_out = std::fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for bucket in fan_out.flatten().filter(|entry| entry.path().is_dir()) {
+        let Ok(entries) = std::fs::read_dir(bucket.path()) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !referenced.contains(&name) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+    Ok(())
+}
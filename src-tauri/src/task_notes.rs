@@ -1,4 +1,7 @@
+use crate::document_revisions::{record_revision, RevisionAttribution, TASK_NOTES};
 use crate::settings::get_db_pool;
+use crate::text_merge::{three_way_merge, ConflictHunk, ConflictResolution, MergeGranularity};
+use serde::Serialize;
 use sqlx::Row;
 
 /// Read shared notes for a task from the database
@@ -23,10 +26,57 @@ pub async fn read_task_notes(task_id: i32) -> Result<String, String> {
     }
 }
 
-/// Write shared notes for a task to the database
+/// Write shared notes for a task to the database, recording a revision.
+/// `author` is 'user' (the default) or 'agent', with the agent's id if known.
 #[tauri::command]
-pub async fn write_task_notes(task_id: i32, content: String) -> Result<(), String> {
+pub async fn write_task_notes(
+    task_id: i32,
+    content: String,
+    author: Option<String>,
+    agent_id: Option<i64>,
+) -> Result<(), String> {
+    let attribution = RevisionAttribution::from_args(author, agent_id)?;
+    save_task_notes(task_id, &content, &attribution, None).await
+}
+
+/// Save a task's notes and record the change in its revision history
+pub async fn save_task_notes(
+    task_id: i32,
+    content: &str,
+    attribution: &RevisionAttribution,
+    restored_from: Option<i64>,
+) -> Result<(), String> {
+    replace_task_notes(task_id, None, content, attribution, restored_from).await
+}
+
+/// Save a task's notes like `save_task_notes`, failing without a change if
+/// `expected` is given and the stored notes no longer match it
+pub(crate) async fn replace_task_notes(
+    task_id: i32,
+    expected: Option<&str>,
+    content: &str,
+    attribution: &RevisionAttribution,
+    restored_from: Option<i64>,
+) -> Result<(), String> {
     let pool = get_db_pool()?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let previous: Option<Option<String>> =
+        sqlx::query_scalar("SELECT content FROM task_notes WHERE task_id = ?")
+            .bind(task_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    let previous = previous.flatten().unwrap_or_default();
+    if expected.is_some_and(|expected| expected != previous) {
+        return Err(format!(
+            "Notes of task {} changed while they were being edited; nothing was saved",
+            task_id
+        ));
+    }
 
     sqlx::query(
         "INSERT INTO task_notes (task_id, content, created_at, updated_at)
@@ -36,10 +86,96 @@ pub async fn write_task_notes(task_id: i32, content: String) -> Result<(), Strin
          updated_at = CURRENT_TIMESTAMP",
     )
     .bind(task_id)
-    .bind(&content)
-    .execute(pool)
+    .bind(content)
+    .execute(&mut *tx)
     .await
     .map_err(|e| format!("Failed to save task notes: {}", e))?;
 
-    Ok(())
+    record_revision(
+        &mut tx,
+        TASK_NOTES,
+        task_id as i64,
+        &previous,
+        content,
+        attribution,
+        restored_from,
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit task notes: {}", e))
+}
+
+/// Result of merging a user's edit of task notes with the agent's version
+#[derive(Debug, Serialize)]
+pub struct NotesMergeResult {
+    /// Merged notes, with conflict markers for any unresolved conflict
+    pub content: String,
+    pub conflicts: Vec<ConflictHunk>,
+    pub applied_changes: usize,
+    /// Whether the merged notes were saved (only when no conflicts remain)
+    pub written: bool,
+}
+
+/// Three-way merge the user's edit of a task's notes with the agent's current
+/// notes. The base is `base_content` when given, otherwise the content saved
+/// when the agent took its edit lock, which is gone once the lock is released;
+/// callers merging after the agent is done pass the base they captured at lock
+/// time. Clean merges are saved; conflicts are
+/// returned for the user to resolve and send back in `resolutions`. Fails
+/// without saving if the notes change while merging, so the merge can be redone.
+#[tauri::command]
+pub async fn merge_task_notes(
+    task_id: i32,
+    user_content: String,
+    base_content: Option<String>,
+    granularity: Option<MergeGranularity>,
+    resolutions: Option<Vec<ConflictResolution>>,
+) -> Result<NotesMergeResult, String> {
+    let base = match base_content {
+        Some(base) => base,
+        None => {
+            let pool = get_db_pool()?;
+            let original: Option<Option<String>> =
+                sqlx::query_scalar("SELECT original_content FROM agent_edit_locks WHERE task_id = ?")
+                    .bind(task_id)
+                    .fetch_optional(pool)
+                    .await
+                    .map_err(|e| format!("Database error: {}", e))?;
+            original.flatten().ok_or_else(|| {
+                format!("No base version found for task {}; pass base_content to merge", task_id)
+            })?
+        }
+    };
+    let agent_content = read_task_notes(task_id).await?;
+
+    let outcome = three_way_merge(
+        &base,
+        &user_content,
+        &agent_content,
+        granularity.unwrap_or_default(),
+        ("user", "agent"),
+        &resolutions.unwrap_or_default(),
+    )?;
+
+    let written = outcome.conflicts.is_empty();
+    if written && outcome.merged != agent_content {
+        // The agent may have written again since its notes were read
+        replace_task_notes(
+            task_id,
+            Some(&agent_content),
+            &outcome.merged,
+            &RevisionAttribution::user(),
+            None,
+        )
+        .await?;
+    }
+
+    Ok(NotesMergeResult {
+        content: outcome.merged,
+        conflicts: outcome.conflicts,
+        applied_changes: outcome.applied_changes,
+        written,
+    })
 }
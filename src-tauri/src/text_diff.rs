@@ -0,0 +1,386 @@
+use serde::Serialize;
+
+/// One step of an edit script turning `a` into `b` (indices into each side)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffOp {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// A contiguous change: `a[a_start..a_end]` is replaced by `b[b_start..b_end]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hunk {
+    pub a_start: usize,
+    pub a_end: usize,
+    pub b_start: usize,
+    pub b_end: usize,
+}
+
+/// Split text into lines, keeping line endings so the pieces join back exactly
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Whether a line opens or closes a fenced code block
+pub fn is_fence(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+/// Split Markdown into blocks: headings, paragraphs/lists separated by blank
+/// lines, and whole fenced code blocks. Trailing blank lines stay with the
+/// block before them, so the pieces join back exactly.
+pub fn split_blocks(text: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let (mut start, mut offset) = (0, 0);
+    let mut in_fence = false;
+    let mut has_content = false;
+    let mut after_blank = false;
+    let mut is_heading = false;
+
+    for line in text.split_inclusive('\n') {
+        if in_fence {
+            in_fence = !is_fence(line);
+        } else if line.trim().is_empty() {
+            after_blank = has_content;
+        } else {
+            let heading = line.trim_start().starts_with('#');
+            if has_content && (after_blank || heading || is_heading) {
+                blocks.push(&text[start..offset]);
+                start = offset;
+            }
+            in_fence = is_fence(line);
+            has_content = true;
+            after_blank = false;
+            is_heading = heading;
+        }
+        offset += line.len();
+    }
+    if start < text.len() {
+        blocks.push(&text[start..]);
+    }
+    blocks
+}
+
+/// Shortest edit script from `a` to `b` (Myers' algorithm)
+pub fn diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<DiffOp> {
+    // Common prefix and suffix never need the search
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops: Vec<DiffOp> = (0..prefix).map(|i| DiffOp::Equal(i, i)).collect();
+    ops.extend(myers(a_mid, b_mid).into_iter().map(|op| match op {
+        DiffOp::Equal(i, j) => DiffOp::Equal(i + prefix, j + prefix),
+        DiffOp::Delete(i) => DiffOp::Delete(i + prefix),
+        DiffOp::Insert(j) => DiffOp::Insert(j + prefix),
+    }));
+    ops.extend(
+        (0..suffix).map(|i| DiffOp::Equal(a.len() - suffix + i, b.len() - suffix + i)),
+    );
+    ops
+}
+
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Vec<DiffOp> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    if max == 0 {
+        return Vec::new();
+    }
+    let index = |k: isize| (k + max + 1) as usize;
+
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    // Round d only reads diagonals -d-1..=d+1 of the round before, so that is
+    // all that is kept of each, rather than the whole of `v`
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        trace.push(v[index(-d - 1)..=index(d + 1)].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+
+    // Walk the trace backwards to recover the path
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            ops.push(DiffOp::Equal((x - 1) as usize, (y - 1) as usize));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(DiffOp::Insert((y - 1) as usize));
+            } else {
+                ops.push(DiffOp::Delete((x - 1) as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    ops
+}
+
+/// Group an edit script into contiguous changes
+pub fn hunks(ops: &[DiffOp]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let (mut a_pos, mut b_pos) = (0, 0);
+    let mut current: Option<Hunk> = None;
+
+    for op in ops {
+        match *op {
+            DiffOp::Equal(i, j) => {
+                hunks.extend(current.take());
+                a_pos = i + 1;
+                b_pos = j + 1;
+            }
+            DiffOp::Delete(i) => {
+                let hunk = current.get_or_insert(Hunk { a_start: i, a_end: i, b_start: b_pos, b_end: b_pos });
+                hunk.a_end = i + 1;
+                a_pos = i + 1;
+            }
+            DiffOp::Insert(j) => {
+                let hunk = current.get_or_insert(Hunk { a_start: a_pos, a_end: a_pos, b_start: j, b_end: j });
+                hunk.b_end = j + 1;
+                b_pos = j + 1;
+            }
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+/// Line counts changed between two texts
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DiffStats {
+    pub added: usize,
+    pub removed: usize,
+}
+
+fn push_line(out: &mut String, prefix: char, line: &str) {
+    out.push(prefix);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+// "start,count" with 1-based start; an empty range names the line before it
+fn range_header(start: usize, end: usize) -> String {
+    if start == end {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, end - start)
+    }
+}
+
+/// Unified diff of two texts by line, with `context` unchanged lines around
+/// each change. Empty when the texts are equal.
+pub fn unified_diff(a: &str, b: &str, context: usize) -> (String, DiffStats) {
+    let (a_lines, b_lines) = (split_lines(a), split_lines(b));
+    let changes = hunks(&diff(&a_lines, &b_lines));
+
+    let mut stats = DiffStats::default();
+    let mut out = String::new();
+    let mut index = 0;
+    while index < changes.len() {
+        // Merge changes whose context would overlap into one block
+        let first = index;
+        while index + 1 < changes.len()
+            && changes[index + 1].a_start - changes[index].a_end <= 2 * context
+        {
+            index += 1;
+        }
+        let last = index;
+        index += 1;
+
+        let a_start = changes[first].a_start.saturating_sub(context);
+        let a_end = (changes[last].a_end + context).min(a_lines.len());
+        let b_start = changes[first].b_start - (changes[first].a_start - a_start);
+        let b_end = changes[last].b_end + (a_end - changes[last].a_end);
+
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range_header(a_start, a_end),
+            range_header(b_start, b_end)
+        ));
+        let mut pos = a_start;
+        for hunk in &changes[first..=last] {
+            for line in &a_lines[pos..hunk.a_start] {
+                push_line(&mut out, ' ', line);
+            }
+            for line in &a_lines[hunk.a_start..hunk.a_end] {
+                push_line(&mut out, '-', line);
+            }
+            for line in &b_lines[hunk.b_start..hunk.b_end] {
+                push_line(&mut out, '+', line);
+            }
+            stats.removed += hunk.a_end - hunk.a_start;
+            stats.added += hunk.b_end - hunk.b_start;
+            pos = hunk.a_end;
+        }
+        for line in &a_lines[pos..a_end] {
+            push_line(&mut out, ' ', line);
+        }
+    }
+    (out, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rebuild `b` from `a` and an edit script, checking the indices line up
+    fn apply(a: &[char], b: &[char], ops: &[DiffOp]) -> Vec<char> {
+        let (mut i, mut j) = (0, 0);
+        let mut out = Vec::new();
+        for op in ops {
+            match *op {
+                DiffOp::Equal(x, y) => {
+                    assert_eq!((x, y), (i, j));
+                    assert_eq!(a[x], b[y]);
+                    out.push(a[x]);
+                    i += 1;
+                    j += 1;
+                }
+                DiffOp::Delete(x) => {
+                    assert_eq!(x, i);
+                    i += 1;
+                }
+                DiffOp::Insert(y) => {
+                    assert_eq!(y, j);
+                    out.push(b[y]);
+                    j += 1;
+                }
+            }
+        }
+        assert_eq!((i, j), (a.len(), b.len()));
+        out
+    }
+
+    fn lcs_len(a: &[char], b: &[char]) -> usize {
+        let mut row = vec![0; b.len() + 1];
+        for x in a {
+            let mut diagonal = 0;
+            for (j, y) in b.iter().enumerate() {
+                let above = row[j + 1];
+                row[j + 1] = if x == y { diagonal + 1 } else { above.max(row[j]) };
+                diagonal = above;
+            }
+        }
+        row[b.len()]
+    }
+
+    fn edits(ops: &[DiffOp]) -> usize {
+        ops.iter().filter(|op| !matches!(op, DiffOp::Equal(..))).count()
+    }
+
+    #[test]
+    fn diff_is_a_shortest_edit_script() {
+        let cases = [
+            ("", ""),
+            ("abc", ""),
+            ("", "abc"),
+            ("abcabba", "cbabac"),
+            ("abc", "xyz"),
+            ("kitten", "sitting"),
+            ("aaaa", "aa"),
+        ];
+        for (a, b) in cases {
+            let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+            let ops = diff(&a, &b);
+            assert_eq!(apply(&a, &b, &ops), b);
+            assert_eq!(edits(&ops), a.len() + b.len() - 2 * lcs_len(&a, &b));
+        }
+    }
+
+    // Deterministic pseudo-random text over a three-letter alphabet
+    fn generated_text(seed: &mut u32) -> Vec<char> {
+        let mut next = |limit: u32| {
+            *seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (*seed >> 16) % limit
+        };
+        let len = next(12);
+        (0..len).map(|_| (b'a' + next(3) as u8) as char).collect()
+    }
+
+    #[test]
+    fn diff_stays_minimal_on_generated_inputs() {
+        // Short texts over few letters, so the search often reaches its outermost diagonals
+        let mut seed = 7;
+        for _ in 0..500 {
+            let (a, b) = (generated_text(&mut seed), generated_text(&mut seed));
+            let ops = diff(&a, &b);
+            assert_eq!(apply(&a, &b, &ops), b);
+            assert_eq!(edits(&ops), a.len() + b.len() - 2 * lcs_len(&a, &b), "{:?} -> {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn unified_diff_shows_changes_with_context() {
+        let a = "one\ntwo\nthree\nfour\nfive\nsix\nseven\n";
+        let b = "one\ntwo\n3\nfour\nfive\nsix\nseven\neight";
+        let (text, stats) = unified_diff(a, b, 1);
+        assert_eq!(
+            text,
+            "@@ -2,3 +2,3 @@\n two\n-three\n+3\n four\n\
+             @@ -7,1 +7,2 @@\n seven\n+eight\n\\ No newline at end of file\n"
+        );
+        assert_eq!((stats.added, stats.removed), (2, 1));
+
+        assert_eq!(unified_diff(a, a, 3).0, "");
+    }
+
+    #[test]
+    fn split_blocks_keeps_fences_and_headings_whole() {
+        let text = "# Title\nIntro line\nstill intro\n\n- item\n- item\n\n```\n# not a heading\n\ncode\n```\n## Next\n";
+        let blocks = split_blocks(text);
+        assert_eq!(
+            blocks,
+            vec![
+                "# Title\n",
+                "Intro line\nstill intro\n\n",
+                "- item\n- item\n\n",
+                "```\n# not a heading\n\ncode\n```\n",
+                "## Next\n",
+            ]
+        );
+        assert_eq!(blocks.concat(), text);
+        assert!(split_blocks("").is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+use crate::text_diff::{diff, hunks, split_blocks, split_lines, Hunk};
+use serde::{Deserialize, Serialize};
+
+/// Unit the documents are compared in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeGranularity {
+    #[default]
+    Line,
+    /// Markdown blocks (headings, paragraphs, lists, code blocks)
+    Block,
+}
+
+/// A place where both sides changed the same part of the base differently
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConflictHunk {
+    pub id: usize,
+    /// 1-based line in the base where the conflicting region starts
+    pub base_line: usize,
+    pub base: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+/// Result of a three-way merge
+#[derive(Debug, Serialize, Clone)]
+pub struct MergeOutcome {
+    /// Merged text, with conflict markers wherever a conflict remains
+    pub merged: String,
+    pub conflicts: Vec<ConflictHunk>,
+    /// Number of changes from either side that applied without conflict
+    pub applied_changes: usize,
+}
+
+/// How to settle one conflict
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConflictResolution {
+    pub id: usize,
+    pub choice: String, // 'ours', 'theirs', 'both', 'base', 'custom'
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+// One region of the merged output
+enum Region {
+    Resolved(String),
+    Conflict(ConflictHunk),
+}
+
+struct SideHunk {
+    hunk: Hunk,
+    ours: bool,
+}
+
+fn split(text: &str, granularity: MergeGranularity) -> Vec<&str> {
+    match granularity {
+        MergeGranularity::Line => split_lines(text),
+        MergeGranularity::Block => split_blocks(text),
+    }
+}
+
+/// Apply one side's hunks within `base[start..end]`
+fn side_text(base: &[&str], side: &[&str], hunks: &[&Hunk], start: usize, end: usize) -> String {
+    let mut text = String::new();
+    let mut pos = start;
+    for hunk in hunks {
+        text.push_str(&base[pos..hunk.a_start].concat());
+        text.push_str(&side[hunk.b_start..hunk.b_end].concat());
+        pos = hunk.a_end;
+    }
+    text.push_str(&base[pos..end].concat());
+    text
+}
+
+// Whether two changes to the base touch the same region; two insertions at the
+// same point, or an insertion at the start of a replaced range, also conflict
+fn overlaps(a: &Hunk, b: &Hunk) -> bool {
+    (a.a_start < b.a_end && b.a_start < a.a_end)
+        || (a.a_start == b.a_start && (a.a_start == a.a_end || b.a_start == b.a_end))
+}
+
+fn merge_regions(base: &str, ours: &str, theirs: &str, granularity: MergeGranularity) -> (Vec<Region>, usize) {
+    let base_units = split(base, granularity);
+    let our_units = split(ours, granularity);
+    let their_units = split(theirs, granularity);
+
+    let mut changes: Vec<SideHunk> = hunks(&diff(&base_units, &our_units))
+        .into_iter()
+        .map(|hunk| SideHunk { hunk, ours: true })
+        .chain(
+            hunks(&diff(&base_units, &their_units))
+                .into_iter()
+                .map(|hunk| SideHunk { hunk, ours: false }),
+        )
+        .collect();
+    changes.sort_by_key(|c| (c.hunk.a_start, c.hunk.a_end));
+
+    let mut regions = Vec::new();
+    let mut applied = 0;
+    let mut pos = 0;
+    let mut index = 0;
+
+    while index < changes.len() {
+        // Gather every change that overlaps this one, directly or through another
+        let mut cluster = vec![&changes[index]];
+        let (start, mut end) = (changes[index].hunk.a_start, changes[index].hunk.a_end);
+        index += 1;
+        while index < changes.len() && cluster.iter().any(|c| overlaps(&c.hunk, &changes[index].hunk)) {
+            end = end.max(changes[index].hunk.a_end);
+            cluster.push(&changes[index]);
+            index += 1;
+        }
+
+        regions.push(Region::Resolved(base_units[pos..start].concat()));
+
+        let our_hunks: Vec<&Hunk> = cluster.iter().filter(|c| c.ours).map(|c| &c.hunk).collect();
+        let their_hunks: Vec<&Hunk> = cluster.iter().filter(|c| !c.ours).map(|c| &c.hunk).collect();
+        let our_text = side_text(&base_units, &our_units, &our_hunks, start, end);
+        let their_text = side_text(&base_units, &their_units, &their_hunks, start, end);
+
+        if their_hunks.is_empty() {
+            regions.push(Region::Resolved(our_text));
+            applied += our_hunks.len();
+        } else if our_hunks.is_empty() {
+            regions.push(Region::Resolved(their_text));
+            applied += their_hunks.len();
+        } else if our_text == their_text {
+            // Both sides made the same change
+            regions.push(Region::Resolved(our_text));
+            applied += 1;
+        } else {
+            let base_line = base_units[..start].concat().matches('\n').count() + 1;
+            regions.push(Region::Conflict(ConflictHunk {
+                id: 0,
+                base_line,
+                base: base_units[start..end].concat(),
+                ours: our_text,
+                theirs: their_text,
+            }));
+        }
+        pos = end;
+    }
+    regions.push(Region::Resolved(base_units[pos..].concat()));
+
+    // Number conflicts in document order
+    let mut next_id = 1;
+    for region in &mut regions {
+        if let Region::Conflict(conflict) = region {
+            conflict.id = next_id;
+            next_id += 1;
+        }
+    }
+    (regions, applied)
+}
+
+fn with_newline(text: &str) -> String {
+    if text.is_empty() || text.ends_with('\n') {
+        text.to_string()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+fn conflict_markers(conflict: &ConflictHunk, labels: (&str, &str)) -> String {
+    format!(
+        "<<<<<<< {}\n{}=======\n{}>>>>>>> {}\n",
+        labels.0,
+        with_newline(&conflict.ours),
+        with_newline(&conflict.theirs),
+        labels.1
+    )
+}
+
+/// Merge two edited versions of `base`. Changes that touch different parts of
+/// the base are combined; overlapping changes that differ become conflicts
+/// unless `resolutions` settles them, and are marked in the merged text with
+/// `labels` for each side.
+pub fn three_way_merge(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    granularity: MergeGranularity,
+    labels: (&str, &str),
+    resolutions: &[ConflictResolution],
+) -> Result<MergeOutcome, String> {
+    let (regions, applied_changes) = merge_regions(base, ours, theirs, granularity);
+
+    let mut merged = String::new();
+    let mut conflicts = Vec::new();
+    for region in regions {
+        match region {
+            Region::Resolved(text) => merged.push_str(&text),
+            Region::Conflict(conflict) => {
+                let Some(resolution) = resolutions.iter().find(|r| r.id == conflict.id) else {
+                    merged.push_str(&conflict_markers(&conflict, labels));
+                    conflicts.push(conflict);
+                    continue;
+                };
+                let text = match resolution.choice.as_str() {
+                    "ours" => conflict.ours.clone(),
+                    "theirs" => conflict.theirs.clone(),
+                    "both" => format!("{}{}", with_newline(&conflict.ours), conflict.theirs),
+                    "base" => conflict.base.clone(),
+                    "custom" => resolution
+                        .content
+                        .clone()
+                        .ok_or_else(|| format!("Resolution for conflict {} has no content", conflict.id))?,
+                    other => return Err(format!("Unknown conflict resolution '{}'", other)),
+                };
+                merged.push_str(&text);
+            }
+        }
+    }
+
+    Ok(MergeOutcome {
+        merged,
+        conflicts,
+        applied_changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "# Plan\nalpha\nbeta\ngamma\n";
+
+    fn merge(ours: &str, theirs: &str, resolutions: &[ConflictResolution]) -> MergeOutcome {
+        three_way_merge(BASE, ours, theirs, MergeGranularity::Line, ("user", "agent"), resolutions).unwrap()
+    }
+
+    fn merge_text(base: &str, ours: &str, theirs: &str, granularity: MergeGranularity) -> MergeOutcome {
+        three_way_merge(base, ours, theirs, granularity, ("user", "agent"), &[]).unwrap()
+    }
+
+    fn resolve(choice: &str, content: Option<&str>) -> Vec<ConflictResolution> {
+        vec![ConflictResolution {
+            id: 1,
+            choice: choice.to_string(),
+            content: content.map(str::to_string),
+        }]
+    }
+
+    #[test]
+    fn combines_changes_to_different_lines() {
+        let outcome = merge(
+            "# Plan\nALPHA\nbeta\ngamma\n",
+            "# Plan\nalpha\nbeta\ngamma\ndelta\n",
+            &[],
+        );
+        assert_eq!(outcome.merged, "# Plan\nALPHA\nbeta\ngamma\ndelta\n");
+        assert!(outcome.conflicts.is_empty());
+        assert_eq!(outcome.applied_changes, 2);
+    }
+
+    #[test]
+    fn identical_changes_do_not_conflict() {
+        let edited = "# Plan\nalpha\nBETA\ngamma\n";
+        let outcome = merge(edited, edited, &[]);
+        assert_eq!(outcome.merged, edited);
+        assert!(outcome.conflicts.is_empty());
+    }
+
+    #[test]
+    fn marks_differing_changes_to_the_same_line() {
+        let outcome = merge("# Plan\nalpha\nmine\ngamma\n", "# Plan\nalpha\ntheirs\ngamma\n", &[]);
+        assert_eq!(
+            outcome.merged,
+            "# Plan\nalpha\n<<<<<<< user\nmine\n=======\ntheirs\n>>>>>>> agent\ngamma\n"
+        );
+        assert_eq!(outcome.conflicts.len(), 1);
+        let conflict = &outcome.conflicts[0];
+        assert_eq!((conflict.id, conflict.base_line), (1, 3));
+        assert_eq!(conflict.base, "beta\n");
+        assert_eq!((conflict.ours.as_str(), conflict.theirs.as_str()), ("mine\n", "theirs\n"));
+    }
+
+    #[test]
+    fn applies_resolutions_by_line() {
+        let (ours, theirs) = ("# Plan\nalpha\nmine\ngamma\n", "# Plan\nalpha\ntheirs\ngamma\n");
+        let merged = |choice: &str, content: Option<&str>| {
+            let outcome = merge(ours, theirs, &resolve(choice, content));
+            assert!(outcome.conflicts.is_empty());
+            outcome.merged
+        };
+        assert_eq!(merged("ours", None), ours);
+        assert_eq!(merged("theirs", None), theirs);
+        assert_eq!(merged("both", None), "# Plan\nalpha\nmine\ntheirs\ngamma\n");
+        assert_eq!(merged("base", None), BASE);
+        assert_eq!(merged("custom", Some("both of us\n")), "# Plan\nalpha\nboth of us\ngamma\n");
+
+        let labels = ("user", "agent");
+        let run = |choice: &str| {
+            three_way_merge(BASE, ours, theirs, MergeGranularity::Line, labels, &resolve(choice, None))
+        };
+        assert!(run("custom").is_err());
+        assert!(run("newest").is_err());
+    }
+
+    #[test]
+    fn block_granularity_treats_a_paragraph_as_one_unit() {
+        let base = "# Plan\n\nfirst line\nsecond line\n\n## Notes\n";
+        let ours = "# Plan\n\nFIRST line\nsecond line\n\n## Notes\n";
+        let theirs = "# Plan\n\nfirst line\nSECOND line\n\n## Notes\nmore\n";
+
+        let by_line = merge_text(base, ours, theirs, MergeGranularity::Line);
+        assert_eq!(by_line.merged, "# Plan\n\nFIRST line\nSECOND line\n\n## Notes\nmore\n");
+
+        let by_block = merge_text(base, ours, theirs, MergeGranularity::Block);
+        assert_eq!(by_block.conflicts.len(), 1);
+        assert_eq!(by_block.conflicts[0].base, "first line\nsecond line\n\n");
+        assert!(by_block.merged.ends_with(">>>>>>> agent\n## Notes\nmore\n"));
+
+        let resolved = three_way_merge(
+            base,
+            ours,
+            theirs,
+            MergeGranularity::Block,
+            ("user", "agent"),
+            &resolve("theirs", None),
+        )
+        .unwrap();
+        assert_eq!(resolved.merged, "# Plan\n\nfirst line\nSECOND line\n\n## Notes\nmore\n");
+    }
+}
@@ -7,6 +7,7 @@ import type {
   NewTask,
   SubTask,
   NewSubTask,
+  SubtaskNode,
   TaskWithSubTasks,
   Agent,
   ModelInfo,
@@ -122,9 +123,16 @@ export async function getSpaceContext(spaceId: number): Promise<string> {
 export async function updateSpaceContext(
   spaceId: number,
   content: string,
+  ownerId?: string,
+  attribution?: RevisionAttribution,
 ): Promise<void> {
   try {
-    await invoke("write_space_context", { spaceId, content });
+    await invoke("write_space_context", {
+      spaceId,
+      content,
+      ownerId: ownerId ?? null,
+      attribution: attribution ?? null,
+    });
   } catch (error) {
     console.error("Failed to update space context:", error);
     throw error;
@@ -146,7 +154,7 @@ export async function getTaskById(
 
   const task = tasks[0];
   const subtasks = await database.select<SubTask[]>(
-    "SELECT * FROM subtasks WHERE task_id = $1 ORDER BY created_at ASC",
+    "SELECT * FROM subtasks WHERE task_id = $1 ORDER BY position ASC, created_at ASC",
     [taskId],
   );
 
@@ -167,7 +175,7 @@ export async function getTasksBySpace(
 
   for (const task of tasks) {
     const subtasks = await database.select<SubTask[]>(
-      "SELECT * FROM subtasks WHERE task_id = $1 ORDER BY created_at ASC",
+      "SELECT * FROM subtasks WHERE task_id = $1 ORDER BY position ASC, created_at ASC",
       [task.id],
     );
 
@@ -240,6 +248,11 @@ export async function updateTask(
     values.push(updates.due_date || null);
   }
 
+  if (updates.task_type !== undefined) {
+    fields.push("task_type = $" + (fields.length + 1));
+    values.push(updates.task_type || null);
+  }
+
   if (fields.length === 0) {
     throw new Error("No fields to update");
   }
@@ -280,12 +293,14 @@ export async function createSubTask(subtask: NewSubTask): Promise<SubTask> {
   const database = await getDb();
 
   const result = await database.execute(
-    "INSERT INTO subtasks (task_id, title, description, agent_id) VALUES ($1, $2, $3, $4)",
+    `INSERT INTO subtasks (task_id, title, description, agent_id, parent_subtask_id, position)
+     VALUES ($1, $2, $3, $4, $5, (SELECT COALESCE(MAX(position), 0) + 1 FROM subtasks WHERE task_id = $1))`,
     [
       subtask.task_id,
       subtask.title,
       subtask.description || null,
       subtask.agent_id || null,
+      subtask.parent_subtask_id || null,
     ],
   );
 
@@ -305,7 +320,9 @@ export async function toggleSubTaskCompletion(id: number): Promise<SubTask> {
   const database = await getDb();
 
   await database.execute(
-    "UPDATE subtasks SET completed = NOT completed, updated_at = CURRENT_TIMESTAMP WHERE id = $1",
+    `UPDATE subtasks SET completed = NOT completed,
+     execution_status = CASE WHEN completed THEN 'idle' ELSE 'completed' END,
+     updated_at = CURRENT_TIMESTAMP WHERE id = $1`,
     [id],
   );
 
@@ -326,6 +343,10 @@ export async function deleteSubTask(id: number): Promise<void> {
   await database.execute("DELETE FROM subtasks WHERE id = $1", [id]);
 }
 
+export async function getSubtaskTree(taskId: number): Promise<SubtaskNode[]> {
+  return await invoke<SubtaskNode[]>("get_subtask_tree", { taskId });
+}
+
 // Agent operations
 export async function getAllAgents(): Promise<Agent[]> {
   const database = await getDb();
@@ -420,12 +441,13 @@ export async function deleteAgent(id: number): Promise<void> {
 export async function updateAgentPromptSection(
   agentId: number,
   newSection: string,
+  ownerId?: string,
 ): Promise<void> {
-  const database = await getDb();
-  await database.execute(
-    "UPDATE agents SET updatable_prompt_section = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2",
-    [newSection, agentId],
-  );
+  await invoke("update_agent_prompt_section", {
+    agentId,
+    content: newSection,
+    ownerId: ownerId ?? null,
+  });
 }
 
 // Task-Agent Session operations
@@ -520,13 +542,13 @@ export async function getSetting(key: string): Promise<string | null> {
   }
 }
 
-export async function setSetting(key: string, value: string): Promise<void> {
+export async function setSetting(
+  key: string,
+  value: string,
+  ownerId?: string,
+): Promise<void> {
   try {
-    const database = await getDb();
-    await database.execute(
-      "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES ($1, $2, CURRENT_TIMESTAMP)",
-      [key, value]
-    );
+    await invoke("set_setting", { key, value, ownerId: ownerId ?? null });
   } catch (error) {
     console.error("Failed to set setting:", error);
     throw error;
@@ -570,6 +592,52 @@ export async function cancelTaskPlanning(taskId: number): Promise<string> {
   }
 }
 
+export interface ActivePlanningRun {
+  task_id: number;
+  mode: string;
+  started_at: string;
+  planning_run_id: number | null;
+  last_progress: {
+    status: string;
+    message: string;
+    progress: number;
+    current_step: string | null;
+  } | null;
+}
+
+export async function getActivePlanningRuns(): Promise<ActivePlanningRun[]> {
+  return await invoke<ActivePlanningRun[]>("get_active_planning_runs");
+}
+
+export interface PlanningRunSummary {
+  id: number;
+  task_id: number;
+  model: string;
+  mode: string;
+  status: "running" | "succeeded" | "failed" | "cancelled" | "interrupted";
+  subtasks_created: number;
+  used_fallback: boolean;
+  input_tokens: number;
+  output_tokens: number;
+  error: string | null;
+  duration_ms: number | null;
+  started_at: string;
+  finished_at: string | null;
+}
+
+// Planning runs cut off by an app restart, to offer resume or discard on startup
+export async function getResumablePlanningRuns(): Promise<PlanningRunSummary[]> {
+  return await invoke<PlanningRunSummary[]>("list_resumable_planning_runs");
+}
+
+export async function resumePlanningRun(runId: number): Promise<string> {
+  return await invoke<string>("resume_planning_run", { runId });
+}
+
+export async function discardPlanningRun(runId: number): Promise<void> {
+  await invoke("discard_planning_run", { runId });
+}
+
 // Test connection to the configured provider
 export async function testConnection(): Promise<string> {
   return await invoke<string>("test_connection");
@@ -749,6 +817,33 @@ export async function updateBackgroundTaskRunStatus(
   );
 }
 
+// Subtask scheduling
+
+export interface ScheduleProposal {
+  subtask_id: number;
+  title: string;
+  scheduled_date: string;
+  estimated_minutes: number;
+  due_date: string | null;
+  fits_before_due: boolean;
+}
+
+export async function proposeSubtaskSchedule(
+  taskId: number,
+  calendarIds?: string[],
+): Promise<ScheduleProposal[]> {
+  return await invoke<ScheduleProposal[]>("propose_subtask_schedule", {
+    taskId,
+    calendarIds: calendarIds || null,
+  });
+}
+
+export async function setSubtaskSchedule(
+  schedule: { subtask_id: number; scheduled_date: string | null }[],
+): Promise<void> {
+  await invoke("set_subtask_schedule", { schedule });
+}
+
 // Update task scheduled date
 export async function updateTaskScheduledDate(
   taskId: number,
@@ -760,3 +855,475 @@ export async function updateTaskScheduledDate(
     [scheduledDate, taskId],
   );
 }
+
+// Task notes merging
+
+export interface NotesConflict {
+  id: number;
+  base_line: number;
+  base: string;
+  ours: string;
+  theirs: string;
+}
+
+export interface NotesConflictResolution {
+  id: number;
+  choice: "ours" | "theirs" | "both" | "base" | "custom";
+  content?: string;
+}
+
+export interface NotesMergeResult {
+  content: string;
+  conflicts: NotesConflict[];
+  applied_changes: number;
+  written: boolean;
+}
+
+// Three-way merge the user's edit with the agent's notes; "ours" is the user's side
+export async function mergeTaskNotes(
+  taskId: number,
+  userContent: string,
+  options: {
+    baseContent?: string;
+    granularity?: "line" | "block";
+    resolutions?: NotesConflictResolution[];
+  } = {},
+): Promise<NotesMergeResult> {
+  return await invoke<NotesMergeResult>("merge_task_notes", {
+    taskId,
+    userContent,
+    baseContent: options.baseContent ?? null,
+    granularity: options.granularity ?? null,
+    resolutions: options.resolutions ?? null,
+  });
+}
+
+// Resource locks (space context, settings, agent prompts)
+
+export type LockableResource = "space_context" | "setting" | "agent_prompt";
+
+export interface ResourceLock {
+  resource_type: LockableResource;
+  resource_id: string;
+  locked_by: string;
+  owner_id: string;
+  locked_at: string;
+  renewed_at: string;
+  expires_at: string;
+}
+
+export async function acquireResourceLock(
+  resourceType: LockableResource,
+  resourceId: string | number,
+  lockedBy: "agent" | "user",
+  ownerId: string,
+  ttlSeconds?: number,
+): Promise<boolean> {
+  return await invoke<boolean>("acquire_resource_lock", {
+    resourceType,
+    resourceId: String(resourceId),
+    lockedBy,
+    ownerId,
+    ttlSeconds: ttlSeconds ?? null,
+  });
+}
+
+export async function releaseResourceLock(
+  resourceType: LockableResource,
+  resourceId: string | number,
+  ownerId: string,
+): Promise<void> {
+  await invoke("release_resource_lock", {
+    resourceType,
+    resourceId: String(resourceId),
+    ownerId,
+  });
+}
+
+export async function checkResourceLock(
+  resourceType: LockableResource,
+  resourceId: string | number,
+): Promise<ResourceLock | null> {
+  return await invoke<ResourceLock | null>("check_resource_lock", {
+    resourceType,
+    resourceId: String(resourceId),
+  });
+}
+
+export type AgentWriteOutcome = "written" | "locked" | "changed";
+
+// Run `write` while holding an agent lock on the resource. Returns "locked"
+// without writing when someone else holds it. With `expected`, the write is a
+// compare-and-swap: it only happens if `read()` still returns the content the
+// agent based its change on, and "changed" is returned otherwise, so an edit
+// saved while the agent was thinking is never overwritten.
+export async function withAgentResourceLock(
+  resourceType: LockableResource,
+  resourceId: string | number,
+  write: (ownerId: string) => Promise<void>,
+  expected?: { content: string; read: () => Promise<string | null> },
+): Promise<AgentWriteOutcome> {
+  const ownerId = `agent-${resourceType}-${resourceId}-${Math.random().toString(36).substring(7)}`;
+  if (!(await acquireResourceLock(resourceType, resourceId, "agent", ownerId))) {
+    return "locked";
+  }
+  try {
+    if (expected && ((await expected.read()) ?? "") !== expected.content) {
+      return "changed";
+    }
+    await write(ownerId);
+    return "written";
+  } finally {
+    await releaseResourceLock(resourceType, resourceId, ownerId).catch((error) => {
+      console.error("Failed to release resource lock:", error);
+    });
+  }
+}
+
+// Document revision history
+
+export type RevisionedDocument = "task_notes" | "space_context";
+
+// Who made a change and why; defaults to the user
+export interface RevisionAttribution {
+  author: "user" | "agent";
+  agent_id?: number | null;
+  background_task_run_id?: number | null;
+  summary?: string | null;
+}
+
+export interface RevisionSummary {
+  id: number;
+  author: "user" | "agent" | "system";
+  agent_id: number | null;
+  background_task_run_id: number | null;
+  summary: string | null;
+  restored_from: number | null;
+  length: number;
+  created_at: string;
+}
+
+export interface DocumentRevision extends Omit<RevisionSummary, "length"> {
+  document_type: RevisionedDocument;
+  document_id: number;
+  content: string;
+}
+
+export interface RevisionDiff {
+  from_revision_id: number | null;
+  to_revision_id: number;
+  diff: string;
+  stats: { added: number; removed: number };
+}
+
+export async function listDocumentRevisions(
+  documentType: RevisionedDocument,
+  documentId: number,
+): Promise<RevisionSummary[]> {
+  return await invoke<RevisionSummary[]>("list_document_revisions", {
+    documentType,
+    documentId,
+  });
+}
+
+export async function getDocumentRevision(revisionId: number): Promise<DocumentRevision> {
+  return await invoke<DocumentRevision>("get_document_revision", { revisionId });
+}
+
+// Without fromRevisionId, shows what toRevisionId itself changed
+export async function diffDocumentRevisions(
+  toRevisionId: number,
+  fromRevisionId?: number,
+): Promise<RevisionDiff> {
+  return await invoke<RevisionDiff>("diff_document_revisions", {
+    fromRevisionId: fromRevisionId ?? null,
+    toRevisionId,
+  });
+}
+
+export async function restoreDocumentRevision(
+  revisionId: number,
+  attribution?: RevisionAttribution,
+): Promise<void> {
+  await invoke("restore_document_revision", {
+    revisionId,
+    attribution: attribution ?? null,
+  });
+}
+
+// Markdown section patches
+
+export interface MarkdownHeading {
+  level: number;
+  title: string;
+  path: string[];
+  line: number;
+}
+
+export type MarkdownPatchOperation =
+  | { op: "replace_section"; heading_path: string[]; content: string }
+  | { op: "insert_after_section"; heading_path: string[]; content: string }
+  | { op: "append_to_section"; heading_path: string[]; content: string }
+  | { op: "find_replace"; find: string; replace: string; expected_count: number };
+
+export interface MarkdownPatchResult {
+  content: string;
+  diff: string;
+  stats: { added: number; removed: number };
+  written: boolean;
+}
+
+export async function listDocumentHeadings(
+  documentType: RevisionedDocument,
+  documentId: number,
+): Promise<MarkdownHeading[]> {
+  return await invoke<MarkdownHeading[]>("list_document_headings", {
+    documentType,
+    documentId,
+  });
+}
+
+export async function patchMarkdown(
+  content: string,
+  operation: MarkdownPatchOperation,
+): Promise<MarkdownPatchResult> {
+  return await invoke<MarkdownPatchResult>("patch_markdown", { content, operation });
+}
+
+export async function patchDocument(
+  documentType: RevisionedDocument,
+  documentId: number,
+  operation: MarkdownPatchOperation,
+  options: {
+    ownerId?: string;
+    attribution?: RevisionAttribution;
+    dryRun?: boolean;
+  } = {},
+): Promise<MarkdownPatchResult> {
+  return await invoke<MarkdownPatchResult>("patch_document", {
+    documentType,
+    documentId,
+    operation,
+    ownerId: options.ownerId ?? null,
+    attribution: options.attribution ?? null,
+    dryRun: options.dryRun ?? null,
+  });
+}
+
+// Workspace search
+
+export type SearchEntityType = "task" | "subtask" | "task_notes" | "space";
+
+export interface SearchFilters {
+  space_id?: number | null;
+  status?: string | null;
+  entity_types?: SearchEntityType[] | null;
+  updated_after?: string | null; // YYYY-MM-DD
+  updated_before?: string | null; // YYYY-MM-DD
+  limit?: number | null;
+}
+
+export interface SearchResult {
+  entity_type: SearchEntityType;
+  entity_id: number;
+  task_id: number | null;
+  space_id: number | null;
+  title: string;
+  snippet: string; // matches wrapped in <mark></mark>
+  status: string | null;
+  updated_at: string | null;
+  rank: number;
+  link: string;
+}
+
+export async function searchWorkspace(
+  query: string,
+  filters?: SearchFilters,
+): Promise<SearchResult[]> {
+  return await invoke<SearchResult[]>("search", { query, filters: filters ?? null });
+}
+
+/** Preview the related notes and outputs agents get in their prompt for a task. */
+export async function retrieveRelatedKnowledge(
+  taskId: number,
+  budgetTokens?: number,
+): Promise<string> {
+  return await invoke<string>("retrieve_related_knowledge", {
+    taskId,
+    budgetTokens: budgetTokens ?? null,
+  });
+}
+
+export interface SpaceFolder {
+  space_id: number;
+  folder_path: string;
+  enabled: boolean;
+  last_synced_at: string | null;
+  last_error: string | null;
+  created_at: string;
+}
+
+export interface FolderSyncReport {
+  space_id: number;
+  exported: number;
+  imported: number;
+  created: number;
+  deleted: number;
+  merged: number;
+  conflicts: number;
+  errors: string[];
+}
+
+export interface FolderConflict {
+  id: number;
+  space_id: number;
+  task_id: number;
+  kind: "task" | "notes";
+  relative_path: string;
+  base_content: string | null;
+  /** null when the file was deleted */
+  file_content: string | null;
+  /** null when the task was deleted */
+  database_content: string | null;
+  detected_at: string;
+}
+
+/** Bind a space to a folder of Markdown task files and sync it once. */
+export async function bindSpaceFolder(spaceId: number, folderPath: string): Promise<FolderSyncReport> {
+  return await invoke<FolderSyncReport>("bind_space_folder", { spaceId, folderPath });
+}
+
+export async function unbindSpaceFolder(spaceId: number): Promise<void> {
+  await invoke("unbind_space_folder", { spaceId });
+}
+
+export async function setSpaceFolderEnabled(spaceId: number, enabled: boolean): Promise<void> {
+  await invoke("set_space_folder_enabled", { spaceId, enabled });
+}
+
+export async function getSpaceFolder(spaceId: number): Promise<SpaceFolder | null> {
+  return await invoke<SpaceFolder | null>("get_space_folder", { spaceId });
+}
+
+export async function syncSpaceFolder(spaceId: number): Promise<FolderSyncReport> {
+  return await invoke<FolderSyncReport>("sync_space_folder", { spaceId });
+}
+
+export async function listSpaceFolderConflicts(spaceId: number): Promise<FolderConflict[]> {
+  return await invoke<FolderConflict[]>("list_space_folder_conflicts", { spaceId });
+}
+
+/** Settle a sync conflict by keeping the file or the database version. Keeping a deleted task file deletes the task. */
+export async function resolveSpaceFolderConflict(
+  conflictId: number,
+  keep: "file" | "database",
+): Promise<FolderSyncReport> {
+  return await invoke<FolderSyncReport>("resolve_space_folder_conflict", { conflictId, keep });
+}
+
+export interface ChalkImportReport {
+  tasks_created: number;
+  tasks_updated: number;
+  subtasks_created: number;
+  subtasks_updated: number;
+  dependencies: number;
+  warnings: string[];
+}
+
+export interface ChalkExportReport {
+  written: string[];
+  unchanged: number;
+  /** Files left alone because they changed since the last import or export. */
+  conflicts: string[];
+  warnings: string[];
+}
+
+/** Import a repo's `.chalk/tasks/*.md` files into a space. */
+export async function importChalkTasks(spaceId: number, repoPath: string): Promise<ChalkImportReport> {
+  return await invoke<ChalkImportReport>("import_chalk_tasks", { spaceId, repoPath });
+}
+
+/** Write a space's tasks and subtasks to a repo's `.chalk/tasks` folder. */
+export async function exportChalkTasks(spaceId: number, repoPath: string): Promise<ChalkExportReport> {
+  return await invoke<ChalkExportReport>("export_chalk_tasks", { spaceId, repoPath });
+}
+
+// Task attachments
+
+export interface TaskAttachment {
+  id: number;
+  task_id: number;
+  file_name: string;
+  mime_type: string;
+  size_bytes: number;
+  sha256: string;
+  author: "user" | "agent";
+  agent_id: number | null;
+  subtask_execution_id: number | null;
+  description: string | null;
+  created_at: string;
+}
+
+export interface TaskAttachmentContent {
+  attachment: TaskAttachment;
+  /** null for binary files */
+  text: string | null;
+  truncated: boolean;
+}
+
+/** Attach a copy of a file on disk to a task. */
+export async function addTaskAttachmentFile(
+  taskId: number,
+  path: string,
+  attribution?: RevisionAttribution,
+): Promise<TaskAttachment> {
+  return await invoke<TaskAttachment>("add_task_attachment_file", {
+    taskId,
+    path,
+    attribution: attribution ?? null,
+  });
+}
+
+// Sent as base64 rather than a JSON array of numbers, which is about four times the size
+function toBase64(bytes: Uint8Array): string {
+  let binary = "";
+  const chunkSize = 0x8000;
+  for (let i = 0; i < bytes.length; i += chunkSize) {
+    binary += String.fromCharCode(...bytes.subarray(i, i + chunkSize));
+  }
+  return btoa(binary);
+}
+
+/** Attach pasted or generated content; the MIME type is guessed from the name if omitted. */
+export async function addTaskAttachmentBytes(
+  taskId: number,
+  fileName: string,
+  content: Uint8Array | string,
+  options: { mimeType?: string; attribution?: RevisionAttribution } = {},
+): Promise<TaskAttachment> {
+  const bytes = typeof content === "string" ? new TextEncoder().encode(content) : content;
+  return await invoke<TaskAttachment>("add_task_attachment_bytes", {
+    taskId,
+    fileName,
+    contentBase64: toBase64(bytes),
+    mimeType: options.mimeType ?? null,
+    attribution: options.attribution ?? null,
+  });
+}
+
+export async function listTaskAttachments(taskId: number): Promise<TaskAttachment[]> {
+  return await invoke<TaskAttachment[]>("list_task_attachments", { taskId });
+}
+
+export async function readTaskAttachment(attachmentId: number): Promise<TaskAttachmentContent> {
+  return await invoke<TaskAttachmentContent>("read_task_attachment", { attachmentId });
+}
+
+/** Open an attachment in the system's default app. */
+export async function openTaskAttachment(attachmentId: number): Promise<void> {
+  await invoke("open_task_attachment", { attachmentId });
+}
+
+export async function deleteTaskAttachment(attachmentId: number): Promise<void> {
+  await invoke("delete_task_attachment", { attachmentId });
+}
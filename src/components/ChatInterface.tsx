@@ -41,7 +41,14 @@ function ChatInterface({ agent, taskId, spaceId, onAgentChange }: ChatInterfaceP
   const [taskNotesChangedSinceLastRead, setTaskNotesChangedSinceLastRead] = useState(false);
   const lastAgentReadContentRef = useRef<string | null>(null);
 
+  // Lease on the task notes held while the agent is working
+  const lockOwnerRef = useRef<string | null>(null);
+  const lockHeartbeatRef = useRef<ReturnType<typeof setInterval> | null>(null);
+
   const MAX_DISPLAY_LENGTH = 20000;
+  const LOCK_TTL_SECONDS = 120;
+  const LOCK_RENEW_INTERVAL_MS = 30000;
+  const LOCK_WAIT_TIMEOUT_MS = 10000;
 
   const scrollToBottom = () => {
     const container = messagesContainerRef.current;
@@ -66,6 +73,15 @@ function ChatInterface({ agent, taskId, spaceId, onAgentChange }: ChatInterfaceP
     loadUserKnowledge();
   }, [agent, taskId]);
 
+  // Stop renewing the lease if the chat goes away mid-turn; it then expires on its own
+  useEffect(() => {
+    return () => {
+      if (lockHeartbeatRef.current) {
+        clearInterval(lockHeartbeatRef.current);
+      }
+    };
+  }, []);
+
   // Listen for task notes changes made by the user (emitted from TaskDetail)
   useEffect(() => {
     const unlisten = listen<{ taskId: number }>("task-notes-changed", (event) => {
@@ -160,6 +176,18 @@ function ChatInterface({ agent, taskId, spaceId, onAgentChange }: ChatInterfaceP
 
   const generateId = () => Math.random().toString(36).substring(7);
 
+  const releaseAgentLock = async () => {
+    if (lockHeartbeatRef.current) {
+      clearInterval(lockHeartbeatRef.current);
+      lockHeartbeatRef.current = null;
+    }
+    const ownerId = lockOwnerRef.current;
+    lockOwnerRef.current = null;
+    if (ownerId) {
+      await invoke("release_edit_lock", { taskId: taskId, ownerId });
+    }
+  };
+
   const sendMessage = async () => {
     if (!input.trim() || isStreaming) return;
 
@@ -178,14 +206,39 @@ function ChatInterface({ agent, taskId, spaceId, onAgentChange }: ChatInterfaceP
 
     // Acquire edit lock before agent starts working
     try {
-      const currentContent = await invoke<string>("read_task_notes", {
-        taskId: taskId,
-      });
-      const lockAcquired = await invoke<boolean>("acquire_edit_lock", {
+      const ownerId = `agent-chat-${taskId}-${generateId()}`;
+      // Wait for whoever holds the notes to finish instead of failing straight away
+      const lockAcquired = await invoke<boolean>("acquire_edit_lock_wait", {
         taskId: taskId,
         lockedBy: "agent",
-        originalContent: currentContent,
+        ownerId,
+        ttlSeconds: LOCK_TTL_SECONDS,
+        originalContent: null,
+        timeoutMs: LOCK_WAIT_TIMEOUT_MS,
       });
+      if (lockAcquired) {
+        lockOwnerRef.current = ownerId;
+        // The notes the agent starts from are only known once the previous
+        // holder is done; re-acquiring records them on the lock
+        const currentContent = await invoke<string>("read_task_notes", {
+          taskId: taskId,
+        });
+        await invoke<boolean>("acquire_edit_lock", {
+          taskId: taskId,
+          lockedBy: "agent",
+          ownerId,
+          ttlSeconds: LOCK_TTL_SECONDS,
+          originalContent: currentContent,
+        });
+        // Keep the lease alive for as long as the agent is working
+        lockHeartbeatRef.current = setInterval(() => {
+          invoke("renew_edit_lock", {
+            taskId: taskId,
+            ownerId,
+            ttlSeconds: LOCK_TTL_SECONDS,
+          }).catch((error) => console.error("Failed to renew edit lock:", error));
+        }, LOCK_RENEW_INTERVAL_MS);
+      }
       await emit("agent-edit-lock-changed", {
         taskId: taskId,
         locked: true,
@@ -281,6 +334,8 @@ function ChatInterface({ agent, taskId, spaceId, onAgentChange }: ChatInterfaceP
       const executeTool = createToolExecutor({
         taskId,
         spaceId,
+        agentId: agent.id,
+        notesOwnerId: lockOwnerRef.current ?? undefined,
         onTaskNotesRead: (content) => {
           lastAgentReadContentRef.current = content;
           setTaskNotesChangedSinceLastRead(false);
@@ -329,7 +384,7 @@ function ChatInterface({ agent, taskId, spaceId, onAgentChange }: ChatInterfaceP
         const originalContent = await invoke<string | null>("get_original_content", {
           taskId: taskId,
         });
-        await invoke("release_edit_lock", { taskId: taskId });
+        await releaseAgentLock();
         await emit("agent-edit-lock-changed", {
           taskId: taskId,
           locked: false,
@@ -349,7 +404,7 @@ function ChatInterface({ agent, taskId, spaceId, onAgentChange }: ChatInterfaceP
         const originalContent = await invoke<string | null>("get_original_content", {
           taskId: taskId,
         });
-        await invoke("release_edit_lock", { taskId: taskId });
+        await releaseAgentLock();
         await emit("agent-edit-lock-changed", {
           taskId: taskId,
           locked: false,
@@ -1,5 +1,6 @@
 import { useState, useEffect, useCallback, useRef } from "react";
-import { getTasksBySpace, getSpaceContext, updateSpaceContext, createTask, getSpaceEvents, getEventsForDate, getSetting, untagEventFromSpace, updateTaskStatus } from "../api";
+import { listen } from "@tauri-apps/api/event";
+import { getTasksBySpace, getSpaceContext, updateSpaceContext, acquireResourceLock, releaseResourceLock, createTask, getSpaceEvents, getEventsForDate, getSetting, untagEventFromSpace, updateTaskStatus, type FolderSyncReport } from "../api";
 import type { Space, TaskWithSubTasks, CalendarEvent, EventSpaceAssociation } from "../types";
 import { MDXEditor, headingsPlugin, listsPlugin, quotePlugin, thematicBreakPlugin, markdownShortcutPlugin, toolbarPlugin, BoldItalicUnderlineToggles, BlockTypeSelect, ListsToggle } from '@mdxeditor/editor';
 import '@mdxeditor/editor/style.css';
@@ -12,6 +13,11 @@ const SPACE_COLORS = [
   "#14B8A6", "#06B6D4", "#6B7280", "#1F2937",
 ];
 
+// How long the user's hold on the space context lasts after their last save
+const CONTEXT_LEASE_SECONDS = 60;
+// How soon a save held up by an agent's lease is tried again
+const CONTEXT_SAVE_RETRY_MS = 1000;
+
 interface SpaceHomeProps {
   selectedSpace: Space | null;
   onTaskClick: (taskId: number) => void;
@@ -53,6 +59,15 @@ function SpaceHome({
   const newTaskInputRef = useRef<HTMLInputElement>(null);
   // Prevents onBlur from double-firing a creation when Enter/Escape already handled it
   const newTaskSubmittingRef = useRef(false);
+  // Each save renews a short lease on the space context so background agents
+  // don't overwrite it while the user is typing
+  const contextLockOwnerRef = useRef(`user-space-context-${Math.random().toString(36).substring(7)}`);
+  // Latest unsaved context; kept until it is written, so a save that finds an
+  // agent holding the lease is retried rather than dropped
+  const pendingContextRef = useRef<{ spaceId: number; content: string } | null>(null);
+  const contextSavingRef = useRef(false);
+  const contextRetryRef = useRef<number | null>(null);
+  const [isContextSaveWaiting, setIsContextSaveWaiting] = useState(false);
 
   const sortedTasks = [
     ...tasks.filter((task) => task.status !== "done"),
@@ -67,6 +82,20 @@ function SpaceHome({
     }
   }, [selectedSpace, refreshTrigger]);
 
+  // Edits made to the space's synced folder show up without a manual refresh
+  useEffect(() => {
+    if (!selectedSpace) return;
+    const spaceId = selectedSpace.id;
+    const unlisten = listen<FolderSyncReport>("space-folder-synced", (event) => {
+      if (event.payload.space_id === spaceId) {
+        loadTasks(spaceId);
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [selectedSpace]);
+
   useEffect(() => {
     if (shouldEditSpaceTitle && selectedSpace) {
       setIsEditingTitle(true);
@@ -182,14 +211,52 @@ function SpaceHome({
     }
   }
 
-  const saveContext = useCallback(async (newContent: string) => {
-    if (selectedSpace) {
-      try {
-        await updateSpaceContext(selectedSpace.id, newContent);
-      } catch (error) {
-        console.error('Failed to save space context:', error);
+  // Write the pending context, one save at a time so they land in order
+  const flushContext = useCallback(async () => {
+    if (contextSavingRef.current) return;
+    contextSavingRef.current = true;
+    try {
+      let pending;
+      while ((pending = pendingContextRef.current)) {
+        const ownerId = contextLockOwnerRef.current;
+        const locked = await acquireResourceLock("space_context", pending.spaceId, "user", ownerId, CONTEXT_LEASE_SECONDS);
+        if (!locked) break;
+        await updateSpaceContext(pending.spaceId, pending.content, ownerId);
+        if (pendingContextRef.current === pending) {
+          pendingContextRef.current = null;
+        }
       }
+    } catch (error) {
+      console.error("Failed to save space context:", error);
+    } finally {
+      contextSavingRef.current = false;
+    }
+
+    const waiting = pendingContextRef.current !== null;
+    setIsContextSaveWaiting(waiting);
+    if (waiting && contextRetryRef.current === null) {
+      contextRetryRef.current = window.setTimeout(() => {
+        contextRetryRef.current = null;
+        flushContext();
+      }, CONTEXT_SAVE_RETRY_MS);
+    }
+  }, []);
+
+  const saveContext = useCallback((newContent: string) => {
+    if (selectedSpace) {
+      pendingContextRef.current = { spaceId: selectedSpace.id, content: newContent };
+      flushContext();
     }
+  }, [selectedSpace, flushContext]);
+
+  useEffect(() => {
+    if (!selectedSpace) return;
+    const spaceId = selectedSpace.id;
+    return () => {
+      releaseResourceLock("space_context", spaceId, contextLockOwnerRef.current).catch((error) => {
+        console.error("Failed to release space context lock:", error);
+      });
+    };
   }, [selectedSpace]);
 
   useEffect(() => {
@@ -657,6 +724,11 @@ function SpaceHome({
             <h2 style={{ fontFamily: "'IBM Plex Sans', sans-serif", fontSize: "20px", fontWeight: 600, color: "#333", lineHeight: "normal", margin: 0, flexShrink: 0 }}>
               Context
             </h2>
+            {isContextSaveWaiting && (
+              <p style={{ fontSize: "13px", fontFamily: "Inter, sans-serif", color: "#828282", margin: 0, flexShrink: 0 }}>
+                An agent is updating the context; your changes will be saved when it finishes.
+              </p>
+            )}
             {isContextLoading ? (
               <p style={{ fontSize: "16px", fontFamily: "Inter, sans-serif", lineHeight: 1.4, color: "#828282", margin: 0 }}>Loading context...</p>
             ) : (
@@ -23,12 +23,22 @@ import { invoke } from "@tauri-apps/api/core";
 import { listen, emit } from "@tauri-apps/api/event";
 import type { TaskWithSubTasks, Agent } from "../types";
 import ChatInterface from "./ChatInterface";
-import { getLastUsedAgentForTask, getAllAgents } from "../api";
+import {
+  getLastUsedAgentForTask,
+  getAllAgents,
+  mergeTaskNotes,
+  type NotesConflict,
+  type NotesConflictResolution,
+} from "../api";
 import { executeBackgroundTask } from "../utils/backgroundTasks";
 import { contextSupplementationTask } from "../utils/contextSupplementationTask";
 import { userKnowledgeTask } from "../utils/userKnowledgeTask";
 import { agentPromptSelfUpdateTask } from "../utils/agentPromptSelfUpdateTask";
 
+// Lease the view holds on the notes while the user reviews an agent's changes
+const REVIEW_LOCK_TTL_SECONDS = 120;
+const REVIEW_LOCK_RENEW_INTERVAL_MS = 30000;
+
 interface TaskDetailProps {
   task: TaskWithSubTasks;
   spaceName: string;
@@ -48,6 +58,21 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
   const [viewMode, setViewMode] = useState<ViewMode>('rich-text');
   const [editorKey, setEditorKey] = useState(0);
   const editorRef = useRef<MDXEditorMethods>(null);
+  // Owner of the leases this view takes, so it only ever releases its own
+  const sessionOwnerRef = useRef(`task-view-${task.id}-${Math.random().toString(36).substring(7)}`);
+
+  // Edits made while an agent holds the notes are kept here, unsaved, and
+  // merged into the agent's version against the notes it started from. The
+  // lease row (and its original content) is gone once the agent releases it,
+  // so the base is captured when the lock is taken.
+  const userDraftRef = useRef<string | null>(null);
+  const mergeBaseRef = useRef<string | null>(null);
+  // Set once a draft is merged, so the agent's changes are not reviewed again
+  const draftMergedRef = useRef(false);
+  const [mergeConflicts, setMergeConflicts] = useState<NotesConflict[]>([]);
+  const [conflictChoices, setConflictChoices] = useState<
+    Record<number, NotesConflictResolution["choice"]>
+  >({});
 
   // Load the last used agent (or first available) when the component mounts
   useEffect(() => {
@@ -98,6 +123,7 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
             if (original) {
               setOriginalContent(original);
             }
+            mergeBaseRef.current = original ?? content;
           }
         }
       } catch (error) {
@@ -111,6 +137,34 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
     loadDocument();
   }, [task.id]);
 
+  // Follow locks taken or expired on the backend (e.g. an agent lease that was
+  // never renewed) without polling check_edit_lock
+  useEffect(() => {
+    const unlisten = listen<{
+      task_id: number;
+      locked: boolean;
+      locked_by: string | null;
+      reason: string;
+    }>("edit-lock-changed", (event) => {
+      if (event.payload.task_id !== task.id) return;
+      if (event.payload.locked && event.payload.locked_by) {
+        setEditLock(event.payload.locked_by as 'agent' | 'user');
+        if (event.payload.locked_by === 'agent') {
+          captureMergeBase();
+        }
+      } else {
+        setEditLock(null);
+        // However the agent let go of the notes, edits made meanwhile are merged
+        if (userDraftRef.current !== null) {
+          mergeUserDraft();
+        }
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [task.id]);
+
   // Listen for lock state changes
   useEffect(() => {
     let unlisten: (() => void) | undefined;
@@ -127,23 +181,22 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
             setEditLock(event.payload.lockedBy as 'agent' | 'user');
 
             if (event.payload.lockedBy === 'agent') {
-              try {
-                const original = await invoke<string | null>("get_original_content", {
-                  taskId: task.id,
-                });
-                if (original !== null) {
-                  setOriginalContent(original);
-                }
-              } catch (error) {
-                console.error("Failed to get original content:", error);
-              }
+              await captureMergeBase();
             }
           } else {
             const originalFromEvent = event.payload.originalContent;
             if (originalFromEvent) {
               setOriginalContent(originalFromEvent);
             }
-            checkForPendingReview(originalFromEvent || undefined);
+            if (userDraftRef.current !== null) {
+              // The user edited alongside the agent; merge instead of reviewing
+              if (originalFromEvent) {
+                mergeBaseRef.current = originalFromEvent;
+              }
+              await mergeUserDraft();
+            } else if (!draftMergedRef.current) {
+              checkForPendingReview(originalFromEvent || undefined);
+            }
             setEditLock(null);
           }
         }
@@ -156,14 +209,64 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
       if (unlisten) {
         unlisten();
       }
-      if (editLock) {
-        invoke("release_edit_lock", { taskId: task.id }).catch((error) => {
-          console.error("Failed to release lock on unmount:", error);
-        });
-      }
     };
   }, [task.id, editLock]);
 
+  // Remember the notes an agent starts from, for reviewing its changes and
+  // merging edits made meanwhile
+  const captureMergeBase = async () => {
+    try {
+      const original = await invoke<string | null>("get_original_content", {
+        taskId: task.id,
+      });
+      if (original !== null) {
+        setOriginalContent(original);
+      }
+      if (userDraftRef.current === null) {
+        draftMergedRef.current = false;
+        mergeBaseRef.current =
+          original ?? (await invoke<string>("read_task_notes", { taskId: task.id }));
+      }
+    } catch (error) {
+      console.error("Failed to get original content:", error);
+    }
+  };
+
+  // Three-way merge the user's draft with the agent's notes. Clean merges are
+  // saved; conflicts are shown for the user to pick a side.
+  const mergeUserDraft = async (resolutions?: NotesConflictResolution[]) => {
+    const draft = userDraftRef.current;
+    const base = mergeBaseRef.current;
+    if (draft === null || base === null) return;
+    try {
+      const result = await mergeTaskNotes(task.id, draft, {
+        baseContent: base,
+        granularity: "block",
+        resolutions,
+      });
+      if (!result.written) {
+        setMergeConflicts(result.conflicts);
+        return;
+      }
+      userDraftRef.current = null;
+      mergeBaseRef.current = null;
+      draftMergedRef.current = true;
+      setMergeConflicts([]);
+      setConflictChoices({});
+      setDocumentContent(result.content);
+      setOriginalContent(result.content);
+      setEditorKey(prev => prev + 1);
+      await emit("task-notes-changed", { taskId: task.id });
+    } catch (error) {
+      console.error("Failed to merge notes:", error);
+    }
+  };
+
+  const applyConflictChoices = () =>
+    mergeUserDraft(
+      mergeConflicts.map((conflict) => ({ id: conflict.id, choice: conflictChoices[conflict.id] })),
+    );
+
   const checkForPendingReview = async (originalFromEvent?: string) => {
     try {
       const currentContent = await invoke<string>("read_task_notes", {
@@ -186,20 +289,9 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
         setTimeout(() => {
           editorRef.current?.setMarkdown(currentContent);
         }, 50);
-      } else {
-        try {
-          await invoke("release_edit_lock", { taskId: task.id });
-        } catch (lockError) {
-          console.error("Failed to release lock after no changes detected:", lockError);
-        }
       }
     } catch (error) {
       console.error("Failed to check for pending changes:", error);
-      try {
-        await invoke("release_edit_lock", { taskId: task.id });
-      } catch (lockError) {
-        console.error("Failed to release lock after error:", lockError);
-      }
     }
   };
 
@@ -221,7 +313,6 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
       setPendingReview(false);
       setViewMode('rich-text');
       setEditorKey(prev => prev + 1);
-      await invoke("release_edit_lock", { taskId: task.id });
     } catch (error) {
       console.error("Failed to accept changes:", error);
     }
@@ -234,12 +325,40 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
       setPendingReview(false);
       setViewMode('rich-text');
       setEditorKey(prev => prev + 1);
-      await invoke("release_edit_lock", { taskId: task.id });
     } catch (error) {
       console.error("Failed to revert changes:", error);
     }
   };
 
+  // Hold the notes while the user reviews an agent's changes, so another agent
+  // waits for the review instead of writing underneath it. Released with this
+  // view's owner id once the review ends or the view closes.
+  useEffect(() => {
+    if (!pendingReview) return;
+    const taskId = task.id;
+    const ownerId = sessionOwnerRef.current;
+
+    invoke<boolean>("acquire_edit_lock", {
+      taskId,
+      lockedBy: "user",
+      ownerId,
+      ttlSeconds: REVIEW_LOCK_TTL_SECONDS,
+      originalContent: null,
+    }).catch((error) => console.error("Failed to lock notes for review:", error));
+    const heartbeat = setInterval(() => {
+      invoke("renew_edit_lock", { taskId, ownerId, ttlSeconds: REVIEW_LOCK_TTL_SECONDS }).catch(
+        (error) => console.error("Failed to renew review lock:", error),
+      );
+    }, REVIEW_LOCK_RENEW_INTERVAL_MS);
+
+    return () => {
+      clearInterval(heartbeat);
+      invoke("release_edit_lock", { taskId, ownerId }).catch((error) =>
+        console.error("Failed to release review lock:", error),
+      );
+    };
+  }, [pendingReview, task.id]);
+
   // Trigger background context supplementation on unmount
   useEffect(() => {
     const taskId = task.id;
@@ -266,7 +385,7 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
 
   const forceUnlock = async () => {
     try {
-      await invoke("release_edit_lock", { taskId: task.id });
+      await invoke("force_release_edit_lock", { taskId: task.id });
       setEditLock(null);
       setPendingReview(false);
       setViewMode('rich-text');
@@ -301,7 +420,9 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
         <div className="document-pane">
           {editLock === 'agent' && (
             <div className="edit-lock-banner">
-              <span style={{ flex: 1 }}>Agent is editing... (Read-only mode)</span>
+              <span style={{ flex: 1 }}>
+                Agent is editing... Your changes will be merged when it finishes.
+              </span>
               <button
                 className="force-unlock-btn"
                 onClick={forceUnlock}
@@ -319,10 +440,12 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
               ref={editorRef}
               key={editorKey}
               markdown={documentContent}
-              readOnly={editLock === 'agent'}
+              readOnly={mergeConflicts.length > 0}
               onChange={(newContent: string) => {
-                if (editLock !== 'agent') {
-                  setDocumentContent(newContent);
+                setDocumentContent(newContent);
+                if (editLock === 'agent') {
+                  userDraftRef.current = newContent;
+                } else {
                   saveDocument(newContent);
                 }
               }}
@@ -356,6 +479,53 @@ function TaskDetail({ task, onBack }: TaskDetailProps) {
             />
           )}
 
+          {mergeConflicts.length > 0 && (
+            <div className="review-panel merge-panel">
+              <h3>Your changes conflict with the agent's</h3>
+              <p>Choose what to keep for each conflict</p>
+              {mergeConflicts.map((conflict) => (
+                <div key={conflict.id} className="merge-conflict">
+                  <div className="merge-conflict-sides">
+                    <div>
+                      <strong>Yours</strong>
+                      <pre>{conflict.ours || "(removed)"}</pre>
+                    </div>
+                    <div>
+                      <strong>Agent's</strong>
+                      <pre>{conflict.theirs || "(removed)"}</pre>
+                    </div>
+                  </div>
+                  <div className="review-actions">
+                    {([
+                      ["ours", "Yours"],
+                      ["theirs", "Agent's"],
+                      ["both", "Both"],
+                    ] as const).map(([choice, label]) => (
+                      <button
+                        key={choice}
+                        className={conflictChoices[conflict.id] === choice ? "btn-accept" : ""}
+                        onClick={() =>
+                          setConflictChoices((prev) => ({ ...prev, [conflict.id]: choice }))
+                        }
+                      >
+                        {label}
+                      </button>
+                    ))}
+                  </div>
+                </div>
+              ))}
+              <div className="review-actions">
+                <button
+                  className="btn-accept"
+                  disabled={mergeConflicts.some((conflict) => !conflictChoices[conflict.id])}
+                  onClick={applyConflictChoices}
+                >
+                  Apply
+                </button>
+              </div>
+            </div>
+          )}
+
           {pendingReview && (
             <div className="review-panel">
               <h3>Agent made changes</h3>
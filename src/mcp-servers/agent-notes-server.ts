@@ -263,23 +263,68 @@ class AgentNotesServer {
       throw new Error("Content is required for update_space_context");
     }
 
+    // Lock check, write and revision go in one transaction, as
+    // write_space_context does in the app
+    await this.runDatabase("BEGIN IMMEDIATE");
     try {
+      // Leave the context alone while someone holds its lock in the app
+      const locks = await this.queryDatabase(
+        `SELECT owner_id FROM resource_locks
+         WHERE resource_type = 'space_context' AND resource_id = ? AND expires_at > datetime('now')`,
+        [String(space_id)],
+      );
+      if (locks.length > 0) {
+        throw new Error(`Space ${space_id} context is being edited elsewhere; try again later`);
+      }
+
+      const spaces = await this.queryDatabase<{ context_markdown: string | null }>(
+        "SELECT context_markdown FROM spaces WHERE id = ?",
+        [space_id],
+      );
+      if (spaces.length === 0) {
+        throw new Error(`Space ${space_id} not found`);
+      }
+      const previous = spaces[0].context_markdown ?? "";
+
       await this.runDatabase(
         "UPDATE spaces SET context_markdown = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
         [content, space_id],
       );
 
-      return {
-        content: [
-          {
-            type: "text",
-            text: `Successfully updated space context for space ${space_id}${summary ? `: ${summary}` : ""}`,
-          },
-        ],
-      };
+      if (previous !== content) {
+        // The first revision of a document keeps what it replaced, as record_revision does
+        const history = await this.queryDatabase(
+          "SELECT 1 FROM document_revisions WHERE document_type = 'space_context' AND document_id = ? LIMIT 1",
+          [space_id],
+        );
+        if (history.length === 0 && previous !== "") {
+          await this.runDatabase(
+            `INSERT INTO document_revisions (document_type, document_id, content, author)
+             VALUES ('space_context', ?, ?, 'system')`,
+            [space_id, previous],
+          );
+        }
+        await this.runDatabase(
+          `INSERT INTO document_revisions (document_type, document_id, content, author, summary)
+           VALUES ('space_context', ?, ?, 'agent', ?)`,
+          [space_id, content, summary ?? null],
+        );
+      }
+
+      await this.runDatabase("COMMIT");
     } catch (error) {
+      await this.runDatabase("ROLLBACK").catch(() => {});
       throw error;
     }
+
+    return {
+      content: [
+        {
+          type: "text",
+          text: `Successfully updated space context for space ${space_id}${summary ? `: ${summary}` : ""}`,
+        },
+      ],
+    };
   }
 
   async run() {
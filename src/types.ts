@@ -24,6 +24,7 @@ export interface Task {
   due_date?: string;
   scheduled_date?: string;
   notes_file_path?: string;
+  task_type?: string;
   created_at: string;
   updated_at: string;
 }
@@ -44,6 +45,13 @@ export interface SubTask {
   description?: string;
   completed: boolean;
   agent_id?: number;
+  parent_subtask_id?: number;
+  position: number;
+  estimated_minutes?: number;
+  due_date?: string;
+  priority?: 'low' | 'medium' | 'high';
+  scheduled_date?: string;
+  execution_status: 'idle' | 'queued' | 'running' | 'review' | 'completed' | 'failed';
   created_at: string;
   updated_at: string;
 }
@@ -53,6 +61,11 @@ export interface NewSubTask {
   title: string;
   description?: string;
   agent_id?: number;
+  parent_subtask_id?: number;
+}
+
+export interface SubtaskNode extends SubTask {
+  children: SubtaskNode[];
 }
 
 export interface TaskWithSubTasks extends Task {
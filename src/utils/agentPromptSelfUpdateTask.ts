@@ -10,6 +10,7 @@ import { invoke } from "@tauri-apps/api/core";
 import {
   getTaskById,
   updateAgentPromptSection,
+  withAgentResourceLock,
 } from "../api";
 import type { BackgroundTaskDefinition } from "./backgroundTasks";
 import type { ToolResult } from "./agentTools";
@@ -153,7 +154,14 @@ Rules:
               `Learned Preferences section exceeds the 500-word limit (currently ${wordCount} words). Please condense and retry.`,
             );
           }
-          await updateAgentPromptSection(ctx.agentId, args.content);
+          const outcome = await withAgentResourceLock("agent_prompt", ctx.agentId, (ownerId) =>
+            updateAgentPromptSection(ctx.agentId, args.content, ownerId),
+          );
+          if (outcome !== "written") {
+            return textResult(
+              "Your prompt is being edited elsewhere right now; the Learned Preferences section was not updated.",
+            );
+          }
           return textResult(
             `Successfully updated your Learned Preferences section (${wordCount} words).`,
           );
@@ -2,7 +2,7 @@
  * Agent tool definitions and executor.
  *
  * Extracted from ChatInterface.tsx so both Task-chat and Today-chat contexts
- * can share the same 9 tools. When `taskId` / `spaceId` are provided in
+ * can share the same 15 tools. When `taskId` / `spaceId` are provided in
  * the context they act as defaults; when omitted the caller must pass
  * explicit IDs (Today-chat context).
  */
@@ -18,6 +18,20 @@ import {
   getEventsForDate,
   checkAgentNotesExists,
   updateSpaceContext,
+  withAgentResourceLock,
+  listDocumentHeadings,
+  patchDocument,
+  searchWorkspace,
+  addTaskAttachmentBytes,
+  listTaskAttachments,
+  readTaskAttachment,
+} from "../api";
+import type {
+  MarkdownPatchOperation,
+  MarkdownPatchResult,
+  RevisionedDocument,
+  SearchEntityType,
+  SearchResult,
 } from "../api";
 
 export interface ToolContext {
@@ -25,6 +39,10 @@ export interface ToolContext {
   taskId?: number;
   /** Current space ID — undefined in Today context. */
   spaceId?: number;
+  /** Agent running the tools, recorded as the author of its edits. */
+  agentId?: number;
+  /** Owner of the edit lease the agent holds on the current task's notes. */
+  notesOwnerId?: string;
   /** Called when task notes are read for the current task (change-tracking). */
   onTaskNotesRead?: (content: string) => void;
   /** Called when space context is updated. */
@@ -78,6 +96,179 @@ export function getAgentToolSchemas() {
         required: ["task_id", "content"],
       },
     },
+    {
+      name: "list_document_headings",
+      description:
+        "List the headings of task notes or space context, with their heading paths. Use before patch_document to target a section without reading the whole document.",
+      input_schema: {
+        type: "object",
+        properties: {
+          document_type: {
+            type: "string",
+            enum: ["task_notes", "space_context"],
+            description: "Which document to read",
+          },
+          document_id: {
+            type: "number",
+            description:
+              "The task ID (task_notes) or space ID (space_context). Defaults to the current task or space.",
+          },
+        },
+        required: ["document_type"],
+      },
+    },
+    {
+      name: "patch_document",
+      description:
+        "Make a targeted edit to task notes or space context instead of rewriting the whole document. Sections are addressed by heading path, e.g. [\"Plan\", \"Risks\"]. Returns a diff of the change.",
+      input_schema: {
+        type: "object",
+        properties: {
+          document_type: {
+            type: "string",
+            enum: ["task_notes", "space_context"],
+            description: "Which document to edit",
+          },
+          document_id: {
+            type: "number",
+            description:
+              "The task ID (task_notes) or space ID (space_context). Defaults to the current task or space.",
+          },
+          op: {
+            type: "string",
+            enum: ["replace_section", "insert_after_section", "append_to_section", "find_replace"],
+            description:
+              "replace_section: replace everything under a heading. insert_after_section: add content (e.g. a new section) after a section. append_to_section: add to the end of a heading's own text. find_replace: replace exact text.",
+          },
+          heading_path: {
+            type: "array",
+            items: { type: "string" },
+            description: "Heading titles from outermost to the target (section operations)",
+          },
+          content: {
+            type: "string",
+            description: "Markdown to write (section operations)",
+          },
+          find: {
+            type: "string",
+            description: "Exact text to find (find_replace)",
+          },
+          replace: {
+            type: "string",
+            description: "Replacement text (find_replace)",
+          },
+          expected_count: {
+            type: "number",
+            description: "How many times find must occur; the edit fails otherwise (find_replace)",
+          },
+          summary: {
+            type: "string",
+            description: "Brief summary of the change, kept in the revision history",
+          },
+        },
+        required: ["document_type", "op"],
+      },
+    },
+    {
+      name: "search_workspace",
+      description:
+        "Full-text search across all tasks, subtasks, task notes and space context. Use to find past work, decisions or related tasks.",
+      input_schema: {
+        type: "object",
+        properties: {
+          query: {
+            type: "string",
+            description: "Words to search for",
+          },
+          space_id: {
+            type: "number",
+            description: "Only search this space",
+          },
+          status: {
+            type: "string",
+            enum: ["todo", "in_progress", "for_review", "done"],
+            description: "Only results from tasks with this status",
+          },
+          entity_types: {
+            type: "array",
+            items: { type: "string", enum: ["task", "subtask", "task_notes", "space"] },
+            description: "Only these kinds of result",
+          },
+          updated_after: {
+            type: "string",
+            description: "Only results updated on or after this date (YYYY-MM-DD)",
+          },
+          updated_before: {
+            type: "string",
+            description: "Only results updated on or before this date (YYYY-MM-DD)",
+          },
+        },
+        required: ["query"],
+      },
+    },
+    {
+      name: "save_attachment",
+      description:
+        "Save a deliverable such as a draft, CSV, code file, SVG or image to a task as a named attachment. Use this instead of task notes for standalone files. Pass text as content, or binary files such as PNGs as content_base64. Saving again under the same name adds a new attachment.",
+      input_schema: {
+        type: "object",
+        properties: {
+          task_id: {
+            type: "number",
+            description: "The ID of the task. Defaults to the current task.",
+          },
+          file_name: {
+            type: "string",
+            description: "File name with extension, e.g. 'summary.md' or 'results.csv'",
+          },
+          content: {
+            type: "string",
+            description: "The full text content of the file",
+          },
+          content_base64: {
+            type: "string",
+            description: "The file's bytes encoded as base64, for images and other binary files. Use instead of content.",
+          },
+          mime_type: {
+            type: "string",
+            description: "MIME type; guessed from the file extension if omitted",
+          },
+          description: {
+            type: "string",
+            description: "Brief description of what the file contains",
+          },
+        },
+        required: ["file_name"],
+      },
+    },
+    {
+      name: "list_attachments",
+      description: "List the files attached to a task, including outputs saved by agents.",
+      input_schema: {
+        type: "object",
+        properties: {
+          task_id: {
+            type: "number",
+            description: "The ID of the task. Defaults to the current task.",
+          },
+        },
+        required: [],
+      },
+    },
+    {
+      name: "read_attachment",
+      description: "Read the text content of a task attachment by its ID (from list_attachments).",
+      input_schema: {
+        type: "object",
+        properties: {
+          attachment_id: {
+            type: "number",
+            description: "The ID of the attachment to read",
+          },
+        },
+        required: ["attachment_id"],
+      },
+    },
     {
       name: "check_task_notes_exists",
       description: "Check if Agent_Notes.md file exists for a specific task",
@@ -95,7 +286,7 @@ export function getAgentToolSchemas() {
     {
       name: "update_space_context",
       description:
-        "Update the shared space context markdown. Use this to record architectural decisions, completed milestones, and space-wide insights that are relevant to all tasks.",
+        "Update the shared space context markdown. Use this to record architectural decisions, completed milestones, and space-wide insights that are relevant to all tasks. Call read_space_context first; the update is refused if the context changed since you read it.",
       input_schema: {
         type: "object",
         properties: {
@@ -199,6 +390,9 @@ export function getAgentToolSchemas() {
 // ── Tool executor ───────────────────────────────────────────────────────
 
 export function createToolExecutor(ctx: ToolContext) {
+  // Space context as the agent last read it; full rewrites are only written over it
+  const readSpaceContexts = new Map<number, string>();
+
   return async (toolName: string, args: any): Promise<ToolResult> => {
     try {
       switch (toolName) {
@@ -231,12 +425,164 @@ export function createToolExecutor(ctx: ToolContext) {
               finalContent = existing + "\n\n" + writeContent;
             }
           }
-          await invoke("write_task_notes", { taskId: actualTaskId, content: finalContent });
+          await invoke("write_task_notes", {
+            taskId: actualTaskId,
+            content: finalContent,
+            author: "agent",
+            agentId: ctx.agentId ?? null,
+          });
           return textResult(
             `Successfully ${operation === "append" ? "appended to" : "wrote"} notes for task ${actualTaskId}`,
           );
         }
 
+        case "list_document_headings": {
+          const documentType = args.document_type as RevisionedDocument;
+          const documentId =
+            args.document_id || (documentType === "task_notes" ? ctx.taskId : ctx.spaceId);
+          if (!documentId) {
+            return textResult("Error: document_id is required (no default context).");
+          }
+          const headings = await listDocumentHeadings(documentType, documentId);
+          return textResult(
+            headings.length > 0
+              ? headings
+                  .map((h) => `${"  ".repeat(h.level - 1)}- ${h.path.join(" > ")} (line ${h.line})`)
+                  .join("\n")
+              : "The document has no headings.",
+          );
+        }
+
+        case "patch_document": {
+          const documentType = args.document_type as RevisionedDocument;
+          const documentId =
+            args.document_id || (documentType === "task_notes" ? ctx.taskId : ctx.spaceId);
+          if (!documentId) {
+            return textResult("Error: document_id is required (no default context).");
+          }
+          const { op, heading_path = [], content = "", find = "", replace = "", expected_count = 1 } = args;
+          const operation: MarkdownPatchOperation =
+            op === "find_replace"
+              ? { op, find, replace, expected_count }
+              : { op, heading_path, content };
+          const attribution = {
+            author: "agent" as const,
+            agent_id: ctx.agentId ?? null,
+            summary: args.summary ?? null,
+          };
+
+          let result: MarkdownPatchResult | undefined;
+          if (documentType === "space_context") {
+            const outcome = await withAgentResourceLock("space_context", documentId, async (ownerId) => {
+              result = await patchDocument(documentType, documentId, operation, { ownerId, attribution });
+            });
+            if (outcome !== "written" || !result) {
+              return textResult(
+                `Error: space ${documentId} context is being edited elsewhere right now. Try again later.`,
+              );
+            }
+            ctx.onSpaceContextUpdated?.(result.content);
+          } else {
+            const ownerId = documentId === ctx.taskId ? ctx.notesOwnerId : undefined;
+            result = await patchDocument(documentType, documentId, operation, { ownerId, attribution });
+          }
+
+          return textResult(
+            result.diff
+              ? `Patched ${documentType} ${documentId} (+${result.stats.added} -${result.stats.removed}):\n${result.diff}`
+              : `No change to ${documentType} ${documentId}.`,
+          );
+        }
+
+        case "search_workspace": {
+          const results = await searchWorkspace(args.query, {
+            space_id: args.space_id ?? null,
+            status: args.status ?? null,
+            entity_types: args.entity_types ?? null,
+            updated_after: args.updated_after ?? null,
+            updated_before: args.updated_before ?? null,
+            limit: 15,
+          });
+          if (results.length === 0) {
+            return textResult(`No matches found for "${args.query}".`);
+          }
+          const labels: Record<SearchEntityType, (r: SearchResult) => string> = {
+            task: (r) => `Task ${r.entity_id}`,
+            subtask: (r) => `Subtask ${r.entity_id} of task ${r.task_id}`,
+            task_notes: (r) => `Notes of task ${r.entity_id}`,
+            space: (r) => `Space ${r.entity_id} context`,
+          };
+          return textResult(
+            results
+              .map((r) => {
+                const snippet = r.snippet.replace(/<\/?mark>/g, "**").replace(/\n/g, " ");
+                const status = r.status ? ` [${r.status}]` : "";
+                return `- ${labels[r.entity_type](r)}: ${r.title}${status}\n  ${snippet}`;
+              })
+              .join("\n"),
+          );
+        }
+
+        case "save_attachment": {
+          const saveTaskId = args.task_id || ctx.taskId;
+          if (!saveTaskId) {
+            return textResult("Error: task_id is required (no default task context).");
+          }
+          let content: Uint8Array | string;
+          if (typeof args.content_base64 === "string") {
+            try {
+              content = Uint8Array.from(atob(args.content_base64.replace(/\s/g, "")), (c) => c.charCodeAt(0));
+            } catch {
+              return textResult("Error: content_base64 is not valid base64.");
+            }
+          } else if (typeof args.content === "string") {
+            content = args.content;
+          } else {
+            return textResult("Error: content or content_base64 is required.");
+          }
+          const attachment = await addTaskAttachmentBytes(saveTaskId, args.file_name, content, {
+            mimeType: args.mime_type,
+            attribution: {
+              author: "agent",
+              agent_id: ctx.agentId ?? null,
+              summary: args.description ?? null,
+            },
+          });
+          return textResult(
+            `Saved ${attachment.file_name} (attachment ${attachment.id}, ${attachment.size_bytes} bytes) to task ${saveTaskId}`,
+          );
+        }
+
+        case "list_attachments": {
+          const listTaskId = args.task_id || ctx.taskId;
+          if (!listTaskId) {
+            return textResult("Error: task_id is required (no default task context).");
+          }
+          const attachments = await listTaskAttachments(listTaskId);
+          if (attachments.length === 0) {
+            return textResult(`Task ${listTaskId} has no attachments.`);
+          }
+          return textResult(JSON.stringify(
+            attachments.map((a) => ({
+              id: a.id, file_name: a.file_name, mime_type: a.mime_type, size_bytes: a.size_bytes,
+              author: a.author, description: a.description, created_at: a.created_at,
+            })),
+            null, 2,
+          ));
+        }
+
+        case "read_attachment": {
+          const { attachment, text, truncated } = await readTaskAttachment(args.attachment_id);
+          if (text === null) {
+            return textResult(
+              `${attachment.file_name} is a binary file (${attachment.mime_type}, ${attachment.size_bytes} bytes) and cannot be read as text.`,
+            );
+          }
+          return textResult(
+            truncated ? `${text}\n\n[Truncated: ${attachment.file_name} is ${attachment.size_bytes} bytes]` : text,
+          );
+        }
+
         case "check_task_notes_exists": {
           const checkTaskId = args.task_id || ctx.taskId;
           if (!checkTaskId) {
@@ -256,7 +602,35 @@ export function createToolExecutor(ctx: ToolContext) {
           if (!actualSpaceId) {
             return textResult("Error: space_id is required (no default space context).");
           }
-          await updateSpaceContext(actualSpaceId, ctxContent);
+          const readContext = readSpaceContexts.get(actualSpaceId);
+          if (readContext === undefined) {
+            return textResult(
+              `Error: read space ${actualSpaceId} context with read_space_context first, so your update is based on its current version.`,
+            );
+          }
+          const outcome = await withAgentResourceLock(
+            "space_context",
+            actualSpaceId,
+            (ownerId) =>
+              updateSpaceContext(actualSpaceId, ctxContent, ownerId, {
+                author: "agent",
+                agent_id: ctx.agentId ?? null,
+                summary: ctxSummary ?? null,
+              }),
+            { content: readContext, read: () => getSpaceContext(actualSpaceId) },
+          );
+          if (outcome === "locked") {
+            return textResult(
+              `Error: space ${actualSpaceId} context is being edited elsewhere right now. Try again later.`,
+            );
+          }
+          if (outcome === "changed") {
+            readSpaceContexts.delete(actualSpaceId);
+            return textResult(
+              `Error: space ${actualSpaceId} context changed since you read it and was not updated. Read it again and redo your update on top of the new version.`,
+            );
+          }
+          readSpaceContexts.set(actualSpaceId, ctxContent);
           ctx.onSpaceContextUpdated?.(ctxContent);
           return textResult(
             `Successfully updated space context for space ${actualSpaceId}${ctxSummary ? `: ${ctxSummary}` : ""}`,
@@ -347,6 +721,7 @@ export function createToolExecutor(ctx: ToolContext) {
             return textResult("Error: space_id is required (no default space context).");
           }
           const context = await getSpaceContext(readCtxSpaceId);
+          readSpaceContexts.set(readCtxSpaceId, context ?? "");
           return textResult(
             context && context.length > 0
               ? context
@@ -403,7 +778,7 @@ export function createToolExecutor(ctx: ToolContext) {
       }
     } catch (error) {
       return textResult(
-        `Error: ${error instanceof Error ? error.message : "An unexpected error occurred"}`,
+        `Error: ${error instanceof Error ? error.message : String(error || "An unexpected error occurred")}`,
       );
     }
   };
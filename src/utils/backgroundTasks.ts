@@ -56,7 +56,16 @@ export interface BackgroundTaskDefinition<TContext = unknown> {
   getTools: () => any[];
 
   /** Create a tool executor for this task. */
-  createToolExecutor: (ctx: TContext) => (toolName: string, args: any) => Promise<ToolResult>;
+  createToolExecutor: (
+    ctx: TContext,
+    run: BackgroundTaskRunInfo,
+  ) => (toolName: string, args: any) => Promise<ToolResult>;
+}
+
+/** Identifies the run a tool executor works for, so its edits can be attributed. */
+export interface BackgroundTaskRunInfo {
+  runId: number;
+  agentId: number;
 }
 
 export interface BackgroundTaskResult {
@@ -169,7 +178,7 @@ export async function executeBackgroundTask<TContext>(
     const systemPrompt = getSystemPrompt(triggerContext, agent);
     const userMessage = getUserMessage(triggerContext, gathered);
     const tools = getTools();
-    const executeTool = createToolExecutor(triggerContext);
+    const executeTool = createToolExecutor(triggerContext, { runId, agentId: agent.id });
 
     const result = await sendChatTurn(
       {
@@ -10,6 +10,7 @@ import { invoke } from "@tauri-apps/api/core";
 import {
   getSpaceContext,
   updateSpaceContext,
+  withAgentResourceLock,
   getTaskById,
 } from "../api";
 import type { BackgroundTaskDefinition } from "./backgroundTasks";
@@ -153,20 +154,29 @@ Rules:
               type: "string",
               description: "The full markdown content for the space context.",
             },
+            summary: {
+              type: "string",
+              description:
+                "One or two sentences on what changed and why, including anything removed. Kept in the context's revision history.",
+            },
           },
-          required: ["content"],
+          required: ["content", "summary"],
         },
       },
     ];
   },
 
-  createToolExecutor(ctx) {
+  createToolExecutor(ctx, run) {
+    // Space context as the agent last read it; updates are only written over it
+    const readContexts = new Map<number, string>();
+
     return async (toolName: string, args: any): Promise<ToolResult> => {
       const spaceId = args.space_id || ctx.spaceId;
 
       switch (toolName) {
         case "read_space_context": {
           const context = await getSpaceContext(spaceId);
+          readContexts.set(spaceId, context ?? "");
           return textResult(
             context && context.length > 0
               ? context
@@ -183,7 +193,36 @@ Rules:
               `Space context exceeds the 1000-word limit (currently ${wordCount} words). Please condense and retry.`,
             );
           }
-          await updateSpaceContext(spaceId, args.content);
+          const readContext = readContexts.get(spaceId);
+          if (readContext === undefined) {
+            return textResult(
+              `Read space ${spaceId} context with read_space_context first, so your update is based on its current version.`,
+            );
+          }
+          const outcome = await withAgentResourceLock(
+            "space_context",
+            spaceId,
+            (ownerId) =>
+              updateSpaceContext(spaceId, args.content, ownerId, {
+                author: "agent",
+                agent_id: run.agentId,
+                background_task_run_id: run.runId,
+                summary: args.summary ?? null,
+              }),
+            { content: readContext, read: () => getSpaceContext(spaceId) },
+          );
+          if (outcome === "locked") {
+            return textResult(
+              `Space ${spaceId} context is being edited by the user right now; it was not updated.`,
+            );
+          }
+          if (outcome === "changed") {
+            readContexts.delete(spaceId);
+            return textResult(
+              `Space ${spaceId} context changed since you read it; it was not updated. Read it again and redo your update on top of the new version.`,
+            );
+          }
+          readContexts.set(spaceId, args.content);
           return textResult(
             `Successfully updated space context for space ${spaceId} (${wordCount} words).`,
           );
@@ -6,7 +6,7 @@
  * knowledge document — keeping it under 500 words.
  */
 
-import { getSetting, setSetting } from "../api";
+import { getSetting, setSetting, withAgentResourceLock } from "../api";
 import type { BackgroundTaskDefinition } from "./backgroundTasks";
 import type { ToolResult } from "./agentTools";
 
@@ -149,10 +149,14 @@ Rules:
   },
 
   createToolExecutor() {
+    // The document as the agent last read it; updates are only written over it
+    let readKnowledge: string | undefined;
+
     return async (toolName: string, args: any): Promise<ToolResult> => {
       switch (toolName) {
         case "read_user_knowledge": {
           const knowledge = await getSetting(SETTINGS_KEY);
+          readKnowledge = knowledge ?? "";
           return textResult(
             knowledge && knowledge.length > 0
               ? knowledge
@@ -169,7 +173,29 @@ Rules:
               `User knowledge exceeds the 500-word limit (currently ${wordCount} words). Please condense and retry.`,
             );
           }
-          await setSetting(SETTINGS_KEY, args.content);
+          if (readKnowledge === undefined) {
+            return textResult(
+              "Read the document with read_user_knowledge first, so your update is based on its current version.",
+            );
+          }
+          const outcome = await withAgentResourceLock(
+            "setting",
+            SETTINGS_KEY,
+            (ownerId) => setSetting(SETTINGS_KEY, args.content, ownerId),
+            { content: readKnowledge, read: () => getSetting(SETTINGS_KEY) },
+          );
+          if (outcome === "locked") {
+            return textResult(
+              "The user knowledge document is being edited elsewhere right now; it was not updated.",
+            );
+          }
+          if (outcome === "changed") {
+            readKnowledge = undefined;
+            return textResult(
+              "The user knowledge document changed since you read it; it was not updated. Read it again and redo your update on top of the new version.",
+            );
+          }
+          readKnowledge = args.content;
           return textResult(
             `Successfully updated user knowledge document (${wordCount} words).`,
           );